@@ -0,0 +1,255 @@
+//! Optional per-vertex attribute channels: normals, texture coordinates and colors.
+
+use crate::{Mesh, MeshError};
+use nalgebra::{Vector2, Vector3, Vector4};
+
+/// How face normals are weighted when they are accumulated into vertex normals.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NormalWeighting {
+    /// Each face contributes in proportion to its area. This is cheap and
+    /// works well for evenly tessellated meshes.
+    #[default]
+    Area,
+    /// Each face contributes in proportion to the angle of its corner at the
+    /// vertex, which is insensitive to how the surrounding faces are split.
+    Angle,
+}
+
+impl Mesh {
+    /// Recomputes the vertex normals from the current vertex positions.
+    ///
+    /// Call this after the mesh has been deformed to keep shading in sync with
+    /// the geometry. Degenerate triangles are ignored, and vertices without
+    /// any valid triangle get a zero normal.
+    ///
+    /// # Arguments
+    ///
+    /// * `weighting` - How face normals are weighted at each vertex.
+    pub fn compute_normals(&mut self, weighting: NormalWeighting) {
+        let mut normals = self.normals.take().unwrap_or_default();
+        normals.clear();
+        normals.resize(self.vertex_count(), Vector3::zeros());
+
+        for triangle in self.indices.chunks_exact(3) {
+            let corners = [
+                triangle[0] as usize,
+                triangle[1] as usize,
+                triangle[2] as usize,
+            ];
+            let p = corners.map(|i| self.positions[i]);
+            // The cross product has a length of twice the triangle area.
+            let face_normal = (p[1] - p[0]).cross(&(p[2] - p[0]));
+            match weighting {
+                NormalWeighting::Area => {
+                    for &i in &corners {
+                        normals[i] += face_normal;
+                    }
+                }
+                NormalWeighting::Angle => {
+                    let unit = match face_normal.try_normalize(f32::EPSILON) {
+                        Some(unit) => unit,
+                        None => continue,
+                    };
+                    for k in 0..3 {
+                        let e1 = p[(k + 1) % 3] - p[k];
+                        let e2 = p[(k + 2) % 3] - p[k];
+                        normals[corners[k]] += unit * e1.angle(&e2);
+                    }
+                }
+            }
+        }
+
+        for normal in &mut normals {
+            *normal = normal
+                .try_normalize(f32::EPSILON)
+                .unwrap_or_else(Vector3::zeros);
+        }
+        self.normals = Some(normals);
+    }
+
+    /// Sets the vertex normals from a flat list of `x, y, z` components.
+    ///
+    /// # Arguments
+    ///
+    /// * `normals` - Three values per vertex.
+    ///
+    /// # Returns
+    ///
+    /// A `MeshError::AttributeLength` if the length does not match the vertex count.
+    pub fn set_normals_flat(&mut self, normals: &[f32]) -> Result<(), MeshError> {
+        self.check_attribute_len("normals", normals.len(), 3)?;
+        self.normals = Some(
+            normals
+                .chunks_exact(3)
+                .map(|n| Vector3::new(n[0], n[1], n[2]))
+                .collect(),
+        );
+        Ok(())
+    }
+
+    /// Sets the texture coordinates from a flat list of `u, v` components.
+    ///
+    /// # Arguments
+    ///
+    /// * `uvs` - Two values per vertex.
+    ///
+    /// # Returns
+    ///
+    /// A `MeshError::AttributeLength` if the length does not match the vertex count.
+    pub fn set_uvs_flat(&mut self, uvs: &[f32]) -> Result<(), MeshError> {
+        self.check_attribute_len("uvs", uvs.len(), 2)?;
+        self.uvs = Some(
+            uvs.chunks_exact(2)
+                .map(|uv| Vector2::new(uv[0], uv[1]))
+                .collect(),
+        );
+        Ok(())
+    }
+
+    /// Sets the vertex colors from a flat list of `r, g, b, a` components.
+    ///
+    /// # Arguments
+    ///
+    /// * `colors` - Four values per vertex, usually in the range `[0, 1]`.
+    ///
+    /// # Returns
+    ///
+    /// A `MeshError::AttributeLength` if the length does not match the vertex count.
+    pub fn set_colors_flat(&mut self, colors: &[f32]) -> Result<(), MeshError> {
+        self.check_attribute_len("colors", colors.len(), 4)?;
+        self.colors = Some(
+            colors
+                .chunks_exact(4)
+                .map(|c| Vector4::new(c[0], c[1], c[2], c[3]))
+                .collect(),
+        );
+        Ok(())
+    }
+
+    /// Returns a flattened vector of the mesh's vertex normals.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec<f32>` containing the x, y, and z components of each normal in
+    /// sequence, or an empty vector if the mesh has no normals.
+    pub fn get_normals_flat(&self) -> Vec<f32> {
+        self.normals
+            .iter()
+            .flatten()
+            .flat_map(|n| n.iter().cloned())
+            .collect()
+    }
+
+    /// Returns a flattened vector of the mesh's texture coordinates.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec<f32>` containing the u and v components of each vertex in
+    /// sequence, or an empty vector if the mesh has no texture coordinates.
+    pub fn get_uvs_flat(&self) -> Vec<f32> {
+        self.uvs
+            .iter()
+            .flatten()
+            .flat_map(|uv| uv.iter().cloned())
+            .collect()
+    }
+
+    /// Returns a flattened vector of the mesh's vertex colors.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec<f32>` containing the r, g, b and a components of each vertex in
+    /// sequence, or an empty vector if the mesh has no colors.
+    pub fn get_colors_flat(&self) -> Vec<f32> {
+        self.colors
+            .iter()
+            .flatten()
+            .flat_map(|c| c.iter().cloned())
+            .collect()
+    }
+
+    fn check_attribute_len(
+        &self,
+        attribute: &'static str,
+        len: usize,
+        width: usize,
+    ) -> Result<(), MeshError> {
+        let expected = self.vertex_count() * width;
+        if len != expected {
+            return Err(MeshError::AttributeLength {
+                attribute,
+                expected,
+                actual: len,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two right triangles sharing the edge 0-2, folded 90 degrees along the y axis.
+    fn folded_mesh() -> Mesh {
+        let positions = vec![
+            0.0, 0.0, 0.0, // 0
+            1.0, 0.0, 0.0, // 1
+            0.0, 1.0, 0.0, // 2
+            0.0, 0.0, 1.0, // 3
+        ];
+        Mesh::new(&positions, &[0, 1, 2, 0, 2, 3]).unwrap()
+    }
+
+    #[test]
+    fn test_compute_normals_flat_surface() {
+        let mut mesh = Mesh::new(
+            &[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0],
+            &[0, 1, 2, 0, 2, 3],
+        )
+        .unwrap();
+        mesh.compute_normals(NormalWeighting::Area);
+        for normal in mesh.normals.as_ref().unwrap() {
+            assert!((normal - Vector3::z()).norm() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_compute_normals_weightings_differ() {
+        let mut mesh = folded_mesh();
+        mesh.compute_normals(NormalWeighting::Angle);
+        let n = mesh.normals.as_ref().unwrap();
+        // Both faces meet vertex 0 at a right angle, so the angle-weighted
+        // normal is the bisector of +z and +x.
+        let expected = Vector3::new(1.0, 0.0, 1.0).normalize();
+        assert!((n[0] - expected).norm() < 1e-6);
+        // Vertices 1 and 3 only touch one face each.
+        assert!((n[1] - Vector3::z()).norm() < 1e-6);
+        assert!((n[3] - Vector3::x()).norm() < 1e-6);
+
+        // Stretching the second face changes the area weighting but not the angles.
+        mesh.positions[3].z = 4.0;
+        mesh.compute_normals(NormalWeighting::Area);
+        let area = mesh.normals.as_ref().unwrap()[0];
+        mesh.compute_normals(NormalWeighting::Angle);
+        let angle = mesh.normals.as_ref().unwrap()[0];
+        assert!(area.x > angle.x);
+    }
+
+    #[test]
+    fn test_flat_attribute_round_trip() {
+        let mut mesh = folded_mesh();
+        assert!(mesh.get_uvs_flat().is_empty());
+
+        let uvs: Vec<f32> = (0..8).map(|i| i as f32 * 0.1).collect();
+        mesh.set_uvs_flat(&uvs).unwrap();
+        assert_eq!(mesh.get_uvs_flat(), uvs);
+
+        let colors = vec![1.0; 16];
+        mesh.set_colors_flat(&colors).unwrap();
+        assert_eq!(mesh.get_colors_flat(), colors);
+
+        assert!(mesh.set_normals_flat(&[0.0; 6]).is_err());
+        assert!(mesh.normals.is_none());
+    }
+}
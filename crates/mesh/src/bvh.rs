@@ -0,0 +1,588 @@
+//! A bounding volume hierarchy for ray casting and proximity queries.
+
+use crate::geometry::Aabb;
+use crate::Mesh;
+use nalgebra::Vector3;
+use std::collections::BinaryHeap;
+
+/// The maximum number of primitives stored in a leaf node.
+const LEAF_SIZE: usize = 4;
+
+/// The closest intersection of a ray with a mesh.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+    /// The triangle that was hit.
+    pub triangle: usize,
+    /// The distance along the ray, in multiples of the ray direction.
+    pub distance: f32,
+    /// The point that was hit.
+    pub point: Vector3<f32>,
+    /// The barycentric coordinates of the hit point with respect to the
+    /// triangle's three corners.
+    pub barycentric: Vector3<f32>,
+}
+
+/// The point on a mesh surface closest to a query point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SurfacePoint {
+    /// The triangle containing the point.
+    pub triangle: usize,
+    /// The closest point on the surface.
+    pub point: Vector3<f32>,
+    /// The distance from the query point.
+    pub distance: f32,
+}
+
+/// A bounding volume hierarchy over the triangles and vertices of a mesh.
+///
+/// The hierarchy stores only indices and bounding boxes, so queries take
+/// the mesh as an argument. After the vertices move, call [`Bvh::refit`] to
+/// update the bounding boxes; the tree structure is kept, which is much
+/// cheaper than rebuilding and stays efficient as long as the deformation
+/// is moderate. Rebuild with [`Bvh::new`] if the indices change.
+#[derive(Clone, Debug, Default)]
+pub struct Bvh {
+    triangles: Tree,
+    vertices: Tree,
+}
+
+impl Bvh {
+    /// Builds the hierarchy for a mesh.
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh` - The mesh to build the hierarchy for.
+    ///
+    /// # Returns
+    ///
+    /// A new `Bvh` instance.
+    pub fn new(mesh: &Mesh) -> Bvh {
+        Bvh {
+            triangles: Tree::build(triangle_bounds(mesh)),
+            vertices: Tree::build(vertex_bounds(mesh)),
+        }
+    }
+
+    /// Updates the bounding boxes after the vertices of the mesh have moved.
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh` - The mesh the hierarchy was built for, with updated positions.
+    pub fn refit(&mut self, mesh: &Mesh) {
+        self.triangles.refit(&triangle_bounds(mesh));
+        self.vertices.refit(&vertex_bounds(mesh));
+    }
+
+    /// Finds the first triangle hit by a ray. Triangles are hit from either side.
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh` - The mesh the hierarchy was built for.
+    /// * `origin` - The start of the ray.
+    /// * `direction` - The direction of the ray. It does not need to be normalized.
+    ///
+    /// # Returns
+    ///
+    /// The closest hit in front of the origin, or `None` if the ray misses.
+    pub fn raycast(
+        &self,
+        mesh: &Mesh,
+        origin: &Vector3<f32>,
+        direction: &Vector3<f32>,
+    ) -> Option<RayHit> {
+        let inverse = direction.map(|c| 1.0 / c);
+        let mut best: Option<RayHit> = None;
+        let mut stack = Vec::new();
+        self.triangles.push_root(&mut stack);
+        while let Some(node) = stack.pop() {
+            let limit = best.map_or(f32::INFINITY, |hit| hit.distance);
+            let node = &self.triangles.nodes[node];
+            if !ray_hits_aabb(&node.aabb, origin, &inverse, limit) {
+                continue;
+            }
+            match node.kind {
+                NodeKind::Leaf { start, end } => {
+                    for &t in &self.triangles.items[start..end] {
+                        let corners = triangle(mesh, t as usize);
+                        if let Some((distance, u, v)) =
+                            intersect_triangle(&corners, origin, direction)
+                        {
+                            if best.is_none_or(|hit| distance < hit.distance) {
+                                best = Some(RayHit {
+                                    triangle: t as usize,
+                                    distance,
+                                    point: origin + direction * distance,
+                                    barycentric: Vector3::new(1.0 - u - v, u, v),
+                                });
+                            }
+                        }
+                    }
+                }
+                NodeKind::Internal { left, right } => {
+                    stack.push(right);
+                    stack.push(left);
+                }
+            }
+        }
+        best
+    }
+
+    /// Finds the point on the surface closest to a query point.
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh` - The mesh the hierarchy was built for.
+    /// * `point` - The query point.
+    ///
+    /// # Returns
+    ///
+    /// The closest surface point, or `None` if the mesh has no triangles.
+    pub fn closest_point(&self, mesh: &Mesh, point: &Vector3<f32>) -> Option<SurfacePoint> {
+        let mut best: Option<(f32, usize, Vector3<f32>)> = None;
+        let mut stack = Vec::new();
+        self.triangles.push_root(&mut stack);
+        while let Some(node) = stack.pop() {
+            let limit = best.map_or(f32::INFINITY, |(d, _, _)| d);
+            let node = &self.triangles.nodes[node];
+            if node.aabb.distance_squared(point) > limit {
+                continue;
+            }
+            match node.kind {
+                NodeKind::Leaf { start, end } => {
+                    for &t in &self.triangles.items[start..end] {
+                        let closest = closest_point_on_triangle(&triangle(mesh, t as usize), point);
+                        let d = (closest - point).norm_squared();
+                        if best.is_none_or(|(best, _, _)| d < best) {
+                            best = Some((d, t as usize, closest));
+                        }
+                    }
+                }
+                NodeKind::Internal { left, right } => {
+                    let (near, far) = self.triangles.order_by_distance(left, right, point);
+                    stack.push(far);
+                    stack.push(near);
+                }
+            }
+        }
+        best.map(|(d, triangle, point)| SurfacePoint {
+            triangle,
+            point,
+            distance: d.sqrt(),
+        })
+    }
+
+    /// Finds the `k` vertices closest to a point.
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh` - The mesh the hierarchy was built for.
+    /// * `point` - The query point.
+    /// * `k` - The number of vertices to return.
+    ///
+    /// # Returns
+    ///
+    /// Up to `k` pairs of vertex index and distance, nearest first.
+    pub fn k_nearest_vertices(
+        &self,
+        mesh: &Mesh,
+        point: &Vector3<f32>,
+        k: usize,
+    ) -> Vec<(usize, f32)> {
+        // A max-heap of the best candidates so far, keyed on squared distance.
+        let mut heap: BinaryHeap<(OrderedDistance, usize)> = BinaryHeap::new();
+        let mut stack = Vec::new();
+        if k > 0 {
+            self.vertices.push_root(&mut stack);
+        }
+        while let Some(node) = stack.pop() {
+            let limit = if heap.len() < k {
+                f32::INFINITY
+            } else {
+                heap.peek().map_or(f32::INFINITY, |(d, _)| d.0)
+            };
+            let node = &self.vertices.nodes[node];
+            if node.aabb.distance_squared(point) > limit {
+                continue;
+            }
+            match node.kind {
+                NodeKind::Leaf { start, end } => {
+                    for &v in &self.vertices.items[start..end] {
+                        let d = (mesh.positions[v as usize] - point).norm_squared();
+                        if heap.len() < k {
+                            heap.push((OrderedDistance(d), v as usize));
+                        } else if heap.peek().is_some_and(|(worst, _)| d < worst.0) {
+                            heap.pop();
+                            heap.push((OrderedDistance(d), v as usize));
+                        }
+                    }
+                }
+                NodeKind::Internal { left, right } => {
+                    let (near, far) = self.vertices.order_by_distance(left, right, point);
+                    stack.push(far);
+                    stack.push(near);
+                }
+            }
+        }
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|(d, v)| (v, d.0.sqrt()))
+            .collect()
+    }
+
+    /// Finds the vertex closest to a point.
+    ///
+    /// # Returns
+    ///
+    /// The index of the nearest vertex, or `None` if the mesh has no vertices.
+    pub fn nearest_vertex(&self, mesh: &Mesh, point: &Vector3<f32>) -> Option<usize> {
+        self.k_nearest_vertices(mesh, point, 1)
+            .first()
+            .map(|&(v, _)| v)
+    }
+}
+
+impl Mesh {
+    /// Builds a bounding volume hierarchy for the mesh.
+    ///
+    /// # Returns
+    ///
+    /// A new `Bvh` instance.
+    pub fn bvh(&self) -> Bvh {
+        Bvh::new(self)
+    }
+}
+
+/// A squared distance that can be ordered in a `BinaryHeap`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct OrderedDistance(f32);
+
+impl Eq for OrderedDistance {}
+
+impl PartialOrd for OrderedDistance {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OrderedDistance {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum NodeKind {
+    /// The node holds `items[start..end]`.
+    Leaf { start: usize, end: usize },
+    /// The node's children. Both come after the node in `nodes`.
+    Internal { left: usize, right: usize },
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    aabb: Aabb,
+    kind: NodeKind,
+}
+
+/// A binary tree over primitives identified by index.
+#[derive(Clone, Debug, Default)]
+struct Tree {
+    nodes: Vec<Node>,
+    /// Primitive indices, ordered so each leaf covers a contiguous range.
+    items: Vec<u32>,
+}
+
+impl Tree {
+    fn build(bounds: Vec<Aabb>) -> Tree {
+        let mut tree = Tree {
+            nodes: Vec::new(),
+            items: (0..bounds.len() as u32).collect(),
+        };
+        if !bounds.is_empty() {
+            tree.build_node(&bounds, 0, bounds.len());
+        }
+        tree
+    }
+
+    /// Builds the subtree over `items[start..end]` and returns its index.
+    fn build_node(&mut self, bounds: &[Aabb], start: usize, end: usize) -> usize {
+        let items = &mut self.items[start..end];
+        let aabb = union_of(items.iter().map(|&i| &bounds[i as usize]));
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            aabb,
+            kind: NodeKind::Leaf { start, end },
+        });
+        if end - start <= LEAF_SIZE {
+            return index;
+        }
+
+        // Split at the median of the box centers along the longest axis.
+        let centers = items
+            .iter()
+            .map(|&i| Aabb::from_point(bounds[i as usize].center()))
+            .reduce(|a, b| a.union(&b))
+            .expect("nodes are never empty");
+        let axis = centers.size().imax();
+        let middle = items.len() / 2;
+        items.select_nth_unstable_by(middle, |&a, &b| {
+            bounds[a as usize].center()[axis].total_cmp(&bounds[b as usize].center()[axis])
+        });
+        let left = self.build_node(bounds, start, start + middle);
+        let right = self.build_node(bounds, start + middle, end);
+        self.nodes[index].kind = NodeKind::Internal { left, right };
+        index
+    }
+
+    fn refit(&mut self, bounds: &[Aabb]) {
+        // Children always come after their parent, so a reverse pass sees
+        // every child before its parent.
+        for i in (0..self.nodes.len()).rev() {
+            self.nodes[i].aabb = match self.nodes[i].kind {
+                NodeKind::Leaf { start, end } => {
+                    union_of(self.items[start..end].iter().map(|&i| &bounds[i as usize]))
+                }
+                NodeKind::Internal { left, right } => {
+                    self.nodes[left].aabb.union(&self.nodes[right].aabb)
+                }
+            };
+        }
+    }
+
+    fn push_root(&self, stack: &mut Vec<usize>) {
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+    }
+
+    /// Returns the two children ordered by their distance to a point.
+    fn order_by_distance(&self, a: usize, b: usize, point: &Vector3<f32>) -> (usize, usize) {
+        if self.nodes[a].aabb.distance_squared(point) <= self.nodes[b].aabb.distance_squared(point)
+        {
+            (a, b)
+        } else {
+            (b, a)
+        }
+    }
+}
+
+fn union_of<'a>(boxes: impl Iterator<Item = &'a Aabb>) -> Aabb {
+    boxes
+        .copied()
+        .reduce(|a, b| a.union(&b))
+        .expect("nodes are never empty")
+}
+
+fn triangle(mesh: &Mesh, t: usize) -> [Vector3<f32>; 3] {
+    let i = &mesh.indices[t * 3..t * 3 + 3];
+    [
+        mesh.positions[i[0] as usize],
+        mesh.positions[i[1] as usize],
+        mesh.positions[i[2] as usize],
+    ]
+}
+
+fn triangle_bounds(mesh: &Mesh) -> Vec<Aabb> {
+    (0..mesh.indices.len() / 3)
+        .map(|t| {
+            let [a, b, c] = triangle(mesh, t);
+            Aabb::from_point(a).grow(&b).grow(&c)
+        })
+        .collect()
+}
+
+fn vertex_bounds(mesh: &Mesh) -> Vec<Aabb> {
+    mesh.positions
+        .iter()
+        .map(|&p| Aabb::from_point(p))
+        .collect()
+}
+
+/// Tests a ray against a box with the slab method.
+fn ray_hits_aabb(aabb: &Aabb, origin: &Vector3<f32>, inverse: &Vector3<f32>, limit: f32) -> bool {
+    let mut near = 0.0f32;
+    let mut far = limit;
+    for axis in 0..3 {
+        let t1 = (aabb.min[axis] - origin[axis]) * inverse[axis];
+        let t2 = (aabb.max[axis] - origin[axis]) * inverse[axis];
+        // `min`/`max` ignore the NaN produced by a zero direction inside the slab.
+        near = near.max(t1.min(t2));
+        far = far.min(t1.max(t2));
+    }
+    near <= far
+}
+
+/// Intersects a ray with a triangle using the Möller-Trumbore algorithm.
+///
+/// Returns the distance along the ray and the barycentric coordinates of the
+/// second and third corners.
+fn intersect_triangle(
+    corners: &[Vector3<f32>; 3],
+    origin: &Vector3<f32>,
+    direction: &Vector3<f32>,
+) -> Option<(f32, f32, f32)> {
+    let e1 = corners[1] - corners[0];
+    let e2 = corners[2] - corners[0];
+    let p = direction.cross(&e2);
+    let determinant = e1.dot(&p);
+    if determinant.abs() < f32::EPSILON * e1.norm() * e2.norm() * direction.norm() {
+        return None;
+    }
+    let inverse = 1.0 / determinant;
+    let s = origin - corners[0];
+    let u = s.dot(&p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(&e1);
+    let v = direction.dot(&q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = e2.dot(&q) * inverse;
+    (t >= 0.0).then_some((t, u, v))
+}
+
+/// Returns the point of a triangle closest to `p` (Ericson, Real-Time
+/// Collision Detection, section 5.1.5).
+fn closest_point_on_triangle(corners: &[Vector3<f32>; 3], p: &Vector3<f32>) -> Vector3<f32> {
+    let [a, b, c] = *corners;
+    let (ab, ac, ap) = (b - a, c - a, p - a);
+    let (d1, d2) = (ab.dot(&ap), ac.dot(&ap));
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+    let bp = p - b;
+    let (d3, d4) = (ab.dot(&bp), ac.dot(&bp));
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+    let cp = p - c;
+    let (d5, d6) = (ab.dot(&cp), ac.dot(&cp));
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && d4 - d3 >= 0.0 && d5 - d6 >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+    let denominator = 1.0 / (va + vb + vc);
+    a + ab * (vb * denominator) + ac * (vc * denominator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A flat `n` x `n` grid of unit squares in the xy-plane.
+    fn grid(n: usize) -> Mesh {
+        let positions: Vec<f32> = (0..n * n)
+            .flat_map(|v| [(v % n) as f32, (v / n) as f32, 0.0])
+            .collect();
+        let mut indices = Vec::new();
+        for y in 0..n - 1 {
+            for x in 0..n - 1 {
+                let v = (y * n + x) as u32;
+                let n = n as u32;
+                indices.extend_from_slice(&[v, v + 1, v + n + 1, v, v + n + 1, v + n]);
+            }
+        }
+        Mesh::new(&positions, &indices).unwrap()
+    }
+
+    // Finds the first hit by testing every triangle.
+    fn brute_force_raycast(
+        mesh: &Mesh,
+        origin: &Vector3<f32>,
+        direction: &Vector3<f32>,
+    ) -> Option<f32> {
+        (0..mesh.indices.len() / 3)
+            .filter_map(|t| intersect_triangle(&triangle(mesh, t), origin, direction))
+            .map(|(d, _, _)| d)
+            .min_by(|a, b| a.total_cmp(b))
+    }
+
+    #[test]
+    fn test_raycast_hits_grid() {
+        let mesh = grid(10);
+        let bvh = mesh.bvh();
+        let origin = Vector3::new(3.25, 4.5, 5.0);
+        let hit = bvh.raycast(&mesh, &origin, &-Vector3::z()).unwrap();
+        assert!((hit.distance - 5.0).abs() < 1e-6);
+        assert!((hit.point - Vector3::new(3.25, 4.5, 0.0)).norm() < 1e-6);
+        let corners = triangle(&mesh, hit.triangle);
+        let from_barycentric = corners[0] * hit.barycentric.x
+            + corners[1] * hit.barycentric.y
+            + corners[2] * hit.barycentric.z;
+        assert!((from_barycentric - hit.point).norm() < 1e-5);
+
+        // The ray points away from the mesh.
+        assert!(bvh.raycast(&mesh, &origin, &Vector3::z()).is_none());
+        // The ray passes beside the mesh.
+        assert!(bvh
+            .raycast(&mesh, &Vector3::new(20.0, 0.0, 5.0), &-Vector3::z())
+            .is_none());
+    }
+
+    #[test]
+    fn test_raycast_matches_brute_force_after_refit() {
+        let mut mesh = grid(8);
+        let mut bvh = mesh.bvh();
+        for (i, position) in mesh.positions.iter_mut().enumerate() {
+            position.z = ((i * 7) % 5) as f32 * 0.3;
+        }
+        bvh.refit(&mesh);
+        for i in 0..20 {
+            let origin = Vector3::new(0.37 * i as f32, 6.5 - 0.3 * i as f32, 4.0);
+            let direction = Vector3::new(0.05, 0.02, -1.0);
+            let expected = brute_force_raycast(&mesh, &origin, &direction);
+            let actual = bvh
+                .raycast(&mesh, &origin, &direction)
+                .map(|hit| hit.distance);
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_closest_point() {
+        let mesh = grid(5);
+        let bvh = mesh.bvh();
+        let above = bvh
+            .closest_point(&mesh, &Vector3::new(1.5, 2.5, 3.0))
+            .unwrap();
+        assert!((above.point - Vector3::new(1.5, 2.5, 0.0)).norm() < 1e-6);
+        assert!((above.distance - 3.0).abs() < 1e-6);
+
+        let outside = bvh
+            .closest_point(&mesh, &Vector3::new(6.0, 2.0, 0.0))
+            .unwrap();
+        assert!((outside.point - Vector3::new(4.0, 2.0, 0.0)).norm() < 1e-6);
+    }
+
+    #[test]
+    fn test_k_nearest_vertices() {
+        let mesh = grid(6);
+        let bvh = mesh.bvh();
+        let point = Vector3::new(2.1, 3.2, 0.0);
+        let nearest = bvh.k_nearest_vertices(&mesh, &point, 3);
+        assert_eq!(nearest.len(), 3);
+        assert_eq!(nearest[0].0, 3 * 6 + 2);
+        assert!(nearest.windows(2).all(|w| w[0].1 <= w[1].1));
+
+        let mut expected: Vec<(usize, f32)> = (0..mesh.vertex_count())
+            .map(|v| (v, (mesh.positions[v] - point).norm()))
+            .collect();
+        expected.sort_by(|a, b| a.1.total_cmp(&b.1));
+        assert_eq!(nearest, expected[..3].to_vec());
+        assert_eq!(bvh.nearest_vertex(&mesh, &point), Some(20));
+        assert!(bvh.k_nearest_vertices(&mesh, &point, 0).is_empty());
+    }
+}
@@ -0,0 +1,335 @@
+//! Welding coincident vertices and removing unused or degenerate elements.
+
+use crate::Mesh;
+use nalgebra::Vector3;
+use std::collections::HashMap;
+
+/// A welded copy of a mesh together with the mapping back to the original.
+///
+/// Exporters split vertices wherever an attribute such as a texture
+/// coordinate changes, which leaves the triangles on either side of a seam
+/// disconnected. Welding merges those vertices again so the physics mesh is
+/// connected, while `remap` lets results be copied back to the original
+/// (render) vertices, which keep their seams.
+#[derive(Clone, Debug)]
+pub struct WeldedMesh {
+    /// The welded mesh.
+    pub mesh: Mesh,
+    /// For each vertex of the original mesh, the welded vertex it was merged into.
+    pub remap: Vec<u32>,
+}
+
+impl WeldedMesh {
+    /// Returns `true` if no vertices were merged, so the welded vertices are
+    /// the original vertices in the same order and their buffers can be
+    /// used for the original mesh directly.
+    pub fn is_identity(&self) -> bool {
+        self.remap.iter().enumerate().all(|(i, &w)| i == w as usize)
+    }
+
+    /// Expands a per-vertex array of the welded mesh to the original vertices.
+    ///
+    /// # Arguments
+    ///
+    /// * `welded` - One value per welded vertex.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec` with one value per original vertex.
+    pub fn gather<T: Copy>(&self, welded: &[T]) -> Vec<T> {
+        self.remap.iter().map(|&i| welded[i as usize]).collect()
+    }
+
+    /// Returns the welded positions expanded to the original vertices, as a
+    /// flat list of `x, y, z` components.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec<f32>` with the same layout as `Mesh::get_vertex_positions_flat`
+    /// of the original mesh.
+    pub fn render_positions_flat(&self) -> Vec<f32> {
+        self.remap
+            .iter()
+            .flat_map(|&i| self.mesh.positions[i as usize].iter().cloned())
+            .collect()
+    }
+
+    /// Copies the welded positions back to the original mesh.
+    ///
+    /// # Arguments
+    ///
+    /// * `render` - The mesh that was welded. Its vertex count must match `remap`.
+    pub fn copy_positions_to(&self, render: &mut Mesh) {
+        for (v, &i) in self.remap.iter().enumerate() {
+            render.positions[v] = self.mesh.positions[i as usize];
+            render.old_positions[v] = self.mesh.old_positions[i as usize];
+        }
+    }
+}
+
+impl Mesh {
+    /// Merges vertices that lie within `tolerance` of each other.
+    ///
+    /// Vertices are visited in order and each one is merged into the first
+    /// earlier vertex within the tolerance, so the welded vertices keep the
+    /// state and attributes of the first vertex in their group. Triangles
+    /// that collapse because two of their corners were merged are dropped.
+    /// The original mesh is left untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `tolerance` - The maximum distance between merged vertices. Zero
+    ///   only merges vertices with identical positions.
+    ///
+    /// # Returns
+    ///
+    /// A `WeldedMesh` holding the welded mesh and the remap table.
+    pub fn weld_vertices(&self, tolerance: f32) -> WeldedMesh {
+        let tolerance = tolerance.max(0.0);
+        let mut grid: HashMap<[i64; 3], Vec<u32>> = HashMap::new();
+        let mut kept: Vec<usize> = Vec::new();
+        let mut remap = Vec::with_capacity(self.vertex_count());
+
+        for (i, position) in self.positions.iter().enumerate() {
+            let cell = grid_cell(position, tolerance);
+            let existing = neighbor_cells(cell, tolerance).find_map(|neighbor| {
+                grid.get(&neighbor)?.iter().copied().find(|&w| {
+                    let other = self.positions[kept[w as usize]];
+                    (other - position).norm() <= tolerance
+                })
+            });
+            let welded = existing.unwrap_or_else(|| {
+                let w = kept.len() as u32;
+                kept.push(i);
+                grid.entry(cell).or_default().push(w);
+                w
+            });
+            remap.push(welded);
+        }
+
+        let mut indices = Vec::with_capacity(self.indices.len());
+        for triangle in self.indices.chunks_exact(3) {
+            let t = [
+                remap[triangle[0] as usize],
+                remap[triangle[1] as usize],
+                remap[triangle[2] as usize],
+            ];
+            if t[0] != t[1] && t[1] != t[2] && t[2] != t[0] {
+                indices.extend_from_slice(&t);
+            }
+        }
+
+        let mesh = self.with_vertices(&kept, indices);
+        WeldedMesh { mesh, remap }
+    }
+
+    /// Removes vertices that are not used by any triangle.
+    ///
+    /// The remaining vertices keep their relative order, and the attribute
+    /// channels are compacted along with them.
+    ///
+    /// # Returns
+    ///
+    /// For each vertex before the call, its new index, or `None` if it was removed.
+    pub fn remove_unreferenced_vertices(&mut self) -> Vec<Option<u32>> {
+        let mut used = vec![false; self.vertex_count()];
+        for &i in &self.indices {
+            used[i as usize] = true;
+        }
+        let mut kept = Vec::new();
+        let remap: Vec<Option<u32>> = used
+            .iter()
+            .enumerate()
+            .map(|(i, &used)| {
+                used.then(|| {
+                    kept.push(i);
+                    (kept.len() - 1) as u32
+                })
+            })
+            .collect();
+
+        let indices = self
+            .indices
+            .iter()
+            .map(|&i| remap[i as usize].expect("indexed vertices are kept"))
+            .collect();
+        *self = self.with_vertices(&kept, indices);
+        remap
+    }
+
+    /// Removes triangles that repeat a vertex or have zero area.
+    ///
+    /// The vertices are left in place; call
+    /// [`Mesh::remove_unreferenced_vertices`] afterwards to drop any that
+    /// are no longer used.
+    ///
+    /// # Returns
+    ///
+    /// The number of triangles that were removed.
+    pub fn remove_degenerate_triangles(&mut self) -> usize {
+        let before = self.indices.len() / 3;
+        let kept: Vec<u32> = self
+            .indices
+            .chunks_exact(3)
+            .filter(|t| {
+                t[0] != t[1] && t[1] != t[2] && t[2] != t[0] && !self.triangle_has_zero_area(t)
+            })
+            .flatten()
+            .copied()
+            .collect();
+        self.indices = kept;
+        before - self.indices.len() / 3
+    }
+}
+
+/// Returns the grid cell containing a point. With a zero tolerance the cell
+/// is the exact bit pattern of the coordinates.
+fn grid_cell(position: &Vector3<f32>, tolerance: f32) -> [i64; 3] {
+    if tolerance > 0.0 {
+        position.map(|c| (c / tolerance).floor() as i64).into()
+    } else {
+        // Adding zero turns -0.0 into 0.0 so both weld together.
+        position.map(|c| (c + 0.0).to_bits() as i64).into()
+    }
+}
+
+/// Returns the cells that may contain points within `tolerance` of a point in `cell`.
+fn neighbor_cells(cell: [i64; 3], tolerance: f32) -> impl Iterator<Item = [i64; 3]> {
+    let range = if tolerance > 0.0 { -1..=1 } else { 0..=0 };
+    range.clone().flat_map(move |dx| {
+        let range = range.clone();
+        range.clone().flat_map(move |dy| {
+            range
+                .clone()
+                .map(move |dz| [cell[0] + dx, cell[1] + dy, cell[2] + dz])
+        })
+    })
+}
+
+/// Picks the entries of an optional attribute channel at the given vertices.
+pub(crate) fn select<T: Copy>(channel: &Option<Vec<T>>, vertices: &[usize]) -> Option<Vec<T>> {
+    channel.as_ref().map(|values| pick(values, vertices))
+}
+
+/// Returns the values of the given vertices, in order.
+fn pick<T: Copy>(values: &[T], vertices: &[usize]) -> Vec<T> {
+    vertices.iter().map(|&i| values[i]).collect()
+}
+
+impl Mesh {
+    /// Returns a mesh made of the given vertices, in order, with all their
+    /// state and attributes, and new triangles that index into them.
+    pub(crate) fn with_vertices(&self, vertices: &[usize], indices: Vec<u32>) -> Mesh {
+        Mesh {
+            positions: pick(&self.positions, vertices),
+            old_positions: pick(&self.old_positions, vertices),
+            accelerations: pick(&self.accelerations, vertices),
+            inverse_masses: pick(&self.inverse_masses, vertices),
+            indices,
+            normals: select(&self.normals, vertices),
+            uvs: select(&self.uvs, vertices),
+            colors: select(&self.colors, vertices),
+            morph_targets: self
+                .morph_targets
+                .iter()
+                .map(|t| t.select(vertices))
+                .collect(),
+            skin_weights: select(&self.skin_weights, vertices),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Vector2;
+
+    // A square split along the diagonal 0-2, with the second triangle using
+    // its own copies (4 and 5) of the diagonal vertices, as at a UV seam.
+    fn seamed_square() -> Mesh {
+        let positions = vec![
+            0.0, 0.0, 0.0, // 0
+            1.0, 0.0, 0.0, // 1
+            1.0, 1.0, 0.0, // 2
+            0.0, 1.0, 0.0, // 3
+            0.0, 0.0, 0.0, // 4, copy of 0
+            1.0, 1.0, 0.0, // 5, copy of 2
+        ];
+        Mesh::new(&positions, &[0, 1, 2, 4, 5, 3]).unwrap()
+    }
+
+    #[test]
+    fn test_weld_vertices_connects_seam() {
+        let mut mesh = seamed_square();
+        mesh.uvs = Some((0..6).map(|i| Vector2::new(i as f32, 0.0)).collect());
+        assert_eq!(mesh.topology().boundary_loops().len(), 2);
+
+        let welded = mesh.weld_vertices(0.0);
+        assert_eq!(welded.mesh.vertex_count(), 4);
+        assert_eq!(welded.remap, vec![0, 1, 2, 3, 0, 2]);
+        assert!(!welded.is_identity());
+        assert_eq!(welded.mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(welded.mesh.topology().boundary_loops().len(), 1);
+        // Attributes come from the first vertex of each group.
+        assert_eq!(welded.mesh.uvs.as_ref().unwrap()[2], Vector2::new(2.0, 0.0));
+        assert_eq!(
+            welded.render_positions_flat(),
+            mesh.get_vertex_positions_flat()
+        );
+    }
+
+    #[test]
+    fn test_weld_vertices_tolerance() {
+        let mut mesh = seamed_square();
+        mesh.positions[5].x += 0.01;
+        assert_eq!(mesh.weld_vertices(0.0).mesh.vertex_count(), 5);
+        let mut welded = mesh.weld_vertices(0.02);
+        assert_eq!(welded.mesh.vertex_count(), 4);
+
+        // Moving a welded vertex moves every render vertex merged into it.
+        welded.mesh.positions[2].z = 1.0;
+        welded.copy_positions_to(&mut mesh);
+        assert_eq!(mesh.positions[2].z, 1.0);
+        assert_eq!(mesh.positions[5], Vector3::new(1.0, 1.0, 1.0));
+
+        // Welding an edge of a triangle collapses it.
+        let triangle = Mesh::new(&[0.0, 0.0, 0.0, 0.001, 0.0, 0.0, 0.0, 1.0, 0.0], &[0, 1, 2])
+            .unwrap()
+            .weld_vertices(0.01);
+        assert!(triangle.mesh.indices.is_empty());
+        assert!(!triangle.is_identity());
+        let triangle =
+            Mesh::new(&[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0], &[0, 1, 2]).unwrap();
+        assert!(triangle.weld_vertices(0.01).is_identity());
+    }
+
+    #[test]
+    fn test_remove_unreferenced_and_degenerate() {
+        let positions = vec![
+            0.0, 0.0, 0.0, // 0
+            9.0, 9.0, 9.0, // 1, unused
+            1.0, 0.0, 0.0, // 2
+            0.0, 1.0, 0.0, // 3
+            2.0, 0.0, 0.0, // 4, collinear with 0 and 2
+        ];
+        let mut mesh = Mesh::new(&positions, &[0, 2, 3, 0, 2, 4, 2, 2, 3]).unwrap();
+        mesh.colors = Some(
+            (0..5)
+                .map(|i| nalgebra::Vector4::repeat(i as f32))
+                .collect(),
+        );
+        let deltas = (0..5).map(|i| Vector3::repeat(i as f32)).collect();
+        mesh.add_morph_target("offset", deltas).unwrap();
+
+        assert_eq!(mesh.remove_degenerate_triangles(), 2);
+        assert_eq!(mesh.indices, vec![0, 2, 3]);
+
+        let remap = mesh.remove_unreferenced_vertices();
+        assert_eq!(remap, vec![Some(0), None, Some(1), Some(2), None]);
+        assert_eq!(mesh.vertex_count(), 3);
+        assert_eq!(mesh.indices, vec![0, 1, 2]);
+        assert_eq!(mesh.colors.as_ref().unwrap()[1].x, 2.0);
+        assert_eq!(mesh.morph_targets[0].deltas[2], Vector3::repeat(3.0));
+        assert!(mesh.validate().is_valid());
+    }
+}
@@ -0,0 +1,260 @@
+//! Connected components (islands) of a mesh and splitting a mesh into them.
+
+use crate::geometry::Aabb;
+use crate::Mesh;
+
+/// The connected components of a mesh.
+///
+/// Two vertices belong to the same component when a chain of triangles
+/// connects them. A vertex that no triangle uses forms a component of its
+/// own. Components are numbered in the order of their lowest vertex.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Components {
+    /// The component of each vertex.
+    pub vertex_labels: Vec<usize>,
+    /// The component of each triangle.
+    pub triangle_labels: Vec<usize>,
+    /// The number of components.
+    pub count: usize,
+}
+
+impl Components {
+    /// Returns the vertices of a component, in ascending order.
+    pub fn vertices(&self, component: usize) -> Vec<usize> {
+        labelled(&self.vertex_labels, component)
+    }
+
+    /// Returns the triangles of a component, in ascending order.
+    pub fn triangles(&self, component: usize) -> Vec<usize> {
+        labelled(&self.triangle_labels, component)
+    }
+}
+
+/// A summary of one connected component.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Island {
+    /// The number of vertices in the island.
+    pub vertex_count: usize,
+    /// The number of triangles in the island.
+    pub triangle_count: usize,
+    /// The bounding box of the vertices of the island.
+    pub bounds: Aabb,
+    /// The total area of the triangles of the island.
+    pub surface_area: f32,
+}
+
+/// One connected component split off into a mesh of its own.
+#[derive(Clone, Debug)]
+pub struct SubMesh {
+    /// The mesh of the component.
+    pub mesh: Mesh,
+    /// For each vertex of `mesh`, the vertex of the original mesh it came from.
+    pub vertices: Vec<usize>,
+    /// For each triangle of `mesh`, the triangle of the original mesh it came from.
+    pub triangles: Vec<usize>,
+}
+
+impl Mesh {
+    /// Labels the connected components of the mesh.
+    ///
+    /// # Returns
+    ///
+    /// A `Components` holding the component of every vertex and triangle.
+    pub fn connected_components(&self) -> Components {
+        let mut parents: Vec<usize> = (0..self.vertex_count()).collect();
+        for triangle in self.indices.chunks_exact(3) {
+            let a = triangle[0] as usize;
+            union(&mut parents, a, triangle[1] as usize);
+            union(&mut parents, a, triangle[2] as usize);
+        }
+
+        let mut roots = vec![usize::MAX; parents.len()];
+        let mut count = 0;
+        let vertex_labels = (0..parents.len())
+            .map(|v| {
+                let root = find(&mut parents, v);
+                if roots[root] == usize::MAX {
+                    roots[root] = count;
+                    count += 1;
+                }
+                roots[root]
+            })
+            .collect::<Vec<_>>();
+        let triangle_labels = self
+            .indices
+            .chunks_exact(3)
+            .map(|triangle| vertex_labels[triangle[0] as usize])
+            .collect();
+        Components {
+            vertex_labels,
+            triangle_labels,
+            count,
+        }
+    }
+
+    /// Summarizes each connected component of the mesh.
+    ///
+    /// # Arguments
+    ///
+    /// * `components` - The components of this mesh, from
+    ///   [`Mesh::connected_components`].
+    ///
+    /// # Returns
+    ///
+    /// One `Island` per component, in component order.
+    pub fn islands(&self, components: &Components) -> Vec<Island> {
+        let mut islands: Vec<Option<Island>> = vec![None; components.count];
+        for (v, &label) in components.vertex_labels.iter().enumerate() {
+            let position = &self.positions[v];
+            let island = islands[label].get_or_insert(Island {
+                vertex_count: 0,
+                triangle_count: 0,
+                bounds: Aabb::from_point(*position),
+                surface_area: 0.0,
+            });
+            island.vertex_count += 1;
+            island.bounds = island.bounds.grow(position);
+        }
+        let mut islands: Vec<Island> = islands
+            .into_iter()
+            .map(|island| island.expect("every component has a vertex"))
+            .collect();
+        for (corners, &label) in self.triangle_corners().zip(&components.triangle_labels) {
+            let island = &mut islands[label];
+            island.triangle_count += 1;
+            island.surface_area += (corners[1] - corners[0])
+                .cross(&(corners[2] - corners[0]))
+                .norm()
+                * 0.5;
+        }
+        islands
+    }
+
+    /// Splits the mesh into one mesh per connected component.
+    ///
+    /// Each sub-mesh keeps the vertex state and attributes of its vertices,
+    /// and the order of its vertices and triangles in the original mesh.
+    ///
+    /// # Arguments
+    ///
+    /// * `components` - The components of this mesh, from
+    ///   [`Mesh::connected_components`].
+    ///
+    /// # Returns
+    ///
+    /// One `SubMesh` per component, in component order, with the maps back
+    /// to the original vertices and triangles.
+    pub fn split_components(&self, components: &Components) -> Vec<SubMesh> {
+        let mut parts: Vec<(Vec<usize>, Vec<usize>)> = vec![Default::default(); components.count];
+        let mut local = vec![0u32; self.vertex_count()];
+        for (v, &label) in components.vertex_labels.iter().enumerate() {
+            local[v] = parts[label].0.len() as u32;
+            parts[label].0.push(v);
+        }
+        for (t, &label) in components.triangle_labels.iter().enumerate() {
+            parts[label].1.push(t);
+        }
+
+        parts
+            .into_iter()
+            .map(|(vertices, triangles)| {
+                let indices = triangles
+                    .iter()
+                    .flat_map(|&t| &self.indices[t * 3..t * 3 + 3])
+                    .map(|&i| local[i as usize])
+                    .collect();
+                SubMesh {
+                    mesh: self.with_vertices(&vertices, indices),
+                    vertices,
+                    triangles,
+                }
+            })
+            .collect()
+    }
+}
+
+fn labelled(labels: &[usize], component: usize) -> Vec<usize> {
+    labels
+        .iter()
+        .enumerate()
+        .filter(|&(_, &label)| label == component)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Finds the representative of a vertex in a union-find forest, halving
+/// the path on the way.
+fn find(parents: &mut [usize], mut v: usize) -> usize {
+    while parents[v] != v {
+        parents[v] = parents[parents[v]];
+        v = parents[v];
+    }
+    v
+}
+
+fn union(parents: &mut [usize], a: usize, b: usize) {
+    let (a, b) = (find(parents, a), find(parents, b));
+    // Keep the lower vertex as the root, which makes the labelling stable.
+    parents[a.max(b)] = a.min(b);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Vector3;
+
+    // A unit square (vertices 0-3), a stray vertex (4) and a triangle far
+    // away along x (vertices 5-7).
+    fn pieces() -> Mesh {
+        let positions = [
+            0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0, // square
+            9.0, 9.0, 9.0, // stray
+            10.0, 0.0, 0.0, 12.0, 0.0, 0.0, 10.0, 2.0, 0.0, // triangle
+        ];
+        Mesh::new(&positions, &[5, 6, 7, 0, 1, 2, 0, 2, 3]).unwrap()
+    }
+
+    #[test]
+    fn test_connected_components() {
+        let components = pieces().connected_components();
+        assert_eq!(components.count, 3);
+        assert_eq!(components.vertex_labels, vec![0, 0, 0, 0, 1, 2, 2, 2]);
+        assert_eq!(components.triangle_labels, vec![2, 0, 0]);
+        assert_eq!(components.vertices(2), vec![5, 6, 7]);
+        assert_eq!(components.triangles(0), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_islands() {
+        let mesh = pieces();
+        let islands = mesh.islands(&mesh.connected_components());
+        assert_eq!(islands.len(), 3);
+        assert_eq!(islands[0].vertex_count, 4);
+        assert_eq!(islands[0].triangle_count, 2);
+        assert_eq!(islands[0].surface_area, 1.0);
+        assert_eq!(islands[1].triangle_count, 0);
+        assert_eq!(islands[1].bounds.min, Vector3::new(9.0, 9.0, 9.0));
+        assert_eq!(islands[2].surface_area, 2.0);
+        assert_eq!(islands[2].bounds.max, Vector3::new(12.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn test_split_components() {
+        let mut mesh = pieces();
+        mesh.inverse_masses[6] = 0.0;
+        let parts = mesh.split_components(&mesh.connected_components());
+        assert_eq!(parts.len(), 3);
+
+        let triangle = &parts[2];
+        assert_eq!(triangle.vertices, vec![5, 6, 7]);
+        assert_eq!(triangle.triangles, vec![0]);
+        assert_eq!(triangle.mesh.indices, vec![0, 1, 2]);
+        assert_eq!(triangle.mesh.positions[1], mesh.positions[6]);
+        assert_eq!(triangle.mesh.inverse_masses, vec![1.0, 0.0, 1.0]);
+
+        let square = &parts[0];
+        assert_eq!(square.triangles, vec![1, 2]);
+        assert_eq!(square.mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+        assert!(parts[1].mesh.indices.is_empty());
+    }
+}
@@ -0,0 +1,463 @@
+//! Quadric error metric simplification and level-of-detail generation.
+
+use crate::Mesh;
+use nalgebra::{Matrix3, Matrix4, Vector3, Vector4};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+
+/// A simplified mesh together with its correspondence to the original.
+#[derive(Clone, Debug)]
+pub struct Decimation {
+    /// The simplified mesh.
+    pub mesh: Mesh,
+    /// For each vertex of the original mesh, the vertex of the simplified
+    /// mesh it was collapsed into.
+    pub vertex_map: Vec<u32>,
+}
+
+impl Mesh {
+    /// Simplifies the mesh with the quadric error metric of Garland and
+    /// Heckbert until it has at most `target_triangles` triangles.
+    ///
+    /// Edges are collapsed cheapest first, where the cost of a collapse is
+    /// the squared distance of the new vertex to the planes of the original
+    /// triangles around it. Boundary vertices are never moved or removed,
+    /// which also preserves UV seams, since exporters split vertices along
+    /// them and turn the seams into boundaries. Collapses that would flip a
+    /// triangle or make the surface non-manifold are skipped, so the target
+    /// may not be reached.
+    ///
+    /// Surviving vertices keep their texture coordinates, colors, morph
+    /// target offsets and skin weights. Normals are not carried over; call
+    /// `compute_normals` on the result if they are needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `target_triangles` - The triangle budget.
+    ///
+    /// # Returns
+    ///
+    /// A `Decimation` holding the simplified mesh and the vertex map.
+    pub fn decimate(&self, target_triangles: usize) -> Decimation {
+        let mut state = CollapseState::new(self);
+        state.run(target_triangles);
+        state.finish(self)
+    }
+
+    /// Builds a chain of progressively simpler meshes.
+    ///
+    /// Each level is simplified from the previous one, which is much faster
+    /// than starting from the original every time.
+    ///
+    /// # Arguments
+    ///
+    /// * `triangle_budgets` - The triangle budget of each level, from the
+    ///   most to the least detailed.
+    ///
+    /// # Returns
+    ///
+    /// One `Decimation` per budget. Every vertex map refers back to the
+    /// vertices of this mesh.
+    pub fn lod_chain(&self, triangle_budgets: &[usize]) -> Vec<Decimation> {
+        let mut levels: Vec<Decimation> = Vec::with_capacity(triangle_budgets.len());
+        for &budget in triangle_budgets {
+            let level = match levels.last() {
+                Some(previous) => {
+                    let next = previous.mesh.decimate(budget);
+                    let vertex_map = previous
+                        .vertex_map
+                        .iter()
+                        .map(|&v| next.vertex_map[v as usize])
+                        .collect();
+                    Decimation {
+                        mesh: next.mesh,
+                        vertex_map,
+                    }
+                }
+                None => self.decimate(budget),
+            };
+            levels.push(level);
+        }
+        levels
+    }
+}
+
+/// A candidate edge collapse in the priority queue.
+struct Candidate {
+    cost: f64,
+    keep: usize,
+    remove: usize,
+    target: Vector3<f64>,
+    /// The versions of both vertices when the candidate was computed. The
+    /// candidate is stale if either vertex has changed since.
+    versions: (u32, u32),
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    // Reversed, so the `BinaryHeap` pops the cheapest collapse first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+/// The working state of a simplification.
+struct CollapseState {
+    positions: Vec<Vector3<f64>>,
+    quadrics: Vec<Matrix4<f64>>,
+    locked: Vec<bool>,
+    versions: Vec<u32>,
+    /// The vertex each vertex was collapsed into, or itself if it survives.
+    parent: Vec<usize>,
+    faces: Vec<[usize; 3]>,
+    face_alive: Vec<bool>,
+    alive_faces: usize,
+    vertex_faces: Vec<Vec<usize>>,
+    heap: BinaryHeap<Candidate>,
+}
+
+impl CollapseState {
+    fn new(mesh: &Mesh) -> CollapseState {
+        let vertex_count = mesh.vertex_count();
+        let topology = mesh.topology();
+        let positions: Vec<Vector3<f64>> =
+            mesh.positions.iter().map(|p| p.map(f64::from)).collect();
+        let faces: Vec<[usize; 3]> = mesh
+            .indices
+            .chunks_exact(3)
+            .map(|t| [t[0] as usize, t[1] as usize, t[2] as usize])
+            .collect();
+
+        let mut quadrics = vec![Matrix4::zeros(); vertex_count];
+        let mut vertex_faces = vec![Vec::new(); vertex_count];
+        for (f, face) in faces.iter().enumerate() {
+            let quadric = plane_quadric(face.map(|v| positions[v]));
+            for &v in face {
+                quadrics[v] += quadric;
+                vertex_faces[v].push(f);
+            }
+        }
+
+        // Vertices on boundaries and non-manifold edges stay where they are.
+        let locked = (0..vertex_count)
+            .map(|v| {
+                topology.one_ring(v).iter().any(|&n| {
+                    let edge = topology.find_edge(v, n).expect("neighbours share an edge");
+                    topology.is_boundary_edge(edge) || !topology.is_manifold_edge(edge)
+                })
+            })
+            .collect();
+
+        let mut state = CollapseState {
+            locked,
+            positions,
+            quadrics,
+            versions: vec![0; vertex_count],
+            parent: (0..vertex_count).collect(),
+            face_alive: vec![true; faces.len()],
+            alive_faces: faces.len(),
+            faces,
+            vertex_faces,
+            heap: BinaryHeap::new(),
+        };
+        for &[a, b] in topology.edges() {
+            state.push_candidate(a, b);
+        }
+        state
+    }
+
+    /// Collapses edges until the face budget is met or no valid collapse is left.
+    fn run(&mut self, target_faces: usize) {
+        while self.alive_faces > target_faces {
+            let Some(candidate) = self.heap.pop() else {
+                break;
+            };
+            let (keep, remove) = (candidate.keep, candidate.remove);
+            if candidate.versions != (self.versions[keep], self.versions[remove])
+                || self.parent[keep] != keep
+                || self.parent[remove] != remove
+            {
+                continue;
+            }
+            if self.is_valid_collapse(keep, remove, &candidate.target) {
+                self.collapse(keep, remove, candidate.target);
+            }
+        }
+    }
+
+    /// Computes the best collapse of the edge `a`-`b` and queues it.
+    fn push_candidate(&mut self, a: usize, b: usize) {
+        let (keep, remove) = match (self.locked[a], self.locked[b]) {
+            (true, true) => return,
+            (false, true) => (b, a),
+            _ => (a, b),
+        };
+        let quadric = self.quadrics[keep] + self.quadrics[remove];
+        let target = if self.locked[keep] {
+            self.positions[keep]
+        } else {
+            optimal_position(&quadric).unwrap_or_else(|| {
+                let (p, q) = (self.positions[keep], self.positions[remove]);
+                [p, q, (p + q) * 0.5]
+                    .into_iter()
+                    .min_by(|x, y| {
+                        quadric_error(&quadric, x).total_cmp(&quadric_error(&quadric, y))
+                    })
+                    .expect("there are three candidates")
+            })
+        };
+        self.heap.push(Candidate {
+            cost: quadric_error(&quadric, &target),
+            keep,
+            remove,
+            target,
+            versions: (self.versions[keep], self.versions[remove]),
+        });
+    }
+
+    fn neighbors(&self, v: usize) -> HashSet<usize> {
+        self.vertex_faces[v]
+            .iter()
+            .filter(|&&f| self.face_alive[f])
+            .flat_map(|&f| self.faces[f])
+            .filter(|&n| n != v)
+            .collect()
+    }
+
+    /// Checks that collapsing `remove` into `keep` keeps the surface manifold
+    /// and does not flip any triangle.
+    fn is_valid_collapse(&self, keep: usize, remove: usize, target: &Vector3<f64>) -> bool {
+        // Link condition: the only shared neighbours are the opposite
+        // corners of the triangles on the edge.
+        let shared_faces = self.vertex_faces[remove]
+            .iter()
+            .filter(|&&f| self.face_alive[f] && self.faces[f].contains(&keep))
+            .count();
+        let shared_neighbors = self
+            .neighbors(keep)
+            .intersection(&self.neighbors(remove))
+            .count();
+        if shared_neighbors != shared_faces {
+            return false;
+        }
+
+        for (moved, other) in [(keep, remove), (remove, keep)] {
+            for &f in &self.vertex_faces[moved] {
+                let face = self.faces[f];
+                if !self.face_alive[f] || face.contains(&other) {
+                    continue;
+                }
+                let before = face.map(|i| self.positions[i]);
+                let after = face.map(|i| {
+                    if i == moved {
+                        *target
+                    } else {
+                        self.positions[i]
+                    }
+                });
+                let n_before = (before[1] - before[0]).cross(&(before[2] - before[0]));
+                let n_after = (after[1] - after[0]).cross(&(after[2] - after[0]));
+                if n_before.dot(&n_after) <= 0.0 {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    fn collapse(&mut self, keep: usize, remove: usize, target: Vector3<f64>) {
+        let faces = std::mem::take(&mut self.vertex_faces[remove]);
+        for f in faces {
+            if !self.face_alive[f] {
+                continue;
+            }
+            if self.faces[f].contains(&keep) {
+                self.face_alive[f] = false;
+                self.alive_faces -= 1;
+            } else {
+                for corner in &mut self.faces[f] {
+                    if *corner == remove {
+                        *corner = keep;
+                    }
+                }
+                self.vertex_faces[keep].push(f);
+            }
+        }
+        self.vertex_faces[keep].retain(|&f| self.face_alive[f]);
+
+        self.parent[remove] = keep;
+        self.positions[keep] = target;
+        let quadric = self.quadrics[remove];
+        self.quadrics[keep] += quadric;
+        self.versions[keep] += 1;
+        for n in self.neighbors(keep) {
+            self.push_candidate(keep, n);
+        }
+    }
+
+    /// Builds the simplified mesh from the surviving vertices and faces.
+    fn finish(mut self, mesh: &Mesh) -> Decimation {
+        let mut compact = vec![u32::MAX; self.parent.len()];
+        let mut kept = Vec::new();
+        for (v, compact) in compact.iter_mut().enumerate() {
+            if self.parent[v] == v {
+                *compact = kept.len() as u32;
+                kept.push(v);
+            }
+        }
+        let vertex_map = (0..self.parent.len())
+            .map(|v| {
+                let mut root = v;
+                while self.parent[root] != root {
+                    root = self.parent[root];
+                }
+                compact[root]
+            })
+            .collect();
+
+        let indices = std::mem::take(&mut self.faces)
+            .into_iter()
+            .zip(&self.face_alive)
+            .filter(|(_, &alive)| alive)
+            .flat_map(|(face, _)| face.map(|v| compact[v]))
+            .collect();
+        let mut simplified = mesh.with_vertices(&kept, indices);
+        for (i, &v) in kept.iter().enumerate() {
+            simplified.teleport_vertex(i, self.positions[v].map(|c| c as f32));
+        }
+        simplified.normals = None;
+        Decimation {
+            mesh: simplified,
+            vertex_map,
+        }
+    }
+}
+
+/// Returns the quadric measuring the squared distance to a triangle's plane.
+fn plane_quadric(corners: [Vector3<f64>; 3]) -> Matrix4<f64> {
+    let normal = (corners[1] - corners[0]).cross(&(corners[2] - corners[0]));
+    let Some(normal) = normal.try_normalize(f64::EPSILON) else {
+        return Matrix4::zeros();
+    };
+    let plane = Vector4::new(normal.x, normal.y, normal.z, -normal.dot(&corners[0]));
+    plane * plane.transpose()
+}
+
+fn quadric_error(quadric: &Matrix4<f64>, position: &Vector3<f64>) -> f64 {
+    let p = position.push(1.0);
+    (p.transpose() * quadric * p)[0].max(0.0)
+}
+
+/// Returns the position minimising the quadric error, if it is well defined.
+fn optimal_position(quadric: &Matrix4<f64>) -> Option<Vector3<f64>> {
+    let a: Matrix3<f64> = quadric.fixed_view::<3, 3>(0, 0).into();
+    if a.determinant().abs() < 1e-12 {
+        return None;
+    }
+    let b = -quadric.fixed_view::<3, 1>(0, 3);
+    a.try_inverse().map(|inverse| inverse * b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An `n` x `n` grid of unit squares folded by 90 degrees along x = `fold`,
+    // so the part with x > `fold` rises along z.
+    fn folded_grid(n: usize, fold: f32) -> Mesh {
+        let positions: Vec<f32> = (0..n * n)
+            .flat_map(|v| {
+                let (x, y) = ((v % n) as f32, (v / n) as f32);
+                if x > fold {
+                    [fold, y, x - fold]
+                } else {
+                    [x, y, 0.0]
+                }
+            })
+            .collect();
+        let mut indices = Vec::new();
+        for y in 0..n - 1 {
+            for x in 0..n - 1 {
+                let v = (y * n + x) as u32;
+                let n = n as u32;
+                indices.extend_from_slice(&[v, v + 1, v + n + 1, v, v + n + 1, v + n]);
+            }
+        }
+        Mesh::new(&positions, &indices).unwrap()
+    }
+
+    fn area(mesh: &Mesh) -> f32 {
+        mesh.indices
+            .chunks_exact(3)
+            .map(|t| {
+                let p = [0, 1, 2].map(|k| mesh.positions[t[k] as usize]);
+                (p[1] - p[0]).cross(&(p[2] - p[0])).norm() * 0.5
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_decimate_flat_grid_keeps_boundary() {
+        let mesh = folded_grid(6, 10.0);
+        let decimation = mesh.decimate(0);
+        let simplified = &decimation.mesh;
+        assert!(simplified.indices.len() / 3 < 50);
+        assert!(simplified.validate().is_valid());
+        assert!((area(simplified) - 25.0).abs() < 1e-4);
+
+        let boundary = mesh.topology();
+        for (v, &mapped) in decimation.vertex_map.iter().enumerate() {
+            if boundary.is_boundary_vertex(v) {
+                let position = simplified.positions[mapped as usize];
+                assert_eq!(position, mesh.positions[v]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_decimate_preserves_fold() {
+        let mesh = folded_grid(9, 4.0);
+        let decimation = mesh.decimate(40);
+        assert!(decimation.mesh.indices.len() / 3 <= 40);
+        // Every surviving vertex still lies on one of the two planes.
+        for p in &decimation.mesh.positions {
+            assert!(p.z.abs() < 1e-4 || (p.x - 4.0).abs() < 1e-4, "{:?}", p);
+        }
+        assert!((area(&decimation.mesh) - 64.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_lod_chain_maps_to_original() {
+        let mesh = folded_grid(9, 4.0);
+        let levels = mesh.lod_chain(&[80, 40, 20]);
+        assert_eq!(levels.len(), 3);
+        let mut previous = mesh.indices.len() / 3;
+        for level in &levels {
+            let triangles = level.mesh.indices.len() / 3;
+            assert!(triangles <= previous);
+            previous = triangles;
+            assert_eq!(level.vertex_map.len(), mesh.vertex_count());
+            assert!(level
+                .vertex_map
+                .iter()
+                .all(|&v| (v as usize) < level.mesh.vertex_count()));
+        }
+        // Corners are on the boundary and map to themselves at every level.
+        let corner = mesh.positions[80];
+        let last = levels.last().unwrap();
+        assert_eq!(last.mesh.positions[last.vertex_map[80] as usize], corner);
+    }
+}
@@ -0,0 +1,253 @@
+//! Comparison of two meshes with the same topology, such as the same mesh
+//! before and after a simulation.
+
+use crate::bvh::Bvh;
+use crate::{Mesh, MeshError};
+use nalgebra::{Matrix2, Matrix3x2, Vector3};
+
+/// The differences between a reference mesh and a deformed copy of it,
+/// returned by [`Mesh::diff`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeshDiff {
+    /// How far each vertex moved.
+    pub displacements: Vec<f32>,
+    /// The largest displacement of any vertex.
+    pub max_displacement: f32,
+    /// The root mean square displacement of the vertices.
+    pub rms_displacement: f32,
+    /// The largest distance from a vertex of either mesh to the surface of
+    /// the other. Unlike the displacements, this ignores motion along the
+    /// surface, so it measures how much the shape changed.
+    pub hausdorff_distance: f32,
+    /// The principal Green strain of each triangle with the largest
+    /// magnitude: positive where the triangle was stretched and negative
+    /// where it was compressed, with 0 for no change.
+    pub triangle_strain: Vec<f32>,
+    /// The relative change of the area of each triangle, so -0.5 means the
+    /// triangle shrank to half its area.
+    pub area_change: Vec<f32>,
+}
+
+impl MeshDiff {
+    /// Scales the displacements to values between 0 and 1 for display as a
+    /// heatmap.
+    ///
+    /// # Arguments
+    ///
+    /// * `max` - The displacement that maps to 1; larger displacements are
+    ///   clamped. Pass 0 to use `max_displacement`, which makes the largest
+    ///   displacement map to 1.
+    ///
+    /// # Returns
+    ///
+    /// One value per vertex, all 0 if no vertex moved.
+    pub fn heatmap(&self, max: f32) -> Vec<f32> {
+        let max = if max > 0.0 {
+            max
+        } else {
+            self.max_displacement
+        };
+        if max <= 0.0 {
+            return vec![0.0; self.displacements.len()];
+        }
+        self.displacements
+            .iter()
+            .map(|d| (d / max).min(1.0))
+            .collect()
+    }
+}
+
+impl Mesh {
+    /// Compares the mesh with a deformed copy of it.
+    ///
+    /// # Arguments
+    ///
+    /// * `deformed` - A mesh with the same vertices and triangles as this one,
+    ///   in different positions.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `MeshDiff` from this mesh to `deformed`, or
+    /// `MeshError::TopologyMismatch` if the meshes do not have the same
+    /// number of vertices and the same triangles.
+    pub fn diff(&self, deformed: &Mesh) -> Result<MeshDiff, MeshError> {
+        if self.vertex_count() != deformed.vertex_count() || self.indices != deformed.indices {
+            return Err(MeshError::TopologyMismatch);
+        }
+        let displacements: Vec<f32> = self
+            .positions
+            .iter()
+            .zip(&deformed.positions)
+            .map(|(a, b)| (b - a).norm())
+            .collect();
+        let max_displacement = displacements.iter().copied().fold(0.0, f32::max);
+        let rms_displacement = if displacements.is_empty() {
+            0.0
+        } else {
+            (displacements.iter().map(|d| d * d).sum::<f32>() / displacements.len() as f32).sqrt()
+        };
+        let hausdorff_distance =
+            one_sided_hausdorff(self, deformed).max(one_sided_hausdorff(deformed, self));
+
+        let (triangle_strain, area_change) = self
+            .triangle_corners()
+            .zip(deformed.triangle_corners())
+            .map(|(rest, current)| triangle_deformation(&rest, &current))
+            .unzip();
+        Ok(MeshDiff {
+            displacements,
+            max_displacement,
+            rms_displacement,
+            hausdorff_distance,
+            triangle_strain,
+            area_change,
+        })
+    }
+}
+
+/// Returns the largest distance from a vertex of `from` to the surface of
+/// `to`, or to its nearest vertex if `to` has no triangles.
+fn one_sided_hausdorff(from: &Mesh, to: &Mesh) -> f32 {
+    let bvh = Bvh::new(to);
+    from.positions
+        .iter()
+        .map(|p| match bvh.closest_point(to, p) {
+            Some(surface) => surface.distance,
+            None => bvh
+                .k_nearest_vertices(to, p, 1)
+                .first()
+                .map_or(0.0, |&(_, distance)| distance),
+        })
+        .fold(0.0, f32::max)
+}
+
+/// Returns the signed principal strain with the largest magnitude and the
+/// relative area change of a triangle, or zeros if the rest triangle is
+/// degenerate.
+fn triangle_deformation(rest: &[Vector3<f32>; 3], current: &[Vector3<f32>; 3]) -> (f32, f32) {
+    let (e1, e2) = (rest[1] - rest[0], rest[2] - rest[0]);
+    let rest_normal = e1.cross(&e2);
+    let rest_area = rest_normal.norm();
+    if rest_area <= f32::EPSILON {
+        return (0.0, 0.0);
+    }
+    // Express the rest edges in an orthonormal frame of the rest triangle.
+    let u = e1.normalize();
+    let v = rest_normal.cross(&u).normalize();
+    let rest_edges = Matrix2::new(e1.dot(&u), e2.dot(&u), e1.dot(&v), e2.dot(&v));
+    let Some(inverse) = rest_edges.try_inverse() else {
+        return (0.0, 0.0);
+    };
+
+    let (d1, d2) = (current[1] - current[0], current[2] - current[0]);
+    let gradient = Matrix3x2::from_columns(&[d1, d2]) * inverse;
+    let strain = (gradient.transpose() * gradient - Matrix2::identity()) * 0.5;
+    let mean = (strain.m11 + strain.m22) * 0.5;
+    let radius = (((strain.m11 - strain.m22) * 0.5).powi(2) + strain.m12.powi(2)).sqrt();
+    let principal = if mean >= 0.0 {
+        mean + radius
+    } else {
+        mean - radius
+    };
+    let area = d1.cross(&d2).norm();
+    (principal, area / rest_area - 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Rotation3;
+
+    fn grid(n: usize) -> Mesh {
+        let positions: Vec<f32> = (0..n * n)
+            .flat_map(|v| [(v % n) as f32, (v / n) as f32, 0.0])
+            .collect();
+        let mut indices = Vec::new();
+        for y in 0..n as u32 - 1 {
+            for x in 0..n as u32 - 1 {
+                let v = y * n as u32 + x;
+                indices.extend([
+                    v,
+                    v + 1,
+                    v + n as u32 + 1,
+                    v,
+                    v + n as u32 + 1,
+                    v + n as u32,
+                ]);
+            }
+        }
+        Mesh::new(&positions, &indices).unwrap()
+    }
+
+    #[test]
+    fn test_translation_moves_without_strain() {
+        let rest = grid(4);
+        let mut moved = rest.clone();
+        for p in &mut moved.positions {
+            p.z += 1.0;
+        }
+        let diff = rest.diff(&moved).unwrap();
+        assert_eq!(diff.max_displacement, 1.0);
+        assert_eq!(diff.rms_displacement, 1.0);
+        assert!((diff.hausdorff_distance - 1.0).abs() < 1e-6);
+        assert!(diff.triangle_strain.iter().all(|s| s.abs() < 1e-6));
+        assert!(diff.area_change.iter().all(|a| a.abs() < 1e-6));
+        assert_eq!(diff.heatmap(0.0), vec![1.0; 16]);
+        assert_eq!(diff.heatmap(4.0), vec![0.25; 16]);
+    }
+
+    #[test]
+    fn test_stretch_and_compression_strain() {
+        let rest = grid(3);
+        let mut stretched = rest.clone();
+        for p in &mut stretched.positions {
+            p.x *= 1.1;
+        }
+        let diff = rest.diff(&stretched).unwrap();
+        // Green strain along x is (1.1² - 1) / 2.
+        for (&strain, &area) in diff.triangle_strain.iter().zip(&diff.area_change) {
+            assert!((strain - 0.105).abs() < 1e-5);
+            assert!((area - 0.1).abs() < 1e-5);
+        }
+        assert!((diff.max_displacement - 0.2).abs() < 1e-6);
+
+        let mut squashed = rest.clone();
+        for p in &mut squashed.positions {
+            p.y *= 0.5;
+        }
+        let diff = rest.diff(&squashed).unwrap();
+        assert!((diff.triangle_strain[0] + 0.375).abs() < 1e-6);
+        assert!((diff.area_change[0] + 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_hausdorff_ignores_sliding_along_surface() {
+        let positions = [
+            1.0, 0.0, 0.0, 0.0, 1.0, 0.0, -1.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0,
+            -1.0,
+        ];
+        let indices = [
+            0, 1, 4, 1, 2, 4, 2, 3, 4, 3, 0, 4, 1, 0, 5, 2, 1, 5, 3, 2, 5, 0, 3, 5,
+        ];
+        let octahedron = Mesh::new(&positions, &indices).unwrap();
+        let mut turned = octahedron.clone();
+        let rotation = Rotation3::from_axis_angle(&Vector3::z_axis(), std::f32::consts::FRAC_PI_2);
+        for p in &mut turned.positions {
+            *p = rotation * *p;
+        }
+
+        let diff = octahedron.diff(&turned).unwrap();
+        assert!((diff.max_displacement - 2.0f32.sqrt()).abs() < 1e-6);
+        assert!(diff.hausdorff_distance < 1e-6);
+        assert!(diff.triangle_strain.iter().all(|s| s.abs() < 1e-5));
+    }
+
+    #[test]
+    fn test_diff_requires_same_topology() {
+        let rest = grid(3);
+        let mut other = rest.clone();
+        other.indices.swap(0, 1);
+        assert_eq!(rest.diff(&other), Err(MeshError::TopologyMismatch));
+        assert_eq!(rest.diff(&grid(4)), Err(MeshError::TopologyMismatch));
+    }
+}
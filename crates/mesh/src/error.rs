@@ -0,0 +1,96 @@
+//! The error type for constructing and modifying meshes.
+
+use std::fmt;
+
+/// An error describing why mesh data was rejected.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(tag = "kind"))]
+pub enum MeshError {
+    /// The number of position values is not a multiple of three.
+    InvalidPositionsLength {
+        /// The number of values that was supplied.
+        len: usize,
+    },
+    /// The number of indices is not a multiple of three.
+    InvalidIndicesLength {
+        /// The number of indices that was supplied.
+        len: usize,
+    },
+    /// A triangle refers to a vertex that does not exist.
+    IndexOutOfBounds {
+        /// The triangle containing the bad index.
+        triangle: usize,
+        /// The offending index.
+        index: u32,
+        /// The number of vertices in the mesh.
+        vertex_count: usize,
+    },
+    /// A vertex has a NaN or infinite coordinate.
+    NonFiniteCoordinate {
+        /// The offending vertex.
+        vertex: usize,
+    },
+    /// A per-vertex attribute does not have one entry per vertex.
+    AttributeLength {
+        /// The name of the attribute.
+        attribute: &'static str,
+        /// The number of values that were expected.
+        expected: usize,
+        /// The number of values that were supplied.
+        actual: usize,
+    },
+    /// A joint refers to a joint that the skeleton does not have.
+    JointOutOfBounds {
+        /// The offending joint index.
+        joint: usize,
+        /// The number of joints in the skeleton.
+        joint_count: usize,
+    },
+    /// Two meshes that should share their topology do not have the same
+    /// number of vertices and the same triangles.
+    TopologyMismatch,
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshError::InvalidPositionsLength { len } => {
+                write!(f, "Invalid positions length {}: not a multiple of 3", len)
+            }
+            MeshError::InvalidIndicesLength { len } => {
+                write!(f, "Invalid indices length {}: not a multiple of 3", len)
+            }
+            MeshError::IndexOutOfBounds {
+                triangle,
+                index,
+                vertex_count,
+            } => write!(
+                f,
+                "Triangle {} refers to vertex {}, but the mesh has {} vertices",
+                triangle, index, vertex_count
+            ),
+            MeshError::NonFiniteCoordinate { vertex } => {
+                write!(f, "Vertex {} has a non-finite coordinate", vertex)
+            }
+            MeshError::AttributeLength {
+                attribute,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Invalid {} length: expected {} values, got {}",
+                attribute, expected, actual
+            ),
+            MeshError::JointOutOfBounds { joint, joint_count } => write!(
+                f,
+                "Joint {} does not exist, but the skeleton has {} joints",
+                joint, joint_count
+            ),
+            MeshError::TopologyMismatch => {
+                write!(f, "The meshes do not have the same vertices and triangles")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MeshError {}
@@ -0,0 +1,182 @@
+//! Geodesic distances measured along the surface of a mesh.
+
+use crate::Mesh;
+use nalgebra::Vector3;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+impl Mesh {
+    /// Computes the distance along the surface from a set of source vertices
+    /// to every vertex.
+    ///
+    /// Distances are shortest paths along the edges of the mesh (Dijkstra's
+    /// algorithm). They never underestimate the true geodesic distance, and
+    /// overestimate it by a few percent on regular meshes, where paths have
+    /// to zigzag along edges that do not point at the source.
+    ///
+    /// # Arguments
+    ///
+    /// * `sources` - The vertices the distances are measured from.
+    /// * `max_distance` - The distance at which to stop the search. Use
+    ///   `f32::INFINITY` to reach the whole mesh.
+    ///
+    /// # Returns
+    ///
+    /// The distance of each vertex, or `f32::INFINITY` for vertices that are
+    /// farther than `max_distance` or not connected to any source.
+    pub fn geodesic_distances(&self, sources: &[usize], max_distance: f32) -> Vec<f32> {
+        self.shortest_paths(sources.iter().map(|&v| (v, 0.0)), max_distance)
+    }
+
+    /// Computes the distance along the surface from a point on a triangle to
+    /// every vertex.
+    ///
+    /// This is [`Mesh::geodesic_distances`] started from the corners of the
+    /// triangle, each at its straight-line distance from the point, which
+    /// suits points returned by ray casts.
+    ///
+    /// # Arguments
+    ///
+    /// * `triangle` - The triangle containing the point.
+    /// * `point` - The point on the triangle.
+    /// * `max_distance` - The distance at which to stop the search.
+    ///
+    /// # Returns
+    ///
+    /// The distance of each vertex, or `f32::INFINITY` for vertices that are
+    /// farther than `max_distance` or not connected to the triangle.
+    pub fn geodesic_distances_from_point(
+        &self,
+        triangle: usize,
+        point: &Vector3<f32>,
+        max_distance: f32,
+    ) -> Vec<f32> {
+        let corners = &self.indices[triangle * 3..triangle * 3 + 3];
+        let seeds = corners.iter().map(|&v| {
+            let v = v as usize;
+            (v, (self.positions[v] - point).norm())
+        });
+        self.shortest_paths(seeds, max_distance)
+    }
+
+    fn shortest_paths(
+        &self,
+        seeds: impl Iterator<Item = (usize, f32)>,
+        max_distance: f32,
+    ) -> Vec<f32> {
+        let topology = self.topology();
+        let mut distances = vec![f32::INFINITY; self.vertex_count()];
+        let mut queue = BinaryHeap::new();
+        for (vertex, distance) in seeds {
+            if distance <= max_distance && distance < distances[vertex] {
+                distances[vertex] = distance;
+                queue.push(Visit { distance, vertex });
+            }
+        }
+
+        while let Some(Visit { distance, vertex }) = queue.pop() {
+            if distance > distances[vertex] {
+                continue;
+            }
+            let position = self.positions[vertex];
+            for &neighbor in topology.one_ring(vertex) {
+                let candidate = distance + (self.positions[neighbor] - position).norm();
+                if candidate <= max_distance && candidate < distances[neighbor] {
+                    distances[neighbor] = candidate;
+                    queue.push(Visit {
+                        distance: candidate,
+                        vertex: neighbor,
+                    });
+                }
+            }
+        }
+        distances
+    }
+}
+
+/// A vertex waiting in the Dijkstra queue.
+struct Visit {
+    distance: f32,
+    vertex: usize,
+}
+
+impl PartialEq for Visit {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Visit {}
+
+impl PartialOrd for Visit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Visit {
+    // Reversed, so the `BinaryHeap` pops the closest vertex first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.distance.total_cmp(&self.distance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A strip of unit squares along the x-axis, each split along its
+    // diagonal, with vertex 2i at (i, 0) and vertex 2i + 1 at (i, 1).
+    fn strip(squares: usize) -> Mesh {
+        let positions: Vec<f32> = (0..=squares)
+            .flat_map(|i| [i as f32, 0.0, 0.0, i as f32, 1.0, 0.0])
+            .collect();
+        let indices: Vec<u32> = (0..squares as u32)
+            .flat_map(|i| {
+                let v = 2 * i;
+                [v, v + 2, v + 3, v, v + 3, v + 1]
+            })
+            .collect();
+        Mesh::new(&positions, &indices).unwrap()
+    }
+
+    #[test]
+    fn test_distances_follow_edges() {
+        let mesh = strip(3);
+        let distances = mesh.geodesic_distances(&[0], f32::INFINITY);
+        assert_eq!(distances[0], 0.0);
+        assert_eq!(distances[1], 1.0);
+        assert_eq!(distances[6], 3.0);
+        assert!((distances[3] - 2.0f32.sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_max_distance_stops_search() {
+        let mesh = strip(3);
+        let distances = mesh.geodesic_distances(&[0], 1.5);
+        assert_eq!(distances[2], 1.0);
+        assert!(distances[4].is_infinite());
+        assert!(distances[7].is_infinite());
+    }
+
+    #[test]
+    fn test_distances_follow_surface_not_space() {
+        // Two triangles folded over so that their far corners nearly touch.
+        let positions = [
+            0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.1, 1.0, 0.0, 0.1,
+        ];
+        let indices = [0, 1, 2, 0, 4, 3];
+        let mesh = Mesh::new(&positions, &indices).unwrap();
+        let distances = mesh.geodesic_distances(&[2], f32::INFINITY);
+        assert!((distances[3] - 1.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_distances_from_point_start_at_corners() {
+        let mesh = strip(2);
+        let point = Vector3::new(0.75, 0.25, 0.0);
+        let distances = mesh.geodesic_distances_from_point(0, &point, f32::INFINITY);
+        assert!((distances[2] - (0.25f32.powi(2) * 2.0).sqrt()).abs() < 1e-6);
+        assert!((distances[4] - (1.0 + distances[2])).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,359 @@
+//! Geometric measurements of a mesh: bounds, area, volume, centroid and curvature.
+
+use crate::Mesh;
+use nalgebra::Vector3;
+use std::collections::HashMap;
+
+/// An axis-aligned bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    /// The corner with the smallest coordinates.
+    pub min: Vector3<f32>,
+    /// The corner with the largest coordinates.
+    pub max: Vector3<f32>,
+}
+
+impl Aabb {
+    /// Creates the smallest box containing all the given points.
+    ///
+    /// # Returns
+    ///
+    /// The bounding box, or `None` if there are no points.
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a Vector3<f32>>) -> Option<Aabb> {
+        let mut points = points.into_iter();
+        let first = *points.next()?;
+        Some(points.fold(Aabb::from_point(first), |aabb, p| aabb.grow(p)))
+    }
+
+    /// Creates an empty box around a single point.
+    pub fn from_point(point: Vector3<f32>) -> Aabb {
+        Aabb {
+            min: point,
+            max: point,
+        }
+    }
+
+    /// Returns the box enlarged to contain `point`.
+    pub fn grow(&self, point: &Vector3<f32>) -> Aabb {
+        Aabb {
+            min: self.min.inf(point),
+            max: self.max.sup(point),
+        }
+    }
+
+    /// Returns the smallest box containing both boxes.
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: self.min.inf(&other.min),
+            max: self.max.sup(&other.max),
+        }
+    }
+
+    /// Returns the center of the box.
+    pub fn center(&self) -> Vector3<f32> {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Returns the extent of the box along each axis.
+    pub fn size(&self) -> Vector3<f32> {
+        self.max - self.min
+    }
+
+    /// Returns the squared distance from a point to the box, which is zero
+    /// for points inside it.
+    pub fn distance_squared(&self, point: &Vector3<f32>) -> f32 {
+        (point - point.sup(&self.min).inf(&self.max)).norm_squared()
+    }
+
+    /// Returns `true` if the point lies inside or on the box.
+    pub fn contains(&self, point: &Vector3<f32>) -> bool {
+        (0..3).all(|i| self.min[i] <= point[i] && point[i] <= self.max[i])
+    }
+}
+
+impl Mesh {
+    /// Returns the axis-aligned bounding box of all vertices.
+    ///
+    /// # Returns
+    ///
+    /// The bounding box, or `None` if the mesh has no vertices.
+    pub fn bounding_box(&self) -> Option<Aabb> {
+        Aabb::from_points(&self.positions)
+    }
+
+    /// Returns the total area of the triangles.
+    pub fn surface_area(&self) -> f32 {
+        self.triangle_corners()
+            .map(|p| (p[1] - p[0]).cross(&(p[2] - p[0])).norm() * 0.5)
+            .sum()
+    }
+
+    /// Returns the volume enclosed by the triangles.
+    ///
+    /// The result is only meaningful for closed meshes. It is positive when
+    /// the triangles are wound counter-clockwise as seen from outside, and
+    /// negative for the opposite winding.
+    pub fn volume(&self) -> f32 {
+        self.triangle_corners()
+            .map(|p| p[0].dot(&p[1].cross(&p[2])) / 6.0)
+            .sum()
+    }
+
+    /// Returns the area-weighted centroid of the surface.
+    ///
+    /// Unlike the average of the vertex positions, this does not depend on
+    /// how densely each region is tessellated.
+    ///
+    /// # Returns
+    ///
+    /// The centroid, or `None` if the mesh has no triangles with a non-zero area.
+    pub fn centroid(&self) -> Option<Vector3<f32>> {
+        let (weighted, area) =
+            self.triangle_corners()
+                .fold((Vector3::zeros(), 0.0f32), |(weighted, area), p| {
+                    let a = (p[1] - p[0]).cross(&(p[2] - p[0])).norm() * 0.5;
+                    (weighted + (p[0] + p[1] + p[2]) * (a / 3.0), area + a)
+                });
+        (area > f32::EPSILON).then(|| weighted / area)
+    }
+
+    /// Estimates the mean curvature at each vertex.
+    ///
+    /// The estimate uses the cotangent Laplacian, divided by the mixed
+    /// Voronoi area of the vertex (Meyer et al. 2003). It is positive where the surface
+    /// bulges towards the direction the triangles face (a sphere of radius
+    /// `r` with outward-facing triangles has curvature `1 / r`), and zero on
+    /// boundary and isolated vertices.
+    ///
+    /// # Returns
+    ///
+    /// One value per vertex.
+    pub fn mean_curvature(&self) -> Vec<f32> {
+        let n = self.vertex_count();
+        let mut laplacian = vec![Vector3::zeros(); n];
+        let mut normals = vec![Vector3::zeros(); n];
+        for (t, p) in self.indices.chunks_exact(3).zip(self.triangle_corners()) {
+            let face_normal = (p[1] - p[0]).cross(&(p[2] - p[0]));
+            for k in 0..3 {
+                let (i, j) = (t[(k + 1) % 3] as usize, t[(k + 2) % 3] as usize);
+                let cot = cotangent(p[(k + 1) % 3] - p[k], p[(k + 2) % 3] - p[k]);
+                let edge = self.positions[j] - self.positions[i];
+                laplacian[i] += edge * cot;
+                laplacian[j] -= edge * cot;
+                normals[t[k] as usize] += face_normal;
+            }
+        }
+
+        let areas = self.vertex_areas();
+        let boundary = self.boundary_vertices();
+        (0..n)
+            .map(|v| {
+                if boundary[v] || areas[v] <= f32::EPSILON {
+                    return 0.0;
+                }
+                // laplacian = sum of (cot a + cot b) (p_j - p_i) = -4 A H n.
+                let normal = normals[v].try_normalize(f32::EPSILON).unwrap_or_default();
+                -laplacian[v].dot(&normal) / (4.0 * areas[v])
+            })
+            .collect()
+    }
+
+    /// Estimates the Gaussian curvature at each vertex.
+    ///
+    /// The estimate is the angle defect (`2π` minus the sum of the triangle
+    /// angles at the vertex) divided by the mixed Voronoi area of the
+    /// vertex. It is zero on boundary and isolated vertices.
+    ///
+    /// # Returns
+    ///
+    /// One value per vertex.
+    pub fn gaussian_curvature(&self) -> Vec<f32> {
+        let mut angles = vec![0.0f32; self.vertex_count()];
+        for (t, p) in self.indices.chunks_exact(3).zip(self.triangle_corners()) {
+            for k in 0..3 {
+                angles[t[k] as usize] += (p[(k + 1) % 3] - p[k]).angle(&(p[(k + 2) % 3] - p[k]));
+            }
+        }
+
+        let areas = self.vertex_areas();
+        let boundary = self.boundary_vertices();
+        angles
+            .iter()
+            .enumerate()
+            .map(|(v, &angle)| {
+                if boundary[v] || areas[v] <= f32::EPSILON {
+                    0.0
+                } else {
+                    (2.0 * std::f32::consts::PI - angle) / areas[v]
+                }
+            })
+            .collect()
+    }
+
+    /// Iterates over the corner positions of every triangle.
+    pub(crate) fn triangle_corners(&self) -> impl Iterator<Item = [Vector3<f32>; 3]> + '_ {
+        self.indices.chunks_exact(3).map(|t| {
+            [
+                self.positions[t[0] as usize],
+                self.positions[t[1] as usize],
+                self.positions[t[2] as usize],
+            ]
+        })
+    }
+
+    /// Returns the mixed Voronoi area of each vertex: the part of each
+    /// surrounding triangle that is closer to the vertex than to the other
+    /// corners, with obtuse triangles split so the areas stay positive.
+    fn vertex_areas(&self) -> Vec<f32> {
+        let mut areas = vec![0.0; self.vertex_count()];
+        for (t, p) in self.indices.chunks_exact(3).zip(self.triangle_corners()) {
+            let area = (p[1] - p[0]).cross(&(p[2] - p[0])).norm() * 0.5;
+            let obtuse =
+                (0..3).find(|&k| (p[(k + 1) % 3] - p[k]).dot(&(p[(k + 2) % 3] - p[k])) < 0.0);
+            for k in 0..3 {
+                let (q, r) = (p[(k + 1) % 3], p[(k + 2) % 3]);
+                areas[t[k] as usize] += match obtuse {
+                    Some(o) if o == k => area / 2.0,
+                    Some(_) => area / 4.0,
+                    None => {
+                        let cot_q = cotangent(p[k] - q, r - q);
+                        let cot_r = cotangent(p[k] - r, q - r);
+                        ((r - p[k]).norm_squared() * cot_q + (q - p[k]).norm_squared() * cot_r)
+                            / 8.0
+                    }
+                };
+            }
+        }
+        areas
+    }
+
+    /// Flags the vertices on edges used by exactly one triangle.
+    ///
+    /// This counts edges directly rather than building a full `Topology`,
+    /// so the curvature estimates stay cheap enough to run every frame.
+    fn boundary_vertices(&self) -> Vec<bool> {
+        let mut edge_counts: HashMap<(u32, u32), u32> = HashMap::new();
+        for t in self.indices.chunks_exact(3) {
+            for k in 0..3 {
+                let (a, b) = (t[k], t[(k + 1) % 3]);
+                *edge_counts.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+            }
+        }
+        let mut boundary = vec![false; self.vertex_count()];
+        for (&(a, b), &count) in &edge_counts {
+            if count == 1 {
+                boundary[a as usize] = true;
+                boundary[b as usize] = true;
+            }
+        }
+        boundary
+    }
+}
+
+/// Returns the cotangent of the angle between two vectors.
+fn cotangent(u: Vector3<f32>, v: Vector3<f32>) -> f32 {
+    let sine = u.cross(&v).norm();
+    if sine > f32::EPSILON {
+        u.dot(&v) / sine
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A unit cube with outward-facing triangles.
+    fn cube() -> Mesh {
+        let positions: Vec<f32> = (0..8)
+            .flat_map(|v| [(v & 1) as f32, ((v >> 1) & 1) as f32, ((v >> 2) & 1) as f32])
+            .collect();
+        let indices = vec![
+            0, 2, 1, 1, 2, 3, // z = 0
+            4, 5, 6, 5, 7, 6, // z = 1
+            0, 1, 4, 1, 5, 4, // y = 0
+            2, 6, 3, 3, 6, 7, // y = 1
+            0, 4, 2, 2, 4, 6, // x = 0
+            1, 3, 5, 3, 7, 5, // x = 1
+        ];
+        Mesh::new(&positions, &indices).unwrap()
+    }
+
+    // A sphere of radius 2 made by subdividing an octahedron and projecting
+    // the vertices outwards.
+    fn sphere() -> Mesh {
+        let octahedron = Mesh::new(
+            &[
+                1.0, 0.0, 0.0, 0.0, 1.0, 0.0, -1.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, 1.0, 0.0,
+                0.0, -1.0,
+            ],
+            &[
+                0, 1, 4, 1, 2, 4, 2, 3, 4, 3, 0, 4, 1, 0, 5, 2, 1, 5, 3, 2, 5, 0, 3, 5,
+            ],
+        )
+        .unwrap();
+        let mut mesh = octahedron.subdivide_loop(4, &[]).mesh;
+        for position in &mut mesh.positions {
+            *position = position.normalize() * 2.0;
+        }
+        mesh
+    }
+
+    #[test]
+    fn test_cube_measurements() {
+        let mesh = cube();
+        let aabb = mesh.bounding_box().unwrap();
+        assert_eq!(aabb.min, Vector3::zeros());
+        assert_eq!(aabb.max, Vector3::repeat(1.0));
+        assert_eq!(aabb.center(), Vector3::repeat(0.5));
+        assert!(aabb.contains(&Vector3::new(0.5, 1.0, 0.0)));
+        assert!(!aabb.contains(&Vector3::new(0.5, 1.1, 0.0)));
+
+        assert!((mesh.surface_area() - 6.0).abs() < 1e-6);
+        assert!((mesh.volume() - 1.0).abs() < 1e-6);
+        assert!((mesh.centroid().unwrap() - Vector3::repeat(0.5)).norm() < 1e-6);
+
+        let empty = Mesh::new(&[], &[]).unwrap();
+        assert!(empty.bounding_box().is_none());
+        assert!(empty.centroid().is_none());
+    }
+
+    #[test]
+    fn test_sphere_curvature() {
+        let mesh = sphere();
+        let mean = mesh.mean_curvature();
+        let gaussian = mesh.gaussian_curvature();
+        for v in 0..mesh.vertex_count() {
+            assert!((mean[v] - 0.5).abs() < 0.05, "mean {}", mean[v]);
+            assert!(
+                (gaussian[v] - 0.25).abs() < 0.05,
+                "gaussian {}",
+                gaussian[v]
+            );
+        }
+        let expected_volume = 4.0 / 3.0 * std::f32::consts::PI * 8.0;
+        assert!((mesh.volume() - expected_volume).abs() / expected_volume < 0.02);
+    }
+
+    #[test]
+    fn test_flat_curvature_is_zero() {
+        let mesh = Mesh::new(
+            &[
+                0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.2, 0.9, 0.0, 2.0,
+                1.0, 0.0, 0.0, 2.0, 0.0, 1.0, 2.0, 0.0, 2.0, 2.0, 0.0,
+            ],
+            &[
+                0, 1, 4, 0, 4, 3, 1, 2, 5, 1, 5, 4, 3, 4, 7, 3, 7, 6, 4, 5, 8, 4, 8, 7,
+            ],
+        )
+        .unwrap();
+        let mean = mesh.mean_curvature();
+        let gaussian = mesh.gaussian_curvature();
+        assert!(mean[4].abs() < 1e-5);
+        assert!(gaussian[4].abs() < 1e-5);
+        // Boundary vertices are reported as zero.
+        assert_eq!(mean[0], 0.0);
+        assert_eq!(gaussian[0], 0.0);
+    }
+}
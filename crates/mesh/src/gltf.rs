@@ -0,0 +1,616 @@
+//! Loading meshes from glTF 2.0 (`.gltf` and `.glb`) files.
+//!
+//! This module is only available when the `gltf` feature is enabled.
+
+use crate::morph::MorphTarget;
+use crate::skinning::SkinWeights;
+use crate::{Mesh, MeshError};
+use base64::Engine;
+use nalgebra::{Vector2, Vector3, Vector4};
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+/// An error that can occur while loading a glTF file.
+#[derive(Debug)]
+pub enum GltfError {
+    /// The document could not be parsed or failed validation.
+    Gltf(::gltf::Error),
+    /// An external file could not be read.
+    Io(io::Error),
+    /// A buffer refers to data that cannot be resolved, such as an external
+    /// file when loading from a byte slice, or a missing GLB binary chunk.
+    UnresolvedBuffer(String),
+    /// A base64 data URI could not be decoded.
+    InvalidDataUri(String),
+    /// A triangle primitive has no `POSITION` attribute.
+    MissingPositions {
+        /// The index of the mesh in the document.
+        mesh: usize,
+        /// The index of the primitive within the mesh.
+        primitive: usize,
+    },
+    /// The merged primitives do not describe a valid mesh, for example
+    /// because an index is out of range.
+    Mesh(MeshError),
+    /// Skinned primitives that are merged into one mesh use different
+    /// skins, so their joint indices refer to different joints.
+    MixedSkins {
+        /// The skin of the first skinned primitive.
+        first: usize,
+        /// The other skin.
+        second: usize,
+    },
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GltfError::Gltf(err) => write!(f, "invalid glTF document: {}", err),
+            GltfError::Io(err) => write!(f, "I/O error while reading glTF: {}", err),
+            GltfError::UnresolvedBuffer(uri) => write!(f, "cannot resolve buffer `{}`", uri),
+            GltfError::InvalidDataUri(err) => write!(f, "invalid data URI: {}", err),
+            GltfError::MissingPositions { mesh, primitive } => write!(
+                f,
+                "primitive {} of mesh {} has no POSITION attribute",
+                primitive, mesh
+            ),
+            GltfError::Mesh(err) => write!(f, "invalid glTF mesh: {}", err),
+            GltfError::MixedSkins { first, second } => write!(
+                f,
+                "cannot merge primitives that use skins {} and {}",
+                first, second
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GltfError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GltfError::Gltf(err) => Some(err),
+            GltfError::Io(err) => Some(err),
+            GltfError::Mesh(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<::gltf::Error> for GltfError {
+    fn from(err: ::gltf::Error) -> Self {
+        GltfError::Gltf(err)
+    }
+}
+
+impl From<io::Error> for GltfError {
+    fn from(err: io::Error) -> Self {
+        GltfError::Io(err)
+    }
+}
+
+impl From<MeshError> for GltfError {
+    fn from(err: MeshError) -> Self {
+        GltfError::Mesh(err)
+    }
+}
+
+/// The per-vertex displacements of a single morph target.
+#[derive(Clone, Debug, Default)]
+pub struct GltfMorphTarget {
+    /// The position displacement of each vertex of the primitive.
+    pub position_deltas: Vec<Vector3<f32>>,
+    /// The normal displacement of each vertex, if the target provides one.
+    pub normal_deltas: Option<Vec<Vector3<f32>>>,
+}
+
+/// The vertex data of one triangle primitive.
+#[derive(Clone, Debug, Default)]
+pub struct GltfPrimitive {
+    /// The vertex positions.
+    pub positions: Vec<Vector3<f32>>,
+    /// The vertex normals, if present.
+    pub normals: Option<Vec<Vector3<f32>>>,
+    /// The first set of texture coordinates, if present.
+    pub uvs: Option<Vec<Vector2<f32>>>,
+    /// The first set of vertex colors as RGBA, if present.
+    pub colors: Option<Vec<Vector4<f32>>>,
+    /// The first set of joint influences, if present. Joint indices refer to
+    /// the joints of the skin the mesh is used with.
+    pub skin_weights: Option<Vec<SkinWeights>>,
+    /// The triangle indices. Strips and fans are converted to lists and
+    /// non-indexed primitives get sequential indices.
+    pub indices: Vec<u32>,
+    /// The morph targets of the primitive, in document order.
+    pub morph_targets: Vec<GltfMorphTarget>,
+}
+
+/// A glTF mesh and its triangle primitives.
+#[derive(Clone, Debug, Default)]
+pub struct GltfMesh {
+    /// The name of the mesh, if it has one.
+    pub name: Option<String>,
+    /// The triangle primitives of the mesh. Point and line primitives are skipped.
+    pub primitives: Vec<GltfPrimitive>,
+    /// The morph target names from the `targetNames` extra, if present.
+    pub target_names: Vec<String>,
+    /// The default morph target weights of the mesh.
+    pub weights: Vec<f32>,
+    /// The skins of the nodes that use the mesh, in ascending order. Joint
+    /// indices in the skin weights refer to the joints of these skins.
+    pub skins: Vec<usize>,
+}
+
+/// The meshes contained in a glTF document.
+///
+/// Vertex data is kept in the local space of each mesh; node transforms are
+/// not applied, which matches what Three.js exposes through
+/// `geometry.attributes`.
+#[derive(Clone, Debug, Default)]
+pub struct GltfModel {
+    /// The meshes of the document, in document order.
+    pub meshes: Vec<GltfMesh>,
+}
+
+impl GltfModel {
+    /// Loads a model from the bytes of a `.gltf` or `.glb` file.
+    ///
+    /// Buffers must either be embedded as base64 data URIs or stored in the
+    /// GLB binary chunk, since there is no directory to resolve external files
+    /// against.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The contents of the glTF file.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the loaded `GltfModel`, or a `GltfError`.
+    pub fn from_slice(bytes: &[u8]) -> Result<GltfModel, GltfError> {
+        let gltf = ::gltf::Gltf::from_slice(bytes)?;
+        let buffers = resolve_buffers(&gltf, None)?;
+        Self::from_document(&gltf.document, &buffers)
+    }
+
+    /// Loads a model from a `.gltf` or `.glb` file on disk.
+    ///
+    /// External buffers are resolved relative to the directory of the file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the glTF file.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the loaded `GltfModel`, or a `GltfError`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<GltfModel, GltfError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)?;
+        let gltf = ::gltf::Gltf::from_slice(&bytes)?;
+        let buffers = resolve_buffers(&gltf, path.parent())?;
+        Self::from_document(&gltf.document, &buffers)
+    }
+
+    /// Merges the primitives of every mesh into a single `Mesh`.
+    ///
+    /// Primitives are appended in document order, with their indices offset
+    /// by the number of vertices that precede them. Normals, texture
+    /// coordinates, colors and skin weights are carried over when every
+    /// primitive has them. Joint indices are carried over unchanged, so all
+    /// skinned primitives must belong to meshes that use the same skin.
+    ///
+    /// Morph targets are matched across meshes by their name from
+    /// `targetNames`, falling back to `target<index>` for unnamed targets,
+    /// and vertices of primitives without a target get a zero offset.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a new `Mesh` with all triangle primitives of the
+    /// model, or a `GltfError` if an index or position is invalid or the
+    /// skinned primitives use different skins.
+    pub fn to_mesh(&self) -> Result<Mesh, GltfError> {
+        self.check_skins()?;
+        let primitives: Vec<&GltfPrimitive> =
+            self.meshes.iter().flat_map(|m| &m.primitives).collect();
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        for primitive in &primitives {
+            let offset = (positions.len() / 3) as u32;
+            positions.extend(primitive.positions.iter().flat_map(|p| [p.x, p.y, p.z]));
+            indices.extend(primitive.indices.iter().map(|i| i + offset));
+        }
+        let mut mesh = Mesh::new(&positions, &indices)?;
+        mesh.normals = merge_attribute(&primitives, |p| p.normals.as_deref());
+        mesh.uvs = merge_attribute(&primitives, |p| p.uvs.as_deref());
+        mesh.colors = merge_attribute(&primitives, |p| p.colors.as_deref());
+        mesh.skin_weights = merge_attribute(&primitives, |p| p.skin_weights.as_deref());
+        mesh.morph_targets = self.merge_morph_targets(mesh.vertex_count());
+        Ok(mesh)
+    }
+
+    /// Checks that every mesh with skinned primitives uses the same skin.
+    fn check_skins(&self) -> Result<(), GltfError> {
+        let skinned = self.meshes.iter().filter(|mesh| {
+            mesh.primitives
+                .iter()
+                .any(|primitive| primitive.skin_weights.is_some())
+        });
+        let mut skins = skinned.flat_map(|mesh| &mesh.skins);
+        if let Some(&first) = skins.next() {
+            if let Some(&second) = skins.find(|&&skin| skin != first) {
+                return Err(GltfError::MixedSkins { first, second });
+            }
+        }
+        Ok(())
+    }
+
+    fn merge_morph_targets(&self, vertex_count: usize) -> Vec<MorphTarget> {
+        let mut targets: Vec<MorphTarget> = Vec::new();
+        let mut offset = 0;
+        for gltf_mesh in &self.meshes {
+            for primitive in &gltf_mesh.primitives {
+                for (i, target) in primitive.morph_targets.iter().enumerate() {
+                    let name = match gltf_mesh.target_names.get(i) {
+                        Some(name) if !name.is_empty() => name.clone(),
+                        _ => format!("target{}", i),
+                    };
+                    let index = match targets.iter().position(|t| t.name == name) {
+                        Some(index) => index,
+                        None => {
+                            targets.push(MorphTarget {
+                                name,
+                                deltas: vec![Vector3::zeros(); vertex_count],
+                            });
+                            targets.len() - 1
+                        }
+                    };
+                    let deltas = &mut targets[index].deltas[offset..];
+                    let sources = target
+                        .position_deltas
+                        .iter()
+                        .take(primitive.positions.len());
+                    for (delta, source) in deltas.iter_mut().zip(sources) {
+                        *delta = *source;
+                    }
+                }
+                offset += primitive.positions.len();
+            }
+        }
+        targets
+    }
+
+    fn from_document(
+        document: &::gltf::Document,
+        buffers: &[Vec<u8>],
+    ) -> Result<GltfModel, GltfError> {
+        let mut skins = vec![Vec::new(); document.meshes().len()];
+        for node in document.nodes() {
+            if let (Some(mesh), Some(skin)) = (node.mesh(), node.skin()) {
+                skins[mesh.index()].push(skin.index());
+            }
+        }
+        let meshes = document
+            .meshes()
+            .zip(skins)
+            .map(|(mesh, mut skins)| {
+                skins.sort_unstable();
+                skins.dedup();
+                Ok(GltfMesh {
+                    name: mesh.name().map(str::to_string),
+                    primitives: mesh
+                        .primitives()
+                        .filter_map(|primitive| {
+                            read_primitive(mesh.index(), &primitive, buffers).transpose()
+                        })
+                        .collect::<Result<_, GltfError>>()?,
+                    target_names: target_names(&mesh),
+                    weights: mesh.weights().map(<[f32]>::to_vec).unwrap_or_default(),
+                    skins,
+                })
+            })
+            .collect::<Result<_, GltfError>>()?;
+        Ok(GltfModel { meshes })
+    }
+}
+
+impl Mesh {
+    /// Loads a `Mesh` from the bytes of a `.gltf` or `.glb` file.
+    ///
+    /// This is a shorthand for [`GltfModel::from_slice`] followed by
+    /// [`GltfModel::to_mesh`].
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The contents of the glTF file.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the new `Mesh`, or a `GltfError`.
+    pub fn from_gltf_slice(bytes: &[u8]) -> Result<Mesh, GltfError> {
+        GltfModel::from_slice(bytes)?.to_mesh()
+    }
+}
+
+/// Concatenates an attribute across primitives, or returns `None` if any
+/// primitive lacks it.
+fn merge_attribute<T: Clone>(
+    primitives: &[&GltfPrimitive],
+    attribute: impl Fn(&GltfPrimitive) -> Option<&[T]>,
+) -> Option<Vec<T>> {
+    if primitives.is_empty() {
+        return None;
+    }
+    let mut merged = Vec::new();
+    for primitive in primitives {
+        merged.extend_from_slice(attribute(primitive)?);
+    }
+    Some(merged)
+}
+
+fn resolve_buffers(gltf: &::gltf::Gltf, base: Option<&Path>) -> Result<Vec<Vec<u8>>, GltfError> {
+    gltf.buffers()
+        .map(|buffer| {
+            let mut data = match buffer.source() {
+                ::gltf::buffer::Source::Bin => gltf
+                    .blob
+                    .clone()
+                    .ok_or_else(|| GltfError::UnresolvedBuffer("GLB binary chunk".to_string()))?,
+                ::gltf::buffer::Source::Uri(uri) => read_uri(uri, base)?,
+            };
+            if data.len() < buffer.length() {
+                return Err(GltfError::UnresolvedBuffer(format!(
+                    "buffer {} is shorter than its declared length",
+                    buffer.index()
+                )));
+            }
+            // Buffers may be padded to a multiple of four bytes.
+            data.truncate(buffer.length());
+            Ok(data)
+        })
+        .collect()
+}
+
+fn read_uri(uri: &str, base: Option<&Path>) -> Result<Vec<u8>, GltfError> {
+    if let Some(rest) = uri.strip_prefix("data:") {
+        let (_, payload) = rest.split_once(";base64,").ok_or_else(|| {
+            GltfError::InvalidDataUri("only base64 data URIs are supported".into())
+        })?;
+        return base64::engine::general_purpose::STANDARD
+            .decode(payload)
+            .map_err(|err| GltfError::InvalidDataUri(err.to_string()));
+    }
+    match base {
+        Some(base) => Ok(std::fs::read(base.join(uri))?),
+        None => Err(GltfError::UnresolvedBuffer(uri.to_string())),
+    }
+}
+
+/// Reads a triangle primitive, returning `None` for point and line primitives.
+fn read_primitive(
+    mesh: usize,
+    primitive: &::gltf::Primitive,
+    buffers: &[Vec<u8>],
+) -> Result<Option<GltfPrimitive>, GltfError> {
+    use ::gltf::mesh::Mode;
+
+    let mode = primitive.mode();
+    if !matches!(
+        mode,
+        Mode::Triangles | Mode::TriangleStrip | Mode::TriangleFan
+    ) {
+        return Ok(None);
+    }
+
+    let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice));
+    let positions: Vec<Vector3<f32>> = reader
+        .read_positions()
+        .ok_or(GltfError::MissingPositions {
+            mesh,
+            primitive: primitive.index(),
+        })?
+        .map(Vector3::from)
+        .collect();
+    let vertex_count = positions.len();
+    let raw_indices: Vec<u32> = match reader.read_indices() {
+        Some(indices) => indices.into_u32().collect(),
+        None => (0..vertex_count as u32).collect(),
+    };
+    let indices = match mode {
+        Mode::TriangleStrip => strip_to_list(&raw_indices),
+        Mode::TriangleFan => fan_to_list(&raw_indices),
+        _ => raw_indices,
+    };
+
+    let normals = reader
+        .read_normals()
+        .map(|normals| normals.map(Vector3::from).collect());
+    let uvs = reader
+        .read_tex_coords(0)
+        .map(|uvs| uvs.into_f32().map(Vector2::from).collect());
+    let colors = reader
+        .read_colors(0)
+        .map(|colors| colors.into_rgba_f32().map(Vector4::from).collect());
+    let skin_weights =
+        reader
+            .read_joints(0)
+            .zip(reader.read_weights(0))
+            .map(|(joints, weights)| {
+                joints
+                    .into_u16()
+                    .zip(weights.into_f32())
+                    .map(|(joints, weights)| SkinWeights { joints, weights })
+                    .collect()
+            });
+    let morph_targets = reader
+        .read_morph_targets()
+        .map(|(positions, normals, _)| GltfMorphTarget {
+            position_deltas: positions
+                .map(|deltas| deltas.map(Vector3::from).collect())
+                .unwrap_or_else(|| vec![Vector3::zeros(); vertex_count]),
+            normal_deltas: normals.map(|deltas| deltas.map(Vector3::from).collect()),
+        })
+        .collect();
+
+    Ok(Some(GltfPrimitive {
+        positions,
+        normals,
+        uvs,
+        colors,
+        skin_weights,
+        indices,
+        morph_targets,
+    }))
+}
+
+fn target_names(mesh: &::gltf::Mesh) -> Vec<String> {
+    let value = mesh
+        .extras()
+        .as_ref()
+        .and_then(|raw| ::gltf::json::deserialize::from_str::<::gltf::json::Value>(raw.get()).ok());
+    value
+        .as_ref()
+        .and_then(|value| value.get("targetNames"))
+        .and_then(|names| names.as_array())
+        .map(|names| {
+            names
+                .iter()
+                .map(|name| name.as_str().unwrap_or_default().to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn strip_to_list(strip: &[u32]) -> Vec<u32> {
+    let mut list = Vec::with_capacity(strip.len().saturating_sub(2) * 3);
+    for i in 2..strip.len() {
+        // Every other triangle of a strip has reversed winding.
+        if i % 2 == 0 {
+            list.extend([strip[i - 2], strip[i - 1], strip[i]]);
+        } else {
+            list.extend([strip[i - 1], strip[i - 2], strip[i]]);
+        }
+    }
+    list
+}
+
+fn fan_to_list(fan: &[u32]) -> Vec<u32> {
+    let mut list = Vec::with_capacity(fan.len().saturating_sub(2) * 3);
+    for i in 2..fan.len() {
+        list.extend([fan[0], fan[i - 1], fan[i]]);
+    }
+    list
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FACE_GLTF: &[u8] = include_bytes!("../../../static/assets/face.gltf");
+
+    #[test]
+    fn test_from_slice_reads_embedded_buffers() {
+        let model = GltfModel::from_slice(FACE_GLTF).unwrap();
+        assert_eq!(model.meshes.len(), 1);
+        let primitive = &model.meshes[0].primitives[0];
+        assert_eq!(primitive.positions.len(), 3);
+        assert_eq!(primitive.indices, vec![0, 1, 2]);
+        assert!(primitive.normals.is_none());
+        assert!(primitive.uvs.is_none());
+    }
+
+    #[test]
+    fn test_to_mesh_keeps_shared_attributes() {
+        let mut model = GltfModel::from_slice(FACE_GLTF).unwrap();
+        let mut primitive = model.meshes[0].primitives[0].clone();
+        primitive.uvs = Some(vec![Vector2::zeros(); 3]);
+        primitive.normals = Some(vec![Vector3::z(); 3]);
+        model.meshes[0].primitives[0] = primitive.clone();
+        primitive.uvs = None;
+        model.meshes[0].primitives.push(primitive);
+
+        let mesh = model.to_mesh().unwrap();
+        assert_eq!(mesh.normals.as_ref().map(Vec::len), Some(6));
+        // The second primitive has no UVs, so the merged mesh has none.
+        assert!(mesh.uvs.is_none());
+    }
+
+    #[test]
+    fn test_to_mesh_merges_primitives() {
+        let mut model = GltfModel::from_slice(FACE_GLTF).unwrap();
+        let primitive = model.meshes[0].primitives[0].clone();
+        model.meshes[0].primitives.push(primitive);
+
+        let mesh = model.to_mesh().unwrap();
+        assert_eq!(mesh.vertex_count(), 6);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_to_mesh_matches_morph_targets_by_name() {
+        let mut model = GltfModel::from_slice(FACE_GLTF).unwrap();
+        let mut second = model.meshes[0].clone();
+        let mut primitive = model.meshes[0].primitives[0].clone();
+        primitive.morph_targets = vec![GltfMorphTarget {
+            position_deltas: vec![Vector3::x(); 3],
+            normal_deltas: None,
+        }];
+        model.meshes[0].primitives[0] = primitive.clone();
+        model.meshes[0].target_names = vec!["smile".to_string()];
+        primitive.morph_targets.push(GltfMorphTarget {
+            position_deltas: vec![Vector3::y(); 3],
+            normal_deltas: None,
+        });
+        second.primitives[0] = primitive;
+        second.target_names = vec!["smile".to_string()];
+        model.meshes.push(second);
+
+        let mesh = model.to_mesh().unwrap();
+        let names: Vec<&str> = mesh.morph_targets.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["smile", "target1"]);
+        assert_eq!(mesh.morph_targets[0].deltas, vec![Vector3::x(); 6]);
+        assert_eq!(mesh.morph_targets[1].deltas[2], Vector3::zeros());
+        assert_eq!(mesh.morph_targets[1].deltas[3], Vector3::y());
+    }
+
+    #[test]
+    fn test_to_mesh_rejects_mixed_skins() {
+        let mut model = GltfModel::from_slice(FACE_GLTF).unwrap();
+        model.meshes[0].primitives[0].skin_weights = Some(vec![SkinWeights::single(0); 3]);
+        model.meshes[0].skins = vec![0];
+        let mut second = model.meshes[0].clone();
+        second.skins = vec![1];
+        model.meshes.push(second.clone());
+        assert!(matches!(
+            model.to_mesh(),
+            Err(GltfError::MixedSkins {
+                first: 0,
+                second: 1
+            })
+        ));
+
+        // Meshes without skin weights do not constrain the skin.
+        model.meshes[1].primitives[0].skin_weights = None;
+        assert!(model.to_mesh().is_ok());
+
+        // Meshes that share a skin merge.
+        second.skins = vec![0];
+        model.meshes[1] = second;
+        let mesh = model.to_mesh().unwrap();
+        assert_eq!(mesh.skin_weights.map(|w| w.len()), Some(6));
+    }
+
+    #[test]
+    fn test_from_slice_rejects_external_buffers() {
+        let json = br#"{
+            "asset": { "version": "2.0" },
+            "buffers": [ { "uri": "mesh.bin", "byteLength": 4 } ]
+        }"#;
+        let result = GltfModel::from_slice(json);
+        assert!(matches!(result, Err(GltfError::UnresolvedBuffer(_))));
+    }
+
+    #[test]
+    fn test_strip_and_fan_conversion() {
+        assert_eq!(strip_to_list(&[0, 1, 2, 3]), vec![0, 1, 2, 2, 1, 3]);
+        assert_eq!(fan_to_list(&[0, 1, 2, 3]), vec![0, 1, 2, 0, 2, 3]);
+    }
+}
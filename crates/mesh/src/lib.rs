@@ -1,53 +1,67 @@
-use nalgebra::Vector3;
+use crate::morph::MorphTarget;
+use crate::skinning::SkinWeights;
+use nalgebra::{Vector2, Vector3, Vector4};
 
-/// Represents a single vertex in a 3D mesh.
-///
-/// This struct holds the state of a vertex for physics simulations,
-/// including its current and previous positions, acceleration, and mass.
-#[derive(Clone, Copy, Debug)]
-pub struct Vertex {
-    /// The current position of the vertex in 3D space.
-    pub position: Vector3<f32>,
-    /// The position of the vertex in the previous frame, used for Verlet integration.
-    pub old_position: Vector3<f32>,
-    /// The current acceleration of the vertex.
-    pub acceleration: Vector3<f32>,
-    /// The mass of the vertex.
-    pub mass: f32,
-}
+pub mod attributes;
+pub mod bvh;
+pub mod cleanup;
+pub mod components;
+pub mod decimation;
+pub mod diff;
+mod error;
+pub mod geodesic;
+pub mod geometry;
+#[cfg(feature = "gltf")]
+pub mod gltf;
+pub mod morph;
+pub mod obj;
+pub mod ply;
+pub mod selection;
+pub mod skinning;
+pub mod smoothing;
+pub mod snapshot;
+pub mod stl;
+pub mod subdivision;
+pub mod symmetry;
+pub mod topology;
+pub mod validation;
 
-impl Vertex {
-    /// Creates a new `Vertex` at the given coordinates.
-    ///
-    /// # Arguments
-    ///
-    /// * `x` - The x-coordinate of the vertex.
-    /// * `y` - The y-coordinate of the vertex.
-    /// * `z` - The z-coordinate of the vertex.
-    ///
-    /// # Returns
-    ///
-    /// A new `Vertex` instance.
-    pub fn new(x: f32, y: f32, z: f32) -> Vertex {
-        let position = Vector3::new(x, y, z);
-        Vertex {
-            position,
-            old_position: position,
-            acceleration: Vector3::zeros(),
-            mass: 1.0,
-        }
-    }
-}
+pub use error::MeshError;
 
 /// Represents a 3D mesh composed of vertices and indices.
 ///
-/// The mesh is defined by a list of vertices and a list of indices that
-/// form triangles.
+/// The mesh is defined by per-vertex arrays and a list of indices that form
+/// triangles. The simulation state is stored as one contiguous array per
+/// quantity (structure of arrays), so integration loops run over packed data
+/// and the positions can be handed to a renderer without copying; see
+/// [`Mesh::positions_flat`]. These arrays always have one entry per vertex.
+/// Normals, texture coordinates and colors are optional channels that, when
+/// present, hold one entry per vertex as well, and each morph target holds
+/// one offset per vertex.
+#[derive(Clone, Debug)]
 pub struct Mesh {
-    /// A vector of `Vertex` structs that make up the mesh.
-    pub vertices: Vec<Vertex>,
+    /// The position of each vertex.
+    pub positions: Vec<Vector3<f32>>,
+    /// The position of each vertex at the previous time step, used for
+    /// Verlet integration.
+    pub old_positions: Vec<Vector3<f32>>,
+    /// The current acceleration of each vertex.
+    pub accelerations: Vec<Vector3<f32>>,
+    /// The inverse mass of each vertex. A vertex with an inverse mass of zero
+    /// has infinite mass and is not moved by the simulation.
+    pub inverse_masses: Vec<f32>,
     /// A vector of indices that define the triangles of the mesh.
     pub indices: Vec<u32>,
+    /// The unit normal of each vertex, if the mesh has normals.
+    pub normals: Option<Vec<Vector3<f32>>>,
+    /// The texture coordinates of each vertex, if the mesh has them.
+    pub uvs: Option<Vec<Vector2<f32>>>,
+    /// The RGBA color of each vertex, if the mesh has colors.
+    pub colors: Option<Vec<Vector4<f32>>>,
+    /// The morph targets (blendshapes) of the mesh.
+    pub morph_targets: Vec<MorphTarget>,
+    /// The joints that influence each vertex, if the mesh is skinned.
+    pub skin_weights: Option<Vec<SkinWeights>>,
 }
 
 impl Mesh {
@@ -61,36 +75,99 @@ impl Mesh {
     ///
     /// # Returns
     ///
-    /// A `Result` containing the new `Mesh`, or an error message if the
-    /// length of the `positions` slice is not a multiple of 3.
-    pub fn new(positions: &[f32], indices: &[u32]) -> Result<Mesh, String> {
+    /// A `Result` containing the new `Mesh`, or a `MeshError` if the length
+    /// of either slice is not a multiple of 3, an index refers to a vertex
+    /// that does not exist, or a coordinate is NaN or infinite. Use
+    /// [`Mesh::validate`] to look for softer problems such as degenerate
+    /// triangles.
+    pub fn new(positions: &[f32], indices: &[u32]) -> Result<Mesh, MeshError> {
         if positions.len() % 3 != 0 {
-            return Err("Invalid positions length".to_string());
+            return Err(MeshError::InvalidPositionsLength {
+                len: positions.len(),
+            });
+        }
+        if indices.len() % 3 != 0 {
+            return Err(MeshError::InvalidIndicesLength { len: indices.len() });
+        }
+        if let Some(i) = positions.iter().position(|c| !c.is_finite()) {
+            return Err(MeshError::NonFiniteCoordinate { vertex: i / 3 });
         }
-        let vertices = positions
+        let vertex_count = positions.len() / 3;
+        if let Some(i) = indices.iter().position(|&i| i as usize >= vertex_count) {
+            return Err(MeshError::IndexOutOfBounds {
+                triangle: i / 3,
+                index: indices[i],
+                vertex_count,
+            });
+        }
+        let points: Vec<Vector3<f32>> = positions
             .chunks_exact(3)
-            .map(|pos| Vertex::new(pos[0], pos[1], pos[2]))
+            .map(|pos| Vector3::new(pos[0], pos[1], pos[2]))
             .collect();
 
         Ok(Mesh {
-            vertices,
+            old_positions: points.clone(),
+            accelerations: vec![Vector3::zeros(); vertex_count],
+            inverse_masses: vec![1.0; vertex_count],
+            positions: points,
             indices: indices.to_vec(),
+            normals: None,
+            uvs: None,
+            colors: None,
+            morph_targets: Vec::new(),
+            skin_weights: None,
         })
     }
 
+    /// Returns the number of vertices in the mesh.
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Returns the vertex positions as a flat slice of coordinates.
+    ///
+    /// This is a view into the position storage, so it can be passed to
+    /// rendering APIs every frame without copying.
+    ///
+    /// # Returns
+    ///
+    /// A slice containing the x, y, and z coordinates of each vertex in
+    /// sequence.
+    pub fn positions_flat(&self) -> &[f32] {
+        bytemuck::cast_slice(&self.positions)
+    }
+
+    /// Returns the vertex normals as a flat slice of components, or an empty
+    /// slice if the mesh has no normals.
+    ///
+    /// Like [`Mesh::positions_flat`], this is a view into the normal storage.
+    pub fn normals_flat(&self) -> &[f32] {
+        bytemuck::cast_slice(self.normals.as_deref().unwrap_or_default())
+    }
+
     /// Returns a flattened vector of the mesh's vertex positions.
     ///
-    /// This is useful for passing the vertex data to rendering APIs.
+    /// This copies [`Mesh::positions_flat`] into a new vector, which is only
+    /// needed when the positions must outlive the mesh or be modified.
     ///
     /// # Returns
     ///
     /// A `Vec<f32>` containing the x, y, and z coordinates of each vertex
     /// in sequence.
     pub fn get_vertex_positions_flat(&self) -> Vec<f32> {
-        self.vertices
-            .iter()
-            .flat_map(|v| v.position.iter().cloned())
-            .collect()
+        self.positions_flat().to_vec()
+    }
+
+    /// Moves a vertex and resets its previous position, so the simulation
+    /// does not see the move as velocity.
+    ///
+    /// # Arguments
+    ///
+    /// * `vertex` - The vertex to move.
+    /// * `position` - The new position of the vertex.
+    pub fn teleport_vertex(&mut self, vertex: usize, position: Vector3<f32>) {
+        self.positions[vertex] = position;
+        self.old_positions[vertex] = position;
     }
 }
 
@@ -100,27 +177,59 @@ mod tests {
 
     #[test]
     fn test_mesh_new_valid() {
-        let positions = vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0];
-        let indices = vec![0, 1];
+        let positions = vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        let indices = vec![0, 1, 2];
         let mesh = Mesh::new(&positions, &indices).unwrap();
-        assert_eq!(mesh.vertices.len(), 2);
-        assert_eq!(mesh.indices.len(), 2);
-        assert_eq!(mesh.vertices[1].position, Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(mesh.vertex_count(), 3);
+        assert_eq!(mesh.indices.len(), 3);
+        assert_eq!(mesh.positions[1], Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(mesh.old_positions, mesh.positions);
+        assert_eq!(mesh.inverse_masses, vec![1.0; 3]);
     }
 
     #[test]
     fn test_mesh_new_invalid_positions() {
         let positions = vec![0.0, 0.0, 0.0, 1.0, 0.0]; // Invalid length
-        let indices = vec![0, 1];
+        let indices = vec![];
         let result = Mesh::new(&positions, &indices);
-        assert!(result.is_err());
+        assert_eq!(
+            result.err(),
+            Some(MeshError::InvalidPositionsLength { len: 5 })
+        );
+    }
+
+    #[test]
+    fn test_mesh_new_invalid_indices() {
+        let positions = vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+        assert_eq!(
+            Mesh::new(&positions, &[0, 1]).err(),
+            Some(MeshError::InvalidIndicesLength { len: 2 })
+        );
+        assert_eq!(
+            Mesh::new(&positions, &[0, 1, 2]).err(),
+            Some(MeshError::IndexOutOfBounds {
+                triangle: 0,
+                index: 2,
+                vertex_count: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_mesh_new_non_finite_coordinate() {
+        let positions = vec![0.0, 0.0, 0.0, 1.0, f32::NAN, 0.0];
+        assert_eq!(
+            Mesh::new(&positions, &[]).err(),
+            Some(MeshError::NonFiniteCoordinate { vertex: 1 })
+        );
     }
 
     #[test]
     fn test_get_vertex_positions_flat() {
         let positions = vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0];
-        let indices = vec![0, 1];
+        let indices = vec![];
         let mesh = Mesh::new(&positions, &indices).unwrap();
+        assert_eq!(mesh.positions_flat(), &positions[..]);
         let flat_positions = mesh.get_vertex_positions_flat();
         assert_eq!(flat_positions, positions);
     }
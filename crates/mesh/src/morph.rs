@@ -0,0 +1,160 @@
+//! Morph targets (blendshapes) and their weighted evaluation.
+
+use crate::{Mesh, MeshError};
+use nalgebra::Vector3;
+
+/// A named shape stored as per-vertex offsets from the base shape of a mesh.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MorphTarget {
+    /// The name of the target, such as `"smile"`.
+    pub name: String,
+    /// The offset of each vertex from the base shape when the target is fully
+    /// applied.
+    pub deltas: Vec<Vector3<f32>>,
+}
+
+impl MorphTarget {
+    /// Returns a copy of the target restricted to the given vertices, in order.
+    pub(crate) fn select(&self, vertices: &[usize]) -> MorphTarget {
+        MorphTarget {
+            name: self.name.clone(),
+            deltas: vertices.iter().map(|&v| self.deltas[v]).collect(),
+        }
+    }
+}
+
+impl Mesh {
+    /// Adds a morph target to the mesh.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the target. Names do not have to be unique, but
+    ///   [`Mesh::morph_target_index`] only finds the first target with a name.
+    /// * `deltas` - The offset of each vertex from the base shape.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the index of the new target, or a `MeshError` if
+    /// there is not exactly one offset per vertex.
+    pub fn add_morph_target(
+        &mut self,
+        name: impl Into<String>,
+        deltas: Vec<Vector3<f32>>,
+    ) -> Result<usize, MeshError> {
+        if deltas.len() != self.vertex_count() {
+            return Err(MeshError::AttributeLength {
+                attribute: "morph target",
+                expected: self.vertex_count(),
+                actual: deltas.len(),
+            });
+        }
+        self.morph_targets.push(MorphTarget {
+            name: name.into(),
+            deltas,
+        });
+        Ok(self.morph_targets.len() - 1)
+    }
+
+    /// Returns the index of the first morph target with a given name.
+    pub fn morph_target_index(&self, name: &str) -> Option<usize> {
+        self.morph_targets.iter().position(|t| t.name == name)
+    }
+
+    /// Evaluates a weighted combination of the morph targets.
+    ///
+    /// The result is `base + Σ weight[i] * deltas[i]`. Weights are usually
+    /// between 0 and 1, but any value is allowed, so targets can be
+    /// exaggerated or inverted.
+    ///
+    /// # Arguments
+    ///
+    /// * `base` - The position of each vertex with no target applied. The
+    ///   vertex positions of the mesh are not used, since a simulation keeps
+    ///   changing them.
+    /// * `weights` - The weight of each target, in the order of
+    ///   `morph_targets`. Targets without a weight are not applied.
+    ///
+    /// # Returns
+    ///
+    /// The blended position of each vertex.
+    pub fn blend_morph_targets(&self, base: &[Vector3<f32>], weights: &[f32]) -> Vec<Vector3<f32>> {
+        let mut positions = base.to_vec();
+        for (target, &weight) in self.morph_targets.iter().zip(weights) {
+            if weight == 0.0 {
+                continue;
+            }
+            for (position, delta) in positions.iter_mut().zip(&target.deltas) {
+                *position += delta * weight;
+            }
+        }
+        positions
+    }
+
+    /// Moves the vertices to a weighted combination of the morph targets.
+    ///
+    /// This is [`Mesh::blend_morph_targets`] written back to the vertices. The
+    /// previous positions are reset as well, so a simulation does not see the
+    /// jump as velocity.
+    ///
+    /// # Arguments
+    ///
+    /// * `base` - The position of each vertex with no target applied.
+    /// * `weights` - The weight of each target, in the order of `morph_targets`.
+    pub fn apply_morph_targets(&mut self, base: &[Vector3<f32>], weights: &[f32]) {
+        let positions = self.blend_morph_targets(base, weights);
+        self.old_positions.clone_from(&positions);
+        self.positions = positions;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangle() -> Mesh {
+        Mesh::new(&[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0], &[0, 1, 2]).unwrap()
+    }
+
+    #[test]
+    fn test_add_morph_target_checks_length() {
+        let mut mesh = triangle();
+        let up = vec![Vector3::z(); 3];
+        assert_eq!(mesh.add_morph_target("up", up), Ok(0));
+        assert_eq!(
+            mesh.add_morph_target("short", vec![Vector3::z(); 2]),
+            Err(MeshError::AttributeLength {
+                attribute: "morph target",
+                expected: 3,
+                actual: 2
+            })
+        );
+        assert_eq!(mesh.morph_target_index("up"), Some(0));
+        assert_eq!(mesh.morph_target_index("short"), None);
+    }
+
+    #[test]
+    fn test_blend_morph_targets() {
+        let mut mesh = triangle();
+        let base: Vec<Vector3<f32>> = mesh.positions.clone();
+        mesh.add_morph_target("up", vec![Vector3::z(); 3]).unwrap();
+        let mut stretch = vec![Vector3::zeros(); 3];
+        stretch[1] = Vector3::x();
+        mesh.add_morph_target("stretch", stretch).unwrap();
+
+        let blended = mesh.blend_morph_targets(&base, &[0.5, 2.0]);
+        assert_eq!(blended[0], Vector3::new(0.0, 0.0, 0.5));
+        assert_eq!(blended[1], Vector3::new(3.0, 0.0, 0.5));
+        // Missing weights leave the remaining targets out.
+        assert_eq!(mesh.blend_morph_targets(&base, &[]), base);
+    }
+
+    #[test]
+    fn test_apply_morph_targets_resets_velocity() {
+        let mut mesh = triangle();
+        let base: Vec<Vector3<f32>> = mesh.positions.clone();
+        mesh.add_morph_target("up", vec![Vector3::z(); 3]).unwrap();
+        mesh.apply_morph_targets(&base, &[1.0]);
+        assert_eq!(mesh.positions[2], Vector3::new(0.0, 1.0, 1.0));
+        assert_eq!(mesh.old_positions[2], mesh.positions[2]);
+    }
+}
@@ -0,0 +1,590 @@
+//! Reading and writing meshes in the Wavefront OBJ format.
+
+use crate::{Mesh, MeshError};
+use nalgebra::{Vector2, Vector3};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::ops::Range;
+
+/// An error that can occur while reading an OBJ file.
+#[derive(Debug)]
+pub enum ObjError {
+    /// The underlying reader failed.
+    Io(io::Error),
+    /// A line of the file could not be parsed.
+    Parse {
+        /// The 1-based line number where the error occurred.
+        line: usize,
+        /// A description of what went wrong.
+        message: String,
+    },
+    /// The file parsed, but does not describe a valid mesh.
+    Mesh(MeshError),
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io(err) => write!(f, "I/O error while reading OBJ: {}", err),
+            ObjError::Parse { line, message } => {
+                write!(f, "OBJ parse error on line {}: {}", line, message)
+            }
+            ObjError::Mesh(err) => write!(f, "invalid OBJ mesh: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ObjError::Io(err) => Some(err),
+            ObjError::Parse { .. } => None,
+            ObjError::Mesh(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for ObjError {
+    fn from(err: io::Error) -> Self {
+        ObjError::Io(err)
+    }
+}
+
+impl From<MeshError> for ObjError {
+    fn from(err: MeshError) -> Self {
+        ObjError::Mesh(err)
+    }
+}
+
+/// A single corner of an OBJ face.
+///
+/// OBJ indexes positions, texture coordinates and normals independently, so
+/// each corner carries one (zero-based) index per attribute stream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ObjCorner {
+    /// The index into `ObjModel::positions`.
+    pub position: u32,
+    /// The index into `ObjModel::texcoords`, if the face specified one.
+    pub texcoord: Option<u32>,
+    /// The index into `ObjModel::normals`, if the face specified one.
+    pub normal: Option<u32>,
+}
+
+/// A named group of triangles, started by a `g` or `o` statement.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ObjGroup {
+    /// The name of the group.
+    pub name: String,
+    /// The range of triangles in `ObjModel::triangles` that belong to the group.
+    pub triangles: Range<usize>,
+}
+
+/// The contents of an OBJ file, with all polygons triangulated.
+#[derive(Clone, Debug, Default)]
+pub struct ObjModel {
+    /// The vertex positions (`v` statements).
+    pub positions: Vec<Vector3<f32>>,
+    /// The RGB vertex colors given after the coordinates of `v` statements.
+    /// This is only used when every position has a color.
+    pub colors: Vec<Vector3<f32>>,
+    /// The texture coordinates (`vt` statements).
+    pub texcoords: Vec<Vector2<f32>>,
+    /// The vertex normals (`vn` statements).
+    pub normals: Vec<Vector3<f32>>,
+    /// The triangles of the model. Polygons are fan-triangulated on load.
+    pub triangles: Vec<[ObjCorner; 3]>,
+    /// The named groups of the model, in file order.
+    pub groups: Vec<ObjGroup>,
+}
+
+impl ObjModel {
+    /// Parses an OBJ file.
+    ///
+    /// Positions, texture coordinates, normals, faces and groups are read;
+    /// other statements such as materials and smoothing groups are ignored.
+    /// Polygons with more than three corners are fan-triangulated, and
+    /// negative (relative) indices are resolved.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The source of the OBJ text.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the parsed `ObjModel`, or an `ObjError` if the
+    /// file could not be read or contains malformed statements.
+    pub fn parse<R: BufRead>(reader: R) -> Result<ObjModel, ObjError> {
+        let mut model = ObjModel::default();
+        let mut current_group: Option<(String, usize)> = None;
+
+        for (line_index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line_number = line_index + 1;
+            let line = match line.find('#') {
+                Some(comment) => &line[..comment],
+                None => &line[..],
+            };
+            let mut tokens = line.split_whitespace();
+            let keyword = match tokens.next() {
+                Some(keyword) => keyword,
+                None => continue,
+            };
+
+            match keyword {
+                "v" => {
+                    let values = parse_floats(tokens, line_number)?;
+                    if values.len() < 3 {
+                        return Err(parse_error(line_number, "vertex needs 3 coordinates"));
+                    }
+                    model
+                        .positions
+                        .push(Vector3::new(values[0], values[1], values[2]));
+                    if values.len() >= 6 {
+                        model
+                            .colors
+                            .push(Vector3::new(values[3], values[4], values[5]));
+                    }
+                }
+                "vt" => {
+                    let values = parse_floats(tokens, line_number)?;
+                    if values.is_empty() {
+                        return Err(parse_error(line_number, "texture coordinate is empty"));
+                    }
+                    let v = values.get(1).copied().unwrap_or(0.0);
+                    model.texcoords.push(Vector2::new(values[0], v));
+                }
+                "vn" => {
+                    let values = parse_floats(tokens, line_number)?;
+                    if values.len() < 3 {
+                        return Err(parse_error(line_number, "normal needs 3 components"));
+                    }
+                    model
+                        .normals
+                        .push(Vector3::new(values[0], values[1], values[2]));
+                }
+                "f" => {
+                    let corners = tokens
+                        .map(|token| model.parse_corner(token, line_number))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if corners.len() < 3 {
+                        return Err(parse_error(line_number, "face needs at least 3 corners"));
+                    }
+                    for i in 1..corners.len() - 1 {
+                        model
+                            .triangles
+                            .push([corners[0], corners[i], corners[i + 1]]);
+                    }
+                }
+                "g" | "o" => {
+                    let name = tokens.collect::<Vec<_>>().join(" ");
+                    if let Some((name, start)) = current_group.take() {
+                        model.push_group(name, start);
+                    }
+                    current_group = Some((name, model.triangles.len()));
+                }
+                _ => {}
+            }
+        }
+
+        if let Some((name, start)) = current_group {
+            model.push_group(name, start);
+        }
+        Ok(model)
+    }
+
+    /// Converts the model into a `Mesh`.
+    ///
+    /// Vertex `i` of the mesh is position `i` of the model. If a position is
+    /// referenced with more than one texture coordinate or normal (for
+    /// example along a UV seam), the additional combinations are appended as
+    /// extra vertices after the original positions.
+    ///
+    /// The mesh gets normals or texture coordinates if any face references
+    /// them, with vertices that have none set to zero, and colors if every
+    /// position has one.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a new `Mesh` with the triangles of every group,
+    /// or a `MeshError` if a position is not finite.
+    pub fn to_mesh(&self) -> Result<Mesh, MeshError> {
+        let (corner_vertices, sources) = self.unify_corners();
+        let positions: Vec<f32> = sources
+            .iter()
+            .flat_map(|source| {
+                let p = self.positions[source.position as usize];
+                [p.x, p.y, p.z]
+            })
+            .collect();
+        let mut mesh = Mesh::new(&positions, &corner_vertices)?;
+
+        if sources.iter().any(|source| source.normal.is_some()) {
+            mesh.normals = Some(
+                sources
+                    .iter()
+                    .map(|source| {
+                        source
+                            .normal
+                            .map_or(Vector3::zeros(), |n| self.normals[n as usize])
+                    })
+                    .collect(),
+            );
+        }
+        if sources.iter().any(|source| source.texcoord.is_some()) {
+            mesh.uvs = Some(
+                sources
+                    .iter()
+                    .map(|source| {
+                        source
+                            .texcoord
+                            .map_or(Vector2::zeros(), |t| self.texcoords[t as usize])
+                    })
+                    .collect(),
+            );
+        }
+        if !self.colors.is_empty() && self.colors.len() == self.positions.len() {
+            mesh.colors = Some(
+                sources
+                    .iter()
+                    .map(|source| self.colors[source.position as usize].push(1.0))
+                    .collect(),
+            );
+        }
+        Ok(mesh)
+    }
+
+    /// Builds a model containing the positions, triangles and attributes of a `Mesh`.
+    ///
+    /// All triangles are placed in a single unnamed stream with no groups.
+    /// Normals and texture coordinates are indexed like the positions, and
+    /// colors are written without their alpha channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh` - The mesh to convert.
+    ///
+    /// # Returns
+    ///
+    /// A new `ObjModel` with one OBJ position per mesh vertex.
+    pub fn from_mesh(mesh: &Mesh) -> ObjModel {
+        let positions = mesh.positions.clone();
+        let has_uvs = mesh.uvs.is_some();
+        let has_normals = mesh.normals.is_some();
+        let triangles = mesh
+            .indices
+            .chunks_exact(3)
+            .map(|triangle| {
+                let corner = |i: u32| ObjCorner {
+                    position: i,
+                    texcoord: has_uvs.then_some(i),
+                    normal: has_normals.then_some(i),
+                };
+                [
+                    corner(triangle[0]),
+                    corner(triangle[1]),
+                    corner(triangle[2]),
+                ]
+            })
+            .collect();
+        ObjModel {
+            positions,
+            colors: mesh.colors.iter().flatten().map(|c| c.xyz()).collect(),
+            texcoords: mesh.uvs.clone().unwrap_or_default(),
+            normals: mesh.normals.clone().unwrap_or_default(),
+            triangles,
+            groups: Vec::new(),
+        }
+    }
+
+    /// Writes the model as OBJ text.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - The destination for the OBJ text.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Result` indicating whether writing succeeded.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let write_colors = self.colors.len() == self.positions.len();
+        for (i, p) in self.positions.iter().enumerate() {
+            write!(writer, "v {} {} {}", p.x, p.y, p.z)?;
+            if write_colors {
+                let c = self.colors[i];
+                write!(writer, " {} {} {}", c.x, c.y, c.z)?;
+            }
+            writeln!(writer)?;
+        }
+        for t in &self.texcoords {
+            writeln!(writer, "vt {} {}", t.x, t.y)?;
+        }
+        for n in &self.normals {
+            writeln!(writer, "vn {} {} {}", n.x, n.y, n.z)?;
+        }
+
+        let mut groups = self.groups.iter().peekable();
+        for (i, triangle) in self.triangles.iter().enumerate() {
+            while let Some(group) = groups.next_if(|group| group.triangles.start == i) {
+                writeln!(writer, "g {}", group.name)?;
+            }
+            write!(writer, "f")?;
+            for corner in triangle {
+                write!(writer, " {}", corner.position + 1)?;
+                match (corner.texcoord, corner.normal) {
+                    (Some(t), Some(n)) => write!(writer, "/{}/{}", t + 1, n + 1)?,
+                    (Some(t), None) => write!(writer, "/{}", t + 1)?,
+                    (None, Some(n)) => write!(writer, "//{}", n + 1)?,
+                    (None, None) => {}
+                }
+            }
+            writeln!(writer)?;
+        }
+        for group in groups {
+            writeln!(writer, "g {}", group.name)?;
+        }
+        Ok(())
+    }
+
+    /// Maps every triangle corner to a mesh vertex.
+    ///
+    /// Returns the vertex index of each corner (in triangle order) and, for
+    /// every mesh vertex, the OBJ corner it was created from. Positions that
+    /// no face references get a corner without texture coordinate or normal.
+    fn unify_corners(&self) -> (Vec<u32>, Vec<ObjCorner>) {
+        let mut sources: Vec<ObjCorner> = (0..self.positions.len() as u32)
+            .map(|position| ObjCorner {
+                position,
+                texcoord: None,
+                normal: None,
+            })
+            .collect();
+        let mut first_use: Vec<Option<ObjCorner>> = vec![None; self.positions.len()];
+        let mut extra_vertices: HashMap<ObjCorner, u32> = HashMap::new();
+        let mut corner_vertices = Vec::with_capacity(self.triangles.len() * 3);
+
+        for corner in self.triangles.iter().flatten() {
+            let p = corner.position as usize;
+            let vertex = match first_use[p] {
+                None => {
+                    first_use[p] = Some(*corner);
+                    sources[p] = *corner;
+                    corner.position
+                }
+                Some(first) if first == *corner => corner.position,
+                Some(_) => *extra_vertices.entry(*corner).or_insert_with(|| {
+                    sources.push(*corner);
+                    sources.len() as u32 - 1
+                }),
+            };
+            corner_vertices.push(vertex);
+        }
+        (corner_vertices, sources)
+    }
+
+    fn push_group(&mut self, name: String, start: usize) {
+        self.groups.push(ObjGroup {
+            name,
+            triangles: start..self.triangles.len(),
+        });
+    }
+
+    fn parse_corner(&self, token: &str, line: usize) -> Result<ObjCorner, ObjError> {
+        let mut parts = token.split('/');
+        let position = parts
+            .next()
+            .filter(|s| !s.is_empty())
+            .ok_or_else(|| parse_error(line, "face corner has no position index"))?;
+        let position = resolve_index(position, self.positions.len(), line)?;
+        let texcoord = match parts.next() {
+            Some(s) if !s.is_empty() => Some(resolve_index(s, self.texcoords.len(), line)?),
+            _ => None,
+        };
+        let normal = match parts.next() {
+            Some(s) if !s.is_empty() => Some(resolve_index(s, self.normals.len(), line)?),
+            _ => None,
+        };
+        Ok(ObjCorner {
+            position,
+            texcoord,
+            normal,
+        })
+    }
+}
+
+impl Mesh {
+    /// Reads a `Mesh` from OBJ text.
+    ///
+    /// This is a shorthand for [`ObjModel::parse`] followed by
+    /// [`ObjModel::to_mesh`].
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The source of the OBJ text.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the new `Mesh`, or an `ObjError` if parsing failed.
+    pub fn read_obj<R: BufRead>(reader: R) -> Result<Mesh, ObjError> {
+        Ok(ObjModel::parse(reader)?.to_mesh()?)
+    }
+
+    /// Writes the current vertex positions and triangles of the mesh as OBJ text.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - The destination for the OBJ text.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Result` indicating whether writing succeeded.
+    pub fn write_obj<W: Write>(&self, writer: W) -> io::Result<()> {
+        ObjModel::from_mesh(self).write(writer)
+    }
+}
+
+fn parse_error(line: usize, message: &str) -> ObjError {
+    ObjError::Parse {
+        line,
+        message: message.to_string(),
+    }
+}
+
+fn parse_floats<'a>(
+    tokens: impl Iterator<Item = &'a str>,
+    line: usize,
+) -> Result<Vec<f32>, ObjError> {
+    tokens
+        .map(|token| {
+            token
+                .parse::<f32>()
+                .map_err(|_| parse_error(line, &format!("invalid number `{}`", token)))
+        })
+        .collect()
+}
+
+/// Converts a 1-based (or negative, relative) OBJ index into a 0-based index.
+fn resolve_index(token: &str, count: usize, line: usize) -> Result<u32, ObjError> {
+    let index: i64 = token
+        .parse()
+        .map_err(|_| parse_error(line, &format!("invalid index `{}`", token)))?;
+    let resolved = if index > 0 {
+        index - 1
+    } else {
+        count as i64 + index
+    };
+    if index == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(parse_error(
+            line,
+            &format!("index {} is out of range", index),
+        ));
+    }
+    Ok(resolved as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUAD: &str = "\
+# a unit quad split into two groups
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+g front
+f 1/1/1 2/2/1 3/3/1 4/4/1
+g back
+f -1//1 -2//1 -3//1
+";
+
+    #[test]
+    fn test_parse_triangulates_polygons_and_groups() {
+        let model = ObjModel::parse(QUAD.as_bytes()).unwrap();
+        assert_eq!(model.positions.len(), 4);
+        assert_eq!(model.texcoords.len(), 4);
+        assert_eq!(model.normals.len(), 1);
+        assert_eq!(model.triangles.len(), 3);
+        assert_eq!(model.groups.len(), 2);
+        assert_eq!(model.groups[0].name, "front");
+        assert_eq!(model.groups[0].triangles, 0..2);
+        assert_eq!(model.groups[1].triangles, 2..3);
+        // Relative indices resolve from the end of the position list.
+        assert_eq!(model.triangles[2][0].position, 3);
+        assert_eq!(model.triangles[2][0].texcoord, None);
+    }
+
+    #[test]
+    fn test_to_mesh_splits_seam_vertices() {
+        let mesh = Mesh::read_obj(QUAD.as_bytes()).unwrap();
+        assert_eq!(mesh.indices.len(), 9);
+        // The back group reuses positions 4, 3 and 2 without texture coordinates,
+        // so those three corners become extra vertices.
+        assert_eq!(mesh.vertex_count(), 7);
+        assert_eq!(mesh.indices[..6], [0, 1, 2, 0, 2, 3]);
+        assert_eq!(mesh.positions[4], Vector3::new(0.0, 1.0, 0.0));
+
+        let uvs = mesh.uvs.as_ref().unwrap();
+        assert_eq!(uvs[2], Vector2::new(1.0, 1.0));
+        // Seam vertices from the back group have no texture coordinate.
+        assert_eq!(uvs[4], Vector2::zeros());
+        assert_eq!(mesh.normals.as_ref().unwrap()[6], Vector3::z());
+        assert!(mesh.colors.is_none());
+    }
+
+    #[test]
+    fn test_write_round_trip_with_attributes() {
+        let mut mesh =
+            Mesh::new(&[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0], &[0, 1, 2]).unwrap();
+        mesh.set_uvs_flat(&[0.0, 0.0, 1.0, 0.0, 0.0, 1.0]).unwrap();
+        mesh.set_colors_flat(&[1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0])
+            .unwrap();
+        mesh.compute_normals(crate::attributes::NormalWeighting::Area);
+
+        let mut buffer = Vec::new();
+        mesh.write_obj(&mut buffer).unwrap();
+        let loaded = Mesh::read_obj(buffer.as_slice()).unwrap();
+
+        assert_eq!(loaded.vertex_count(), 3);
+        assert_eq!(loaded.get_uvs_flat(), mesh.get_uvs_flat());
+        assert_eq!(loaded.get_normals_flat(), mesh.get_normals_flat());
+        assert_eq!(loaded.get_colors_flat(), mesh.get_colors_flat());
+    }
+
+    #[test]
+    fn test_write_round_trip() {
+        let positions = vec![0.0, 0.0, 0.0, 1.5, 0.0, 0.0, 0.0, -2.25, 0.1];
+        let mesh = Mesh::new(&positions, &[0, 1, 2]).unwrap();
+        let mut buffer = Vec::new();
+        mesh.write_obj(&mut buffer).unwrap();
+
+        let loaded = Mesh::read_obj(buffer.as_slice()).unwrap();
+        assert_eq!(loaded.get_vertex_positions_flat(), positions);
+        assert_eq!(loaded.indices, mesh.indices);
+    }
+
+    #[test]
+    fn test_parse_rejects_out_of_range_index() {
+        let result = ObjModel::parse("v 0 0 0\nf 1 2 3\n".as_bytes());
+        match result {
+            Err(ObjError::Parse { line, .. }) => assert_eq!(line, 2),
+            _ => panic!("expected a parse error"),
+        }
+    }
+
+    #[test]
+    fn test_read_obj_rejects_non_finite_position() {
+        let result = Mesh::read_obj("v 0 0 0\nv nan 0 0\nv 0 1 0\nf 1 2 3\n".as_bytes());
+        match result {
+            Err(ObjError::Mesh(err)) => {
+                assert_eq!(err, MeshError::NonFiniteCoordinate { vertex: 1 })
+            }
+            _ => panic!("expected a mesh error"),
+        }
+    }
+}
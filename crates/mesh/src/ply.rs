@@ -0,0 +1,736 @@
+//! Reading and writing meshes in the Stanford PLY format.
+//!
+//! Both the ASCII and the binary encodings are supported. The `vertex`
+//! element supplies positions (`x`, `y`, `z`) and, when present, normals
+//! (`nx`, `ny`, `nz`), texture coordinates (`u`, `v`, or `s`, `t`) and colors
+//! (`red`, `green`, `blue` and optionally `alpha`). The `face` element
+//! supplies polygons as a `vertex_indices` list, which are split into
+//! triangles. Other elements and properties are skipped.
+
+use crate::{Mesh, MeshError};
+use nalgebra::{Vector2, Vector3, Vector4};
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+/// An error that can occur while reading a PLY file.
+#[derive(Debug)]
+pub enum PlyError {
+    /// The underlying reader failed.
+    Io(io::Error),
+    /// A line of the header could not be parsed.
+    Header {
+        /// The 1-based line number where the error occurred.
+        line: usize,
+        /// A description of what went wrong.
+        message: String,
+    },
+    /// The data after the header is truncated or malformed.
+    Body(String),
+    /// The file parsed, but does not describe a valid mesh.
+    Mesh(MeshError),
+}
+
+impl fmt::Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlyError::Io(err) => write!(f, "I/O error while reading PLY: {}", err),
+            PlyError::Header { line, message } => {
+                write!(f, "PLY header error on line {}: {}", line, message)
+            }
+            PlyError::Body(message) => write!(f, "invalid PLY data: {}", message),
+            PlyError::Mesh(err) => write!(f, "invalid PLY mesh: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for PlyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PlyError::Io(err) => Some(err),
+            PlyError::Header { .. } | PlyError::Body(_) => None,
+            PlyError::Mesh(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for PlyError {
+    fn from(err: io::Error) -> Self {
+        PlyError::Io(err)
+    }
+}
+
+impl From<MeshError> for PlyError {
+    fn from(err: MeshError) -> Self {
+        PlyError::Mesh(err)
+    }
+}
+
+/// The encoding of the data that follows a PLY header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlyFormat {
+    /// Whitespace-separated decimal numbers.
+    Ascii,
+    /// Little-endian binary values.
+    BinaryLittleEndian,
+    /// Big-endian binary values.
+    BinaryBigEndian,
+}
+
+/// The type of a PLY property value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Scalar> {
+        Some(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    /// The value that a color channel of this type has at full intensity.
+    fn color_scale(self) -> f64 {
+        match self {
+            Scalar::U8 => 255.0,
+            Scalar::U16 => 65535.0,
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Property {
+    Scalar {
+        name: String,
+        kind: Scalar,
+    },
+    List {
+        name: String,
+        count: Scalar,
+        item: Scalar,
+    },
+}
+
+impl Property {
+    fn name(&self) -> &str {
+        match self {
+            Property::Scalar { name, .. } | Property::List { name, .. } => name,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    fn scalar(&self, names: &[&str]) -> Option<(usize, Scalar)> {
+        self.properties
+            .iter()
+            .enumerate()
+            .find_map(|(i, p)| match p {
+                Property::Scalar { name, kind } if names.contains(&name.as_str()) => {
+                    Some((i, *kind))
+                }
+                _ => None,
+            })
+    }
+}
+
+/// The values of one element instance, in property order. Lists are stored
+/// as their items.
+type Record = Vec<Vec<f64>>;
+
+/// A cursor over the data that follows the header.
+enum Body<'a> {
+    Ascii(std::str::SplitAsciiWhitespace<'a>),
+    Binary { bytes: &'a [u8], big_endian: bool },
+}
+
+impl Body<'_> {
+    fn read(&mut self, kind: Scalar) -> Result<f64, PlyError> {
+        match self {
+            Body::Ascii(tokens) => {
+                let token = tokens
+                    .next()
+                    .ok_or_else(|| PlyError::Body("unexpected end of data".to_string()))?;
+                token
+                    .parse()
+                    .map_err(|_| PlyError::Body(format!("invalid number `{}`", token)))
+            }
+            Body::Binary { bytes, big_endian } => {
+                if bytes.len() < kind.size() {
+                    return Err(PlyError::Body("unexpected end of data".to_string()));
+                }
+                let (value, rest) = bytes.split_at(kind.size());
+                *bytes = rest;
+                let mut buffer = [0u8; 8];
+                buffer[..value.len()].copy_from_slice(value);
+                if *big_endian {
+                    buffer[..value.len()].reverse();
+                }
+                Ok(match kind {
+                    Scalar::I8 => buffer[0] as i8 as f64,
+                    Scalar::U8 => buffer[0] as f64,
+                    Scalar::I16 => i16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+                    Scalar::U16 => u16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+                    Scalar::I32 => i32::from_le_bytes(buffer[..4].try_into().unwrap()) as f64,
+                    Scalar::U32 => u32::from_le_bytes(buffer[..4].try_into().unwrap()) as f64,
+                    Scalar::F32 => f32::from_le_bytes(buffer[..4].try_into().unwrap()) as f64,
+                    Scalar::F64 => f64::from_le_bytes(buffer),
+                })
+            }
+        }
+    }
+
+    fn read_record(&mut self, element: &Element) -> Result<Record, PlyError> {
+        element
+            .properties
+            .iter()
+            .map(|property| match *property {
+                Property::Scalar { kind, .. } => Ok(vec![self.read(kind)?]),
+                Property::List { count, item, .. } => {
+                    let count = self.read(count)?;
+                    if !(0.0..=u32::MAX as f64).contains(&count) || count.fract() != 0.0 {
+                        return Err(PlyError::Body(format!("invalid list length {}", count)));
+                    }
+                    (0..count as usize).map(|_| self.read(item)).collect()
+                }
+            })
+            .collect()
+    }
+}
+
+impl Mesh {
+    /// Reads a `Mesh` from a PLY file in any of its encodings.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The source of the PLY data.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the new `Mesh`, or a `PlyError` if the file could
+    /// not be read or does not describe a valid mesh.
+    pub fn read_ply<R: BufRead>(mut reader: R) -> Result<Mesh, PlyError> {
+        let (format, elements) = read_header(&mut reader)?;
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut body = match format {
+            PlyFormat::Ascii => Body::Ascii(
+                std::str::from_utf8(&data)
+                    .map_err(|_| PlyError::Body("ASCII data is not valid UTF-8".to_string()))?
+                    .split_ascii_whitespace(),
+            ),
+            PlyFormat::BinaryLittleEndian | PlyFormat::BinaryBigEndian => Body::Binary {
+                bytes: &data,
+                big_endian: format == PlyFormat::BinaryBigEndian,
+            },
+        };
+
+        let mut positions = Vec::new();
+        let mut normals = Vec::new();
+        let mut uvs = Vec::new();
+        let mut colors = Vec::new();
+        let mut indices = Vec::new();
+        for element in &elements {
+            match element.name.as_str() {
+                "vertex" => {
+                    let vertices = VertexLayout::new(element)?;
+                    for _ in 0..element.count {
+                        let record = body.read_record(element)?;
+                        vertices.read(&record, &mut positions, &mut normals, &mut uvs, &mut colors);
+                    }
+                }
+                "face" => {
+                    let list = element
+                        .properties
+                        .iter()
+                        .position(|p| {
+                            matches!(p, Property::List { .. })
+                                && matches!(p.name(), "vertex_indices" | "vertex_index")
+                        })
+                        .ok_or_else(|| {
+                            PlyError::Body("face element has no vertex_indices list".to_string())
+                        })?;
+                    for _ in 0..element.count {
+                        let record = body.read_record(element)?;
+                        let polygon = &record[list];
+                        for i in 1..polygon.len().saturating_sub(1) {
+                            for &index in &[polygon[0], polygon[i], polygon[i + 1]] {
+                                if !(0.0..=u32::MAX as f64).contains(&index) || index.fract() != 0.0
+                                {
+                                    return Err(PlyError::Body(format!(
+                                        "invalid vertex index {}",
+                                        index
+                                    )));
+                                }
+                                indices.push(index as u32);
+                            }
+                        }
+                    }
+                }
+                _ => {
+                    for _ in 0..element.count {
+                        body.read_record(element)?;
+                    }
+                }
+            }
+        }
+
+        let mut mesh = Mesh::new(&positions, &indices)?;
+        mesh.normals = (!normals.is_empty()).then_some(normals);
+        mesh.uvs = (!uvs.is_empty()).then_some(uvs);
+        mesh.colors = (!colors.is_empty()).then_some(colors);
+        Ok(mesh)
+    }
+
+    /// Writes the mesh as a PLY file.
+    ///
+    /// Positions, normals and texture coordinates are written as `float`
+    /// properties, colors as `uchar` properties, and triangles as
+    /// `vertex_indices` lists. Channels that the mesh does not have are left
+    /// out.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - The destination for the PLY data.
+    /// * `format` - The encoding to write the data in.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Result` indicating whether writing succeeded.
+    pub fn write_ply<W: Write>(&self, mut writer: W, format: PlyFormat) -> io::Result<()> {
+        let format_name = match format {
+            PlyFormat::Ascii => "ascii",
+            PlyFormat::BinaryLittleEndian => "binary_little_endian",
+            PlyFormat::BinaryBigEndian => "binary_big_endian",
+        };
+        writeln!(writer, "ply")?;
+        writeln!(writer, "format {} 1.0", format_name)?;
+        writeln!(writer, "element vertex {}", self.vertex_count())?;
+        let mut properties = vec![("float", "x"), ("float", "y"), ("float", "z")];
+        if self.normals.is_some() {
+            properties.extend([("float", "nx"), ("float", "ny"), ("float", "nz")]);
+        }
+        if self.uvs.is_some() {
+            properties.extend([("float", "u"), ("float", "v")]);
+        }
+        if self.colors.is_some() {
+            properties.extend([
+                ("uchar", "red"),
+                ("uchar", "green"),
+                ("uchar", "blue"),
+                ("uchar", "alpha"),
+            ]);
+        }
+        for (kind, name) in &properties {
+            writeln!(writer, "property {} {}", kind, name)?;
+        }
+        writeln!(writer, "element face {}", self.indices.len() / 3)?;
+        writeln!(writer, "property list uchar int vertex_indices")?;
+        writeln!(writer, "end_header")?;
+
+        let mut out = PlyWriter { writer, format };
+        for v in 0..self.vertex_count() {
+            let mut floats: Vec<f32> = self.positions[v].iter().copied().collect();
+            if let Some(normals) = &self.normals {
+                floats.extend(normals[v].iter());
+            }
+            if let Some(uvs) = &self.uvs {
+                floats.extend(uvs[v].iter());
+            }
+            for value in floats {
+                out.float(value)?;
+            }
+            if let Some(colors) = &self.colors {
+                for &channel in colors[v].iter() {
+                    out.byte((channel.clamp(0.0, 1.0) * 255.0).round() as u8)?;
+                }
+            }
+            out.end_record()?;
+        }
+        for triangle in self.indices.chunks_exact(3) {
+            out.byte(3)?;
+            for &index in triangle {
+                out.int(index as i32)?;
+            }
+            out.end_record()?;
+        }
+        Ok(())
+    }
+}
+
+/// Where the known vertex properties are in a vertex record.
+struct VertexLayout {
+    position: [usize; 3],
+    normal: Option<[usize; 3]>,
+    uv: Option<[usize; 2]>,
+    /// The red, green, blue and alpha properties and their full intensity.
+    color: Option<[Option<(usize, f64)>; 4]>,
+}
+
+impl VertexLayout {
+    fn new(element: &Element) -> Result<VertexLayout, PlyError> {
+        let index = |names: &[&str]| element.scalar(names).map(|(i, _)| i);
+        let all = |names: &[&[&str]; 3]| -> Option<[usize; 3]> {
+            Some([index(names[0])?, index(names[1])?, index(names[2])?])
+        };
+        let position = all(&[&["x"], &["y"], &["z"]]).ok_or_else(|| {
+            PlyError::Body("vertex element needs x, y and z properties".to_string())
+        })?;
+        let channel = |names: &[&str]| {
+            element
+                .scalar(names)
+                .map(|(i, kind)| (i, kind.color_scale()))
+        };
+        let color = [
+            channel(&["red", "r"]),
+            channel(&["green", "g"]),
+            channel(&["blue", "b"]),
+            channel(&["alpha", "a"]),
+        ];
+        Ok(VertexLayout {
+            position,
+            normal: all(&[&["nx"], &["ny"], &["nz"]]),
+            uv: index(&["u", "s", "texture_u"])
+                .zip(index(&["v", "t", "texture_v"]))
+                .map(|(u, v)| [u, v]),
+            color: color[..3].iter().all(Option::is_some).then_some(color),
+        })
+    }
+
+    fn read(
+        &self,
+        record: &Record,
+        positions: &mut Vec<f32>,
+        normals: &mut Vec<Vector3<f32>>,
+        uvs: &mut Vec<Vector2<f32>>,
+        colors: &mut Vec<Vector4<f32>>,
+    ) {
+        let value = |i: usize| record[i][0] as f32;
+        positions.extend(self.position.map(value));
+        if let Some(normal) = self.normal {
+            normals.push(Vector3::from(normal.map(value)));
+        }
+        if let Some(uv) = self.uv {
+            uvs.push(Vector2::from(uv.map(value)));
+        }
+        if let Some(color) = self.color {
+            colors.push(Vector4::from(color.map(|channel| match channel {
+                Some((i, scale)) => (record[i][0] / scale) as f32,
+                None => 1.0,
+            })));
+        }
+    }
+}
+
+fn read_header<R: BufRead>(reader: &mut R) -> Result<(PlyFormat, Vec<Element>), PlyError> {
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    // The line of the last `element`, for reporting it without properties.
+    let mut element_line = 0;
+    let mut line = Vec::new();
+    for number in 1.. {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Err(header_error(number, "missing end_header"));
+        }
+        let text = String::from_utf8_lossy(&line);
+        let tokens: Vec<&str> = text.split_whitespace().collect();
+        if number == 1 {
+            if tokens != ["ply"] {
+                return Err(header_error(number, "not a PLY file"));
+            }
+            continue;
+        }
+        match tokens.as_slice() {
+            ["format", name, _version] => {
+                format = Some(match *name {
+                    "ascii" => PlyFormat::Ascii,
+                    "binary_little_endian" => PlyFormat::BinaryLittleEndian,
+                    "binary_big_endian" => PlyFormat::BinaryBigEndian,
+                    _ => return Err(header_error(number, &format!("unknown format `{}`", name))),
+                });
+            }
+            ["element", name, count] => {
+                check_properties(elements.last(), element_line)?;
+                element_line = number;
+                let count = count
+                    .parse()
+                    .map_err(|_| header_error(number, &format!("invalid count `{}`", count)))?;
+                elements.push(Element {
+                    name: name.to_string(),
+                    count,
+                    properties: Vec::new(),
+                });
+            }
+            ["property", "list", count, item, name] => {
+                let property = Property::List {
+                    name: name.to_string(),
+                    count: scalar(count, number)?,
+                    item: scalar(item, number)?,
+                };
+                elements
+                    .last_mut()
+                    .ok_or_else(|| header_error(number, "property before any element"))?
+                    .properties
+                    .push(property);
+            }
+            ["property", kind, name] => {
+                let property = Property::Scalar {
+                    name: name.to_string(),
+                    kind: scalar(kind, number)?,
+                };
+                elements
+                    .last_mut()
+                    .ok_or_else(|| header_error(number, "property before any element"))?
+                    .properties
+                    .push(property);
+            }
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            ["end_header"] => {
+                check_properties(elements.last(), element_line)?;
+                let format = format.ok_or_else(|| header_error(number, "missing format line"))?;
+                return Ok((format, elements));
+            }
+            _ => {
+                return Err(header_error(
+                    number,
+                    &format!("unexpected line `{}`", text.trim()),
+                ))
+            }
+        }
+    }
+    unreachable!("the header loop only ends by returning")
+}
+
+/// Rejects an element that has records but no properties. Its records take
+/// up no data, so a huge count would keep the reader busy without ever
+/// reaching the end of the file.
+fn check_properties(element: Option<&Element>, line: usize) -> Result<(), PlyError> {
+    match element {
+        Some(element) if element.count > 0 && element.properties.is_empty() => Err(header_error(
+            line,
+            &format!("element `{}` has no properties", element.name),
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn scalar(name: &str, line: usize) -> Result<Scalar, PlyError> {
+    Scalar::parse(name).ok_or_else(|| header_error(line, &format!("unknown type `{}`", name)))
+}
+
+fn header_error(line: usize, message: &str) -> PlyError {
+    PlyError::Header {
+        line,
+        message: message.to_string(),
+    }
+}
+
+/// Writes property values in the encoding of a PLY file.
+struct PlyWriter<W> {
+    writer: W,
+    format: PlyFormat,
+}
+
+impl<W: Write> PlyWriter<W> {
+    fn value(&mut self, text: String, little: &[u8], big: &[u8]) -> io::Result<()> {
+        match self.format {
+            PlyFormat::Ascii => write!(self.writer, "{} ", text),
+            PlyFormat::BinaryLittleEndian => self.writer.write_all(little),
+            PlyFormat::BinaryBigEndian => self.writer.write_all(big),
+        }
+    }
+
+    fn float(&mut self, value: f32) -> io::Result<()> {
+        self.value(
+            value.to_string(),
+            &value.to_le_bytes(),
+            &value.to_be_bytes(),
+        )
+    }
+
+    fn int(&mut self, value: i32) -> io::Result<()> {
+        self.value(
+            value.to_string(),
+            &value.to_le_bytes(),
+            &value.to_be_bytes(),
+        )
+    }
+
+    fn byte(&mut self, value: u8) -> io::Result<()> {
+        self.value(value.to_string(), &[value], &[value])
+    }
+
+    fn end_record(&mut self) -> io::Result<()> {
+        if self.format == PlyFormat::Ascii {
+            writeln!(self.writer)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQUARE: &str = "\
+ply
+format ascii 1.0
+comment a unit square with colored corners
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0 255 0 0
+1 0 0 0 255 0
+1 1 0 0 0 255
+0 1 0 255 255 255
+4 0 1 2 3
+";
+
+    fn colored_triangle() -> Mesh {
+        let mut mesh =
+            Mesh::new(&[0.0, 0.0, 0.0, 1.5, 0.0, 0.0, 0.0, -2.25, 0.1], &[0, 1, 2]).unwrap();
+        mesh.set_uvs_flat(&[0.0, 0.0, 1.0, 0.0, 0.0, 1.0]).unwrap();
+        mesh.set_colors_flat(&[1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0])
+            .unwrap();
+        mesh.compute_normals(crate::attributes::NormalWeighting::Area);
+        mesh
+    }
+
+    #[test]
+    fn test_read_ascii_with_colors() {
+        let mesh = Mesh::read_ply(SQUARE.as_bytes()).unwrap();
+        assert_eq!(mesh.vertex_count(), 4);
+        // The quad is split into a fan of two triangles.
+        assert_eq!(mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+        let colors = mesh.colors.unwrap();
+        assert_eq!(colors[1], Vector4::new(0.0, 1.0, 0.0, 1.0));
+        assert_eq!(colors[3], Vector4::new(1.0, 1.0, 1.0, 1.0));
+        assert!(mesh.normals.is_none() && mesh.uvs.is_none());
+    }
+
+    #[test]
+    fn test_round_trip_in_every_format() {
+        let mesh = colored_triangle();
+        for format in [
+            PlyFormat::Ascii,
+            PlyFormat::BinaryLittleEndian,
+            PlyFormat::BinaryBigEndian,
+        ] {
+            let mut buffer = Vec::new();
+            mesh.write_ply(&mut buffer, format).unwrap();
+            let loaded = Mesh::read_ply(buffer.as_slice()).unwrap();
+            assert_eq!(loaded.positions, mesh.positions, "{:?}", format);
+            assert_eq!(loaded.indices, mesh.indices);
+            assert_eq!(loaded.normals, mesh.normals);
+            assert_eq!(loaded.uvs, mesh.uvs);
+            assert_eq!(loaded.colors, mesh.colors);
+        }
+    }
+
+    #[test]
+    fn test_read_skips_unknown_elements_and_properties() {
+        let mut data = b"ply\nformat binary_little_endian 1.0\nelement vertex 3\n\
+property double x\nproperty double y\nproperty double z\nproperty short quality\n\
+element edge 1\nproperty int vertex1\nproperty int vertex2\n\
+element face 1\nproperty list uchar uint vertex_indices\nend_header\n"
+            .to_vec();
+        for (i, point) in [[0.0f64, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]
+            .iter()
+            .enumerate()
+        {
+            for c in point {
+                data.extend(c.to_le_bytes());
+            }
+            data.extend((i as i16).to_le_bytes());
+        }
+        data.extend([0, 0, 0, 0, 1, 0, 0, 0]);
+        data.push(3);
+        for index in [0u32, 1, 2] {
+            data.extend(index.to_le_bytes());
+        }
+
+        let mesh = Mesh::read_ply(data.as_slice()).unwrap();
+        assert_eq!(mesh.positions[1], Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(mesh.indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_read_reports_errors() {
+        match Mesh::read_ply("ply\nformat ascii 1.0\nproperty float x\n".as_bytes()) {
+            Err(PlyError::Header { line, .. }) => assert_eq!(line, 3),
+            other => panic!("expected a header error, got {:?}", other),
+        }
+        let truncated = SQUARE.replace("4 0 1 2 3\n", "4 0 1 2\n");
+        assert!(matches!(
+            Mesh::read_ply(truncated.as_bytes()),
+            Err(PlyError::Body(_))
+        ));
+        let out_of_range = SQUARE.replace("4 0 1 2 3\n", "3 0 1 7\n");
+        assert!(matches!(
+            Mesh::read_ply(out_of_range.as_bytes()),
+            Err(PlyError::Mesh(MeshError::IndexOutOfBounds { .. }))
+        ));
+        let fractional = SQUARE.replace("4 0 1 2 3\n", "3 0 1.5 2\n");
+        assert!(matches!(
+            Mesh::read_ply(fractional.as_bytes()),
+            Err(PlyError::Body(_))
+        ));
+    }
+
+    #[test]
+    fn test_read_rejects_elements_without_properties() {
+        let empty = "ply\nformat ascii 1.0\nelement foo 18446744073709551615\n\
+                     element vertex 0\nproperty float x\nend_header\n";
+        match Mesh::read_ply(empty.as_bytes()) {
+            Err(PlyError::Header { line, .. }) => assert_eq!(line, 3),
+            other => panic!("expected a header error, got {:?}", other),
+        }
+        let last = "ply\nformat binary_little_endian 1.0\nelement foo 5\nend_header\n";
+        assert!(matches!(
+            Mesh::read_ply(last.as_bytes()),
+            Err(PlyError::Header { line: 3, .. })
+        ));
+        // Declaring no records is harmless.
+        let none = "ply\nformat ascii 1.0\nelement foo 0\nend_header\n";
+        assert!(Mesh::read_ply(none.as_bytes()).is_ok());
+    }
+}
@@ -0,0 +1,152 @@
+//! Weighted vertex selections for region-based editing.
+
+use crate::Mesh;
+
+/// How the weight of a selected vertex falls off with its distance from the
+/// center of the selection.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Falloff {
+    /// Every vertex within the radius has full weight.
+    Constant,
+    /// The weight decreases linearly to zero at the radius.
+    Linear,
+    /// The weight follows a smoothstep curve, which is flat at the center and
+    /// at the radius, so the edge of the region does not crease.
+    #[default]
+    Smooth,
+    /// The weight follows a Gaussian bell that has dropped to about 1% at
+    /// the radius.
+    Gaussian,
+}
+
+impl Falloff {
+    /// Returns the weight of a vertex at a fraction `t` of the radius, where
+    /// `t` is between 0 (the center) and 1 (the edge).
+    pub fn weight(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Falloff::Constant => 1.0,
+            Falloff::Linear => 1.0 - t,
+            Falloff::Smooth => 1.0 - t * t * (3.0 - 2.0 * t),
+            Falloff::Gaussian => (-4.5 * t * t).exp(),
+        }
+    }
+}
+
+/// A set of vertices with a weight between 0 and 1 for each.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Selection {
+    /// The selected vertices.
+    pub vertices: Vec<usize>,
+    /// The weight of each selected vertex.
+    pub weights: Vec<f32>,
+}
+
+impl Selection {
+    /// Selects the vertices within a radius, weighted by their distance.
+    ///
+    /// # Arguments
+    ///
+    /// * `distances` - The distance of each vertex from the center, such as
+    ///   the result of [`Mesh::geodesic_distances`].
+    /// * `radius` - The distance up to which vertices are selected.
+    /// * `falloff` - How the weights fall off towards the radius.
+    ///
+    /// # Returns
+    ///
+    /// The selection, ordered by vertex. Vertices at distance zero always have
+    /// full weight, even when `radius` is zero.
+    pub fn from_distances(distances: &[f32], radius: f32, falloff: Falloff) -> Selection {
+        let mut selection = Selection::default();
+        for (vertex, &distance) in distances.iter().enumerate() {
+            if distance > radius {
+                continue;
+            }
+            let weight = if distance <= 0.0 {
+                1.0
+            } else {
+                falloff.weight(distance / radius)
+            };
+            selection.vertices.push(vertex);
+            selection.weights.push(weight);
+        }
+        selection
+    }
+
+    /// Returns the number of selected vertices.
+    pub fn len(&self) -> usize {
+        self.vertices.len()
+    }
+
+    /// Returns `true` if no vertices are selected.
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    /// Returns the selected vertices together with their weights.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, f32)> + '_ {
+        self.vertices
+            .iter()
+            .copied()
+            .zip(self.weights.iter().copied())
+    }
+}
+
+impl Mesh {
+    /// Selects the vertices within a geodesic radius of a vertex.
+    ///
+    /// # Arguments
+    ///
+    /// * `center` - The vertex at the center of the region.
+    /// * `radius` - The distance along the surface up to which vertices are
+    ///   selected.
+    /// * `falloff` - How the weights fall off towards the radius.
+    pub fn select_geodesic(&self, center: usize, radius: f32, falloff: Falloff) -> Selection {
+        let distances = self.geodesic_distances(&[center], radius);
+        Selection::from_distances(&distances, radius, falloff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_falloff_weights() {
+        for falloff in [Falloff::Linear, Falloff::Smooth, Falloff::Gaussian] {
+            assert_eq!(falloff.weight(0.0), 1.0);
+            assert!(falloff.weight(1.0) < 0.02);
+            assert!(falloff.weight(0.25) > falloff.weight(0.5));
+        }
+        assert_eq!(Falloff::Constant.weight(1.0), 1.0);
+        assert_eq!(Falloff::Smooth.weight(0.5), 0.5);
+    }
+
+    #[test]
+    fn test_from_distances_skips_far_vertices() {
+        let distances = [0.0, 0.5, 1.0, 2.0, f32::INFINITY];
+        let selection = Selection::from_distances(&distances, 1.0, Falloff::Linear);
+        assert_eq!(selection.vertices, vec![0, 1, 2]);
+        assert_eq!(selection.weights, vec![1.0, 0.5, 0.0]);
+
+        let selection = Selection::from_distances(&distances, 0.0, Falloff::Linear);
+        assert_eq!(selection.vertices, vec![0]);
+        assert_eq!(selection.weights, vec![1.0]);
+    }
+
+    #[test]
+    fn test_select_geodesic() {
+        // A fan of four triangles around vertex 0, and a fifth triangle
+        // reaching out to vertex 5 at x = 3.
+        let positions = [
+            0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, -1.0, 0.0, 0.0, 0.0, -1.0, 0.0, 3.0, 0.0,
+            0.0,
+        ];
+        let indices = [0, 1, 2, 0, 2, 3, 0, 3, 4, 0, 4, 1, 1, 5, 2];
+        let mesh = Mesh::new(&positions, &indices).unwrap();
+        let selection = mesh.select_geodesic(0, 2.0, Falloff::Constant);
+        assert_eq!(selection.len(), 5);
+        assert!(!selection.vertices.contains(&5));
+        assert!(selection.iter().all(|(_, weight)| weight == 1.0));
+    }
+}
@@ -0,0 +1,408 @@
+//! Joint hierarchies and skinning with linear blend or dual quaternions.
+
+use crate::{Mesh, MeshError};
+use nalgebra::{DualQuaternion, Isometry3, Point3, Quaternion, UnitDualQuaternion, Vector3};
+
+/// A joint of a skeleton.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Joint {
+    /// The name of the joint, such as `"jaw"`.
+    pub name: String,
+    /// The parent joint, or `None` for a root joint. Parents always come
+    /// before their children in the skeleton.
+    pub parent: Option<usize>,
+    /// The transform of the joint relative to its parent in the bind pose,
+    /// the pose the mesh was modelled in.
+    pub bind: Isometry3<f32>,
+    /// The current transform of the joint relative to its parent.
+    pub pose: Isometry3<f32>,
+}
+
+/// A hierarchy of joints that deforms a skinned mesh.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Skeleton {
+    /// The joints, with every parent before its children.
+    pub joints: Vec<Joint>,
+}
+
+impl Skeleton {
+    /// Creates an empty `Skeleton`.
+    pub fn new() -> Skeleton {
+        Skeleton::default()
+    }
+
+    /// Adds a joint in its bind pose.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the joint.
+    /// * `parent` - The parent joint, or `None` for a root joint.
+    /// * `bind` - The transform of the joint relative to its parent in the
+    ///   bind pose. The current pose starts out equal to it.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the index of the new joint, or a `MeshError` if
+    /// the parent does not exist.
+    pub fn add_joint(
+        &mut self,
+        name: impl Into<String>,
+        parent: Option<usize>,
+        bind: Isometry3<f32>,
+    ) -> Result<usize, MeshError> {
+        if let Some(parent) = parent.filter(|&p| p >= self.joints.len()) {
+            return Err(MeshError::JointOutOfBounds {
+                joint: parent,
+                joint_count: self.joints.len(),
+            });
+        }
+        self.joints.push(Joint {
+            name: name.into(),
+            parent,
+            bind,
+            pose: bind,
+        });
+        Ok(self.joints.len() - 1)
+    }
+
+    /// Returns the index of the first joint with a given name.
+    pub fn joint_index(&self, name: &str) -> Option<usize> {
+        self.joints.iter().position(|j| j.name == name)
+    }
+
+    /// Returns the bind pose of each joint in model space.
+    pub fn bind_transforms(&self) -> Vec<Isometry3<f32>> {
+        self.model_transforms(|joint| &joint.bind)
+    }
+
+    /// Returns the current pose of each joint in model space.
+    pub fn pose_transforms(&self) -> Vec<Isometry3<f32>> {
+        self.model_transforms(|joint| &joint.pose)
+    }
+
+    /// Returns, for each joint, the transform that takes a point attached to
+    /// the joint from its bind pose position to its current position.
+    pub fn skinning_transforms(&self) -> Vec<Isometry3<f32>> {
+        self.pose_transforms()
+            .iter()
+            .zip(self.bind_transforms())
+            .map(|(pose, bind)| pose * bind.inverse())
+            .collect()
+    }
+
+    fn model_transforms(&self, local: impl Fn(&Joint) -> &Isometry3<f32>) -> Vec<Isometry3<f32>> {
+        let mut transforms: Vec<Isometry3<f32>> = Vec::with_capacity(self.joints.len());
+        for joint in &self.joints {
+            let transform = match joint.parent {
+                Some(parent) => transforms[parent] * local(joint),
+                None => *local(joint),
+            };
+            transforms.push(transform);
+        }
+        transforms
+    }
+}
+
+/// The joints that influence a vertex and how strongly.
+///
+/// Weights of unused slots are zero. The weights of a vertex should sum to
+/// one; a vertex whose weights are all zero is not moved by skinning.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SkinWeights {
+    /// The influencing joints.
+    pub joints: [u16; 4],
+    /// The weight of each joint.
+    pub weights: [f32; 4],
+}
+
+impl SkinWeights {
+    /// Returns influences attached to a single joint.
+    pub fn single(joint: u16) -> SkinWeights {
+        SkinWeights {
+            joints: [joint, 0, 0, 0],
+            weights: [1.0, 0.0, 0.0, 0.0],
+        }
+    }
+
+    /// Returns the joints with a non-zero weight, together with the weights.
+    pub fn influences(&self) -> impl Iterator<Item = (usize, f32)> + '_ {
+        self.joints
+            .iter()
+            .zip(&self.weights)
+            .filter(|(_, &w)| w != 0.0)
+            .map(|(&j, &w)| (j as usize, w))
+    }
+
+    /// Mixes the influences of several vertices, keeping the four strongest
+    /// joints and renormalizing their weights.
+    pub(crate) fn blend(sources: impl Iterator<Item = (SkinWeights, f32)>) -> SkinWeights {
+        let mut totals: Vec<(u16, f32)> = Vec::new();
+        for (source, factor) in sources {
+            for (joint, weight) in source.influences() {
+                let joint = joint as u16;
+                match totals.iter_mut().find(|(j, _)| *j == joint) {
+                    Some((_, total)) => *total += weight * factor,
+                    None => totals.push((joint, weight * factor)),
+                }
+            }
+        }
+        totals.sort_by(|a, b| b.1.total_cmp(&a.1));
+        totals.truncate(4);
+        let sum: f32 = totals.iter().map(|(_, w)| w).sum();
+        let mut blended = SkinWeights::default();
+        if sum > 0.0 {
+            for (slot, (joint, weight)) in totals.into_iter().enumerate() {
+                blended.joints[slot] = joint;
+                blended.weights[slot] = weight / sum;
+            }
+        }
+        blended
+    }
+}
+
+/// How the transforms of the joints influencing a vertex are combined.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SkinningMethod {
+    /// Linear blend skinning: the vertex is moved by each joint and the
+    /// results are averaged. This is fast, but loses volume where joints
+    /// twist or bend sharply (the "candy wrapper" effect).
+    #[default]
+    Linear,
+    /// Dual quaternion skinning: the joint transforms are blended as rigid
+    /// transforms, which preserves volume at twisting joints.
+    DualQuaternion,
+}
+
+impl Mesh {
+    /// Sets the joint influences of every vertex.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` that fails with a `MeshError` if there is not exactly one
+    /// entry per vertex.
+    pub fn set_skin_weights(&mut self, skin_weights: Vec<SkinWeights>) -> Result<(), MeshError> {
+        if skin_weights.len() != self.vertex_count() {
+            return Err(MeshError::AttributeLength {
+                attribute: "skin weights",
+                expected: self.vertex_count(),
+                actual: skin_weights.len(),
+            });
+        }
+        self.skin_weights = Some(skin_weights);
+        Ok(())
+    }
+
+    /// Deforms a shape by the current pose of a skeleton.
+    ///
+    /// # Arguments
+    ///
+    /// * `base` - The position of each vertex in the bind pose, for example
+    ///   the result of [`Mesh::blend_morph_targets`]. The vertex positions of
+    ///   the mesh are not used, since a simulation keeps changing them.
+    /// * `skeleton` - The posed skeleton.
+    /// * `method` - How the joint transforms are combined.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the skinned position of each vertex, or a
+    /// `MeshError` if a vertex refers to a joint the skeleton does not have.
+    /// Without skin weights the base shape is returned unchanged.
+    pub fn skin_positions(
+        &self,
+        base: &[Vector3<f32>],
+        skeleton: &Skeleton,
+        method: SkinningMethod,
+    ) -> Result<Vec<Vector3<f32>>, MeshError> {
+        let Some(skin_weights) = &self.skin_weights else {
+            return Ok(base.to_vec());
+        };
+        let joint_count = skeleton.joints.len();
+        if let Some((joint, _)) = skin_weights
+            .iter()
+            .flat_map(SkinWeights::influences)
+            .find(|&(joint, _)| joint >= joint_count)
+        {
+            return Err(MeshError::JointOutOfBounds { joint, joint_count });
+        }
+
+        let transforms = skeleton.skinning_transforms();
+        let positions = base.iter().zip(skin_weights).map(|(position, influences)| {
+            let point = Point3::from(*position);
+            match method {
+                SkinningMethod::Linear => linear_blend(&transforms, influences, &point),
+                SkinningMethod::DualQuaternion => {
+                    let dual_quaternions: Vec<UnitDualQuaternion<f32>> = influences
+                        .influences()
+                        .map(|(joint, _)| UnitDualQuaternion::from_isometry(&transforms[joint]))
+                        .collect();
+                    dual_quaternion_blend(&dual_quaternions, influences, &point)
+                }
+            }
+            .unwrap_or(point)
+            .coords
+        });
+        Ok(positions.collect())
+    }
+}
+
+/// Averages the positions a point is moved to by each influencing joint.
+fn linear_blend(
+    transforms: &[Isometry3<f32>],
+    influences: &SkinWeights,
+    point: &Point3<f32>,
+) -> Option<Point3<f32>> {
+    let mut sum = Vector3::zeros();
+    let mut total = 0.0;
+    for (joint, weight) in influences.influences() {
+        sum += transforms[joint].transform_point(point).coords * weight;
+        total += weight;
+    }
+    (total != 0.0).then(|| Point3::from(sum / total))
+}
+
+/// Blends the dual quaternions of the influencing joints, in the order of
+/// `influences`, and moves a point by the result.
+fn dual_quaternion_blend(
+    dual_quaternions: &[UnitDualQuaternion<f32>],
+    influences: &SkinWeights,
+    point: &Point3<f32>,
+) -> Option<Point3<f32>> {
+    let first = dual_quaternions.first()?;
+    let mut real = Quaternion::new(0.0, 0.0, 0.0, 0.0);
+    let mut dual = Quaternion::new(0.0, 0.0, 0.0, 0.0);
+    for (dq, (_, weight)) in dual_quaternions.iter().zip(influences.influences()) {
+        // `q` and `-q` are the same rotation; blend along the shorter arc.
+        let sign = if dq.real.coords.dot(&first.real.coords) < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+        real += dq.real * (weight * sign);
+        dual += dq.dual * (weight * sign);
+    }
+    if real.norm() <= f32::EPSILON {
+        return None;
+    }
+    let blended = UnitDualQuaternion::new_normalize(DualQuaternion::from_real_and_dual(real, dual));
+    Some(blended.transform_point(point))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{Translation3, UnitQuaternion};
+    use std::f32::consts::FRAC_PI_2;
+
+    // A neck joint at the origin with a head joint one unit above it.
+    fn neck_and_head() -> Skeleton {
+        let mut skeleton = Skeleton::new();
+        let neck = skeleton
+            .add_joint("neck", None, Isometry3::identity())
+            .unwrap();
+        skeleton
+            .add_joint("head", Some(neck), Isometry3::translation(0.0, 1.0, 0.0))
+            .unwrap();
+        skeleton
+    }
+
+    fn turn(angle: f32) -> Isometry3<f32> {
+        Isometry3::from_parts(
+            Translation3::identity(),
+            UnitQuaternion::from_axis_angle(&Vector3::y_axis(), angle),
+        )
+    }
+
+    #[test]
+    fn test_add_joint_rejects_missing_parent() {
+        let mut skeleton = neck_and_head();
+        assert_eq!(
+            skeleton.add_joint("jaw", Some(5), Isometry3::identity()),
+            Err(MeshError::JointOutOfBounds {
+                joint: 5,
+                joint_count: 2
+            })
+        );
+        assert_eq!(skeleton.joint_index("head"), Some(1));
+    }
+
+    #[test]
+    fn test_children_follow_parent_pose() {
+        let mut skeleton = neck_and_head();
+        skeleton.joints[0].pose = Isometry3::translation(1.0, 0.0, 0.0);
+        let pose = skeleton.pose_transforms();
+        assert!((pose[1].translation.vector - Vector3::new(1.0, 1.0, 0.0)).norm() < 1e-6);
+        let skinning = skeleton.skinning_transforms();
+        assert!((skinning[1].translation.vector - Vector3::x()).norm() < 1e-6);
+    }
+
+    #[test]
+    fn test_linear_skinning_turns_head() {
+        let positions = [1.0, 1.0, 0.0, 1.0, 0.0, 0.0, 0.0, 2.0, 0.0];
+        let mut mesh = Mesh::new(&positions, &[0, 1, 2]).unwrap();
+        mesh.set_skin_weights(vec![
+            SkinWeights::single(1),
+            SkinWeights::single(0),
+            SkinWeights::default(),
+        ])
+        .unwrap();
+        let mut skeleton = neck_and_head();
+        skeleton.joints[1].pose = Isometry3::translation(0.0, 1.0, 0.0) * turn(FRAC_PI_2);
+
+        let base: Vec<Vector3<f32>> = mesh.positions.clone();
+        let skinned = mesh
+            .skin_positions(&base, &skeleton, SkinningMethod::Linear)
+            .unwrap();
+        assert!((skinned[0] - Vector3::new(0.0, 1.0, -1.0)).norm() < 1e-6);
+        assert_eq!(skinned[1], base[1]);
+        // Vertices without influences stay where they are.
+        assert_eq!(skinned[2], base[2]);
+
+        mesh.skin_weights.as_mut().unwrap()[2] = SkinWeights::single(7);
+        assert_eq!(
+            mesh.skin_positions(&base, &skeleton, SkinningMethod::Linear),
+            Err(MeshError::JointOutOfBounds {
+                joint: 7,
+                joint_count: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_dual_quaternion_skinning_preserves_volume() {
+        let mut mesh = Mesh::new(&[1.0, 0.0, 0.0], &[]).unwrap();
+        mesh.set_skin_weights(vec![SkinWeights {
+            joints: [0, 1, 0, 0],
+            weights: [0.5, 0.5, 0.0, 0.0],
+        }])
+        .unwrap();
+        let mut skeleton = Skeleton::new();
+        skeleton
+            .add_joint("a", None, Isometry3::identity())
+            .unwrap();
+        skeleton
+            .add_joint("b", None, Isometry3::identity())
+            .unwrap();
+        skeleton.joints[0].pose = turn(-FRAC_PI_2);
+        skeleton.joints[1].pose = turn(FRAC_PI_2);
+
+        let base = [Vector3::x()];
+        let linear = mesh
+            .skin_positions(&base, &skeleton, SkinningMethod::Linear)
+            .unwrap();
+        let dual = mesh
+            .skin_positions(&base, &skeleton, SkinningMethod::DualQuaternion)
+            .unwrap();
+        // Averaging opposite quarter turns collapses the point onto the axis,
+        // while blending the rotations keeps it at its original distance.
+        assert!(linear[0].norm() < 1e-6);
+        assert!((dual[0] - Vector3::x()).norm() < 1e-6);
+    }
+
+    #[test]
+    fn test_blend_keeps_strongest_joints() {
+        let sources = (0..5).map(|j| (SkinWeights::single(j), j as f32 + 1.0));
+        let blended = SkinWeights::blend(sources);
+        assert_eq!(blended.joints, [4, 3, 2, 1]);
+        assert!((blended.weights.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+    }
+}
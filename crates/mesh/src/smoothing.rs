@@ -0,0 +1,276 @@
+//! Laplacian and Taubin smoothing of vertex positions.
+
+use crate::topology::Topology;
+use crate::Mesh;
+use nalgebra::Vector3;
+
+/// How the neighbours of a vertex are weighted when smoothing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LaplacianWeights {
+    /// Every neighbour counts equally. This also evens out the spacing of
+    /// the vertices, which slides them across the surface.
+    #[default]
+    Uniform,
+    /// Each edge is weighted by the cotangents of the angles opposite to it.
+    /// This only moves vertices across the surface where it is curved, so
+    /// flat regions keep their triangulation.
+    Cotangent,
+}
+
+impl Mesh {
+    /// Smooths the mesh by moving vertices towards the weighted average of
+    /// their neighbours.
+    ///
+    /// Plain Laplacian smoothing shrinks the surface a little with every
+    /// iteration; see [`Mesh::smooth_taubin`] for a variant that does not.
+    /// Boundary vertices are never moved, and the smoothed vertices have
+    /// their previous position reset so the physics simulation does not see
+    /// the change as velocity.
+    ///
+    /// # Arguments
+    ///
+    /// * `weights` - How neighbours are weighted.
+    /// * `lambda` - The fraction of the way each vertex moves towards the
+    ///   average per iteration, usually between 0 and 1.
+    /// * `iterations` - How many smoothing steps to apply.
+    /// * `selection` - The vertices to smooth, or `None` to smooth all of them.
+    ///   Out of range indices are ignored.
+    pub fn smooth_laplacian(
+        &mut self,
+        weights: LaplacianWeights,
+        lambda: f32,
+        iterations: usize,
+        selection: Option<&[usize]>,
+    ) {
+        self.smooth_with_factors(weights, &[lambda], iterations, selection);
+    }
+
+    /// Smooths the mesh with Taubin's λ|μ algorithm, which removes noise
+    /// without shrinking the surface.
+    ///
+    /// Each iteration applies a Laplacian step with `lambda` followed by one
+    /// with the negative factor `mu`, which inflates the surface back. `mu`
+    /// should be slightly larger in magnitude than `lambda`, for example
+    /// `lambda = 0.5` and `mu = -0.53`. Boundary vertices are never moved.
+    ///
+    /// # Arguments
+    ///
+    /// * `weights` - How neighbours are weighted.
+    /// * `lambda` - The positive (shrinking) factor.
+    /// * `mu` - The negative (inflating) factor.
+    /// * `iterations` - How many pairs of steps to apply.
+    /// * `selection` - The vertices to smooth, or `None` to smooth all of them.
+    ///   Out of range indices are ignored.
+    pub fn smooth_taubin(
+        &mut self,
+        weights: LaplacianWeights,
+        lambda: f32,
+        mu: f32,
+        iterations: usize,
+        selection: Option<&[usize]>,
+    ) {
+        self.smooth_with_factors(weights, &[lambda, mu], iterations, selection);
+    }
+
+    fn smooth_with_factors(
+        &mut self,
+        weights: LaplacianWeights,
+        factors: &[f32],
+        iterations: usize,
+        selection: Option<&[usize]>,
+    ) {
+        let topology = self.topology();
+        let mut active = match selection {
+            Some(vertices) => {
+                let mut active = vec![false; self.vertex_count()];
+                for &v in vertices {
+                    if let Some(active) = active.get_mut(v) {
+                        *active = true;
+                    }
+                }
+                active
+            }
+            None => vec![true; self.vertex_count()],
+        };
+        for (v, active) in active.iter_mut().enumerate() {
+            *active &= !topology.is_boundary_vertex(v);
+        }
+
+        let mut positions: Vec<Vector3<f32>> = self.positions.clone();
+        for _ in 0..iterations {
+            for &factor in factors {
+                laplacian_step(&topology, weights, factor, &active, &mut positions);
+            }
+        }
+
+        for (v, position) in positions.into_iter().enumerate() {
+            if active[v] {
+                self.teleport_vertex(v, position);
+            }
+        }
+    }
+}
+
+/// Moves each active vertex `factor` of the way towards the weighted average
+/// of its neighbours.
+fn laplacian_step(
+    topology: &Topology,
+    weights: LaplacianWeights,
+    factor: f32,
+    active: &[bool],
+    positions: &mut [Vector3<f32>],
+) {
+    let mut offsets = vec![Vector3::zeros(); positions.len()];
+    let mut totals = vec![0.0f32; positions.len()];
+    // The uniform offsets, for vertices whose weights do not sum to a
+    // positive value.
+    let mut uniform_offsets = vec![Vector3::zeros(); positions.len()];
+    let mut degrees = vec![0usize; positions.len()];
+    for (edge, &[a, b]) in topology.edges().iter().enumerate() {
+        let weight = match weights {
+            LaplacianWeights::Uniform => 1.0,
+            LaplacianWeights::Cotangent => cotangent_weight(topology, edge, positions),
+        };
+        let delta = positions[b] - positions[a];
+        offsets[a] += delta * weight;
+        offsets[b] -= delta * weight;
+        totals[a] += weight;
+        totals[b] += weight;
+        uniform_offsets[a] += delta;
+        uniform_offsets[b] -= delta;
+        degrees[a] += 1;
+        degrees[b] += 1;
+    }
+
+    for (v, position) in positions.iter_mut().enumerate() {
+        if !active[v] {
+            continue;
+        }
+        // Dividing by a sum that is not positive would leave the vertex in
+        // place or push it away from its neighbours, so fall back to uniform
+        // weights.
+        if totals[v] > f32::EPSILON {
+            *position += offsets[v] * (factor / totals[v]);
+        } else if degrees[v] > 0 {
+            *position += uniform_offsets[v] * (factor / degrees[v] as f32);
+        }
+    }
+}
+
+/// Returns half the sum of the cotangents of the angles opposite an edge.
+fn cotangent_weight(topology: &Topology, edge: usize, positions: &[Vector3<f32>]) -> f32 {
+    let [a, b] = topology.edges()[edge];
+    let sum: f32 = topology
+        .edge_half_edges(edge)
+        .iter()
+        .map(|&h| {
+            let apex = positions[topology.opposite_vertex(h)];
+            let (u, v) = (positions[a] - apex, positions[b] - apex);
+            let sine = u.cross(&v).norm();
+            if sine > f32::EPSILON {
+                u.dot(&v) / sine
+            } else {
+                0.0
+            }
+        })
+        .sum();
+    sum * 0.5
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A flat `n` x `n` grid of unit squares in the xy-plane, with every
+    // square split along the diagonal from its lower-left corner.
+    fn grid(n: usize) -> Mesh {
+        let positions: Vec<f32> = (0..n * n)
+            .flat_map(|v| [(v % n) as f32, (v / n) as f32, 0.0])
+            .collect();
+        let mut indices = Vec::new();
+        for y in 0..n - 1 {
+            for x in 0..n - 1 {
+                let v = (y * n + x) as u32;
+                let n = n as u32;
+                indices.extend_from_slice(&[v, v + 1, v + n + 1, v, v + n + 1, v + n]);
+            }
+        }
+        Mesh::new(&positions, &indices).unwrap()
+    }
+
+    #[test]
+    fn test_uniform_smoothing_flattens_spike() {
+        let mut mesh = grid(3);
+        mesh.positions[4].z = 1.0;
+        mesh.smooth_laplacian(LaplacianWeights::Uniform, 1.0, 1, None);
+        assert!((mesh.positions[4] - Vector3::new(1.0, 1.0, 0.0)).norm() < 1e-6);
+        assert_eq!(mesh.old_positions[4], mesh.positions[4]);
+        // Boundary vertices stay where they are.
+        assert_eq!(mesh.positions[0], Vector3::zeros());
+    }
+
+    #[test]
+    fn test_cotangent_smoothing_keeps_flat_triangulation() {
+        let mut mesh = grid(3);
+        let offset = Vector3::new(1.1, 0.9, 0.0);
+        mesh.positions[4] = offset;
+
+        let mut cotangent = mesh.clone();
+        cotangent.smooth_laplacian(LaplacianWeights::Cotangent, 0.5, 5, None);
+        assert!((cotangent.positions[4] - offset).norm() < 1e-5);
+
+        mesh.smooth_laplacian(LaplacianWeights::Uniform, 0.5, 5, None);
+        assert!((mesh.positions[4] - offset).norm() > 0.1);
+    }
+
+    #[test]
+    fn test_smoothing_respects_selection() {
+        let mut mesh = grid(4);
+        mesh.positions[5].z = 1.0;
+        mesh.positions[6].z = 1.0;
+        mesh.smooth_laplacian(LaplacianWeights::Uniform, 0.5, 3, Some(&[5]));
+        assert!(mesh.positions[5].z < 1.0);
+        assert_eq!(mesh.positions[6].z, 1.0);
+    }
+
+    #[test]
+    fn test_taubin_shrinks_less_than_laplacian() {
+        let n = 9;
+        let mut mesh = grid(n);
+        let peak = (n / 2) * n + n / 2;
+        for (v, position) in mesh.positions.iter_mut().enumerate() {
+            let (x, y) = ((v % n) as f32, (v / n) as f32);
+            let scale = std::f32::consts::PI / (n - 1) as f32;
+            position.z = (x * scale).sin() * (y * scale).sin();
+        }
+
+        let mut laplacian = mesh.clone();
+        laplacian.smooth_laplacian(LaplacianWeights::Uniform, 0.5, 10, None);
+        mesh.smooth_taubin(LaplacianWeights::Uniform, 0.5, -0.53, 10, None);
+        assert!(laplacian.positions[peak].z < 0.8);
+        assert!(mesh.positions[peak].z > 0.95);
+    }
+
+    #[test]
+    fn test_cotangent_smoothing_of_collapsed_triangles() {
+        // A fan whose triangles have all collapsed onto a line, with angles
+        // of 180 degrees, so every cotangent weight is 0. The vertex falls
+        // back to the uniform average of its neighbours instead of staying
+        // put.
+        let positions = [
+            0.5, 0.0, 0.0, 2.0, 0.0, 0.0, 1.0, 0.0, 0.0, -2.0, 0.0, 0.0, -1.0, 0.0, 0.0,
+        ];
+        let indices = [0, 1, 2, 0, 2, 3, 0, 3, 4, 0, 4, 1];
+        let mut mesh = Mesh::new(&positions, &indices).unwrap();
+        mesh.smooth_laplacian(LaplacianWeights::Cotangent, 1.0, 1, None);
+        assert!(mesh.positions[0].norm() < 1e-6);
+    }
+
+    #[test]
+    fn test_smoothing_ignores_out_of_range_selection() {
+        let mut mesh = grid(3);
+        mesh.positions[4].z = 1.0;
+        mesh.smooth_laplacian(LaplacianWeights::Uniform, 1.0, 1, Some(&[4, 100]));
+        assert_eq!(mesh.positions[4].z, 0.0);
+    }
+}
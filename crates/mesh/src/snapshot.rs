@@ -0,0 +1,648 @@
+//! A compact, versioned binary format for meshes and their simulation state.
+//!
+//! A snapshot starts with a fixed header:
+//!
+//! | Bytes | Content                                                   |
+//! |-------|-----------------------------------------------------------|
+//! | 4     | The magic bytes `MSNP`                                    |
+//! | 2     | The format version                                        |
+//! | 2     | Flags for the optional sections that follow               |
+//! | 4     | The number of vertices                                    |
+//! | 4     | The number of indices                                     |
+//! | 1     | Bits per quantized coordinate, or 0 for 32-bit floats     |
+//!
+//! It is followed by the positions (quantized against the bounding box,
+//! which is stored first), the previous positions and inverse masses if
+//! the simulation state was saved, the indices as variable-length deltas, and
+//! then normals, texture coordinates and colors if present. All values are
+//! little-endian.
+//!
+//! Version 1 stored masses instead of inverse masses, with 0 for immovable
+//! vertices. They are converted when a version 1 snapshot is read.
+
+use crate::{Mesh, MeshError};
+use nalgebra::{Vector2, Vector3, Vector4};
+use std::fmt;
+
+/// The magic bytes at the start of every snapshot.
+const MAGIC: [u8; 4] = *b"MSNP";
+
+/// The version written by [`Mesh::to_snapshot`].
+pub const SNAPSHOT_VERSION: u16 = 2;
+
+const HAS_NORMALS: u16 = 1 << 0;
+const HAS_UVS: u16 = 1 << 1;
+const HAS_COLORS: u16 = 1 << 2;
+const HAS_SIMULATION: u16 = 1 << 3;
+
+/// An error that can occur while reading a snapshot.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SnapshotError {
+    /// The data does not start with the snapshot magic bytes.
+    BadMagic,
+    /// The snapshot was written by a newer version of the format.
+    UnsupportedVersion {
+        /// The version stored in the snapshot.
+        version: u16,
+    },
+    /// The data ends before the snapshot does.
+    UnexpectedEnd,
+    /// A section of the snapshot holds values that cannot be decoded.
+    InvalidData(String),
+    /// The snapshot decoded, but does not describe a valid mesh.
+    Mesh(MeshError),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::BadMagic => write!(f, "not a mesh snapshot"),
+            SnapshotError::UnsupportedVersion { version } => write!(
+                f,
+                "unsupported snapshot version {} (expected at most {})",
+                version, SNAPSHOT_VERSION
+            ),
+            SnapshotError::UnexpectedEnd => write!(f, "snapshot is truncated"),
+            SnapshotError::InvalidData(message) => write!(f, "invalid snapshot: {}", message),
+            SnapshotError::Mesh(err) => write!(f, "invalid snapshot mesh: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SnapshotError::Mesh(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<MeshError> for SnapshotError {
+    fn from(err: MeshError) -> Self {
+        SnapshotError::Mesh(err)
+    }
+}
+
+/// How vertex positions are stored in a snapshot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PositionEncoding {
+    /// Exact 32-bit floats.
+    Float32,
+    /// Integers between the corners of the bounding box, with the given
+    /// number of bits (1 to 16) per coordinate. Up to 8 bits take one byte
+    /// per coordinate, more take two.
+    Quantized {
+        /// The number of bits per coordinate.
+        bits: u8,
+    },
+}
+
+impl Default for PositionEncoding {
+    fn default() -> Self {
+        PositionEncoding::Quantized { bits: 16 }
+    }
+}
+
+/// Options for writing a snapshot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SnapshotOptions {
+    /// How positions (and previous positions) are stored.
+    pub positions: PositionEncoding,
+    /// Whether to store the previous positions and inverse masses of the
+    /// vertices, so a simulation can resume with the same velocities.
+    pub simulation_state: bool,
+}
+
+impl Default for SnapshotOptions {
+    fn default() -> Self {
+        SnapshotOptions {
+            positions: PositionEncoding::default(),
+            simulation_state: true,
+        }
+    }
+}
+
+impl Mesh {
+    /// Encodes the mesh as a binary snapshot.
+    ///
+    /// Normals are stored as 16-bit signed integers and colors as 8 bits per
+    /// channel; texture coordinates are stored exactly. Morph targets and
+    /// skin weights are not part of the format.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - How to encode the positions and whether to include the
+    ///   simulation state.
+    ///
+    /// # Returns
+    ///
+    /// The encoded snapshot.
+    pub fn to_snapshot(&self, options: &SnapshotOptions) -> Vec<u8> {
+        let mut flags = 0;
+        if self.normals.is_some() {
+            flags |= HAS_NORMALS;
+        }
+        if self.uvs.is_some() {
+            flags |= HAS_UVS;
+        }
+        if self.colors.is_some() {
+            flags |= HAS_COLORS;
+        }
+        if options.simulation_state {
+            flags |= HAS_SIMULATION;
+        }
+        let bits = match options.positions {
+            PositionEncoding::Float32 => 0,
+            PositionEncoding::Quantized { bits } => bits.clamp(1, 16),
+        };
+
+        let mut out = Vec::new();
+        out.extend_from_slice(&MAGIC);
+        out.extend_from_slice(&SNAPSHOT_VERSION.to_le_bytes());
+        out.extend_from_slice(&flags.to_le_bytes());
+        out.extend_from_slice(&(self.vertex_count() as u32).to_le_bytes());
+        out.extend_from_slice(&(self.indices.len() as u32).to_le_bytes());
+        out.push(bits);
+
+        let mut points: Vec<Vector3<f32>> = self.positions.clone();
+        if options.simulation_state {
+            points.extend(self.old_positions.iter().copied());
+        }
+        if bits == 0 {
+            for point in &points {
+                write_floats(&mut out, point.as_slice());
+            }
+        } else {
+            let (min, max) = bounds(&points);
+            write_floats(&mut out, min.as_slice());
+            write_floats(&mut out, max.as_slice());
+            let quantizer = Quantizer::new(bits, min, max);
+            for point in &points {
+                for axis in 0..3 {
+                    quantizer.write(&mut out, axis, point[axis]);
+                }
+            }
+        }
+        if options.simulation_state {
+            write_floats(&mut out, &self.inverse_masses);
+        }
+
+        let mut previous = 0i64;
+        for &index in &self.indices {
+            write_varint(&mut out, zigzag(index as i64 - previous));
+            previous = index as i64;
+        }
+
+        if let Some(normals) = &self.normals {
+            for normal in normals {
+                for &c in normal.iter() {
+                    let snorm = (c.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+                    out.extend_from_slice(&snorm.to_le_bytes());
+                }
+            }
+        }
+        if let Some(uvs) = &self.uvs {
+            for uv in uvs {
+                write_floats(&mut out, uv.as_slice());
+            }
+        }
+        if let Some(colors) = &self.colors {
+            for color in colors {
+                out.extend(
+                    color
+                        .iter()
+                        .map(|&c| (c.clamp(0.0, 1.0) * 255.0).round() as u8),
+                );
+            }
+        }
+        out
+    }
+
+    /// Decodes a mesh from a binary snapshot.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - A snapshot written by [`Mesh::to_snapshot`].
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the decoded `Mesh`, or a `SnapshotError` if the
+    /// data is not a valid snapshot. Without simulation state, the previous
+    /// positions equal the positions and the inverse masses are 1.
+    pub fn from_snapshot(bytes: &[u8]) -> Result<Mesh, SnapshotError> {
+        let mut reader = Reader { bytes };
+        if reader.take(4)? != MAGIC {
+            return Err(SnapshotError::BadMagic);
+        }
+        let version = reader.u16()?;
+        if version > SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion { version });
+        }
+        let flags = reader.u16()?;
+        let vertex_count = reader.u32()? as usize;
+        let index_count = reader.u32()? as usize;
+        let bits = reader.u8()?;
+        if bits > 16 {
+            return Err(SnapshotError::InvalidData(format!(
+                "{} bits per coordinate",
+                bits
+            )));
+        }
+        let simulation = flags & HAS_SIMULATION != 0;
+
+        let point_count = if simulation {
+            vertex_count
+                .checked_mul(2)
+                .ok_or(SnapshotError::UnexpectedEnd)?
+        } else {
+            vertex_count
+        };
+        let coordinate_count = point_count
+            .checked_mul(3)
+            .ok_or(SnapshotError::UnexpectedEnd)?;
+        let coordinates: Vec<f32> = if bits == 0 {
+            reader.floats(coordinate_count)?
+        } else {
+            let min = Vector3::from_iterator(reader.floats(3)?);
+            let max = Vector3::from_iterator(reader.floats(3)?);
+            let quantizer = Quantizer::new(bits, min, max);
+            let data = reader.take_items(coordinate_count, quantizer.width)?;
+            data.chunks_exact(quantizer.width)
+                .enumerate()
+                .map(|(i, value)| quantizer.read(i % 3, value))
+                .collect()
+        };
+        let (positions, old_positions) = coordinates.split_at(vertex_count * 3);
+        let inverse_masses = if simulation {
+            let values = reader.floats(vertex_count)?;
+            if version == 1 {
+                // Masses, where a mass of 0 marks an immovable vertex.
+                Some(
+                    values
+                        .into_iter()
+                        .map(|mass| if mass > 0.0 { 1.0 / mass } else { 0.0 })
+                        .collect(),
+                )
+            } else {
+                Some(values)
+            }
+        } else {
+            None
+        };
+
+        // Every index takes at least one byte, which bounds the allocation.
+        if index_count > reader.bytes.len() {
+            return Err(SnapshotError::UnexpectedEnd);
+        }
+        let mut indices = Vec::with_capacity(index_count);
+        let mut previous = 0i64;
+        for _ in 0..index_count {
+            let delta = unzigzag(reader.varint()?);
+            let index = previous
+                .checked_add(delta)
+                .ok_or_else(|| SnapshotError::InvalidData(format!("index delta {}", delta)))?;
+            let index = u32::try_from(index)
+                .map_err(|_| SnapshotError::InvalidData(format!("index {}", index)))?;
+            indices.push(index);
+            previous = index as i64;
+        }
+
+        let mut mesh = Mesh::new(positions, &indices)?;
+        if let Some(inverse_masses) = inverse_masses {
+            mesh.old_positions = old_positions
+                .chunks_exact(3)
+                .map(|old| Vector3::new(old[0], old[1], old[2]))
+                .collect();
+            mesh.inverse_masses = inverse_masses;
+        }
+
+        if flags & HAS_NORMALS != 0 {
+            let data = reader.take_items(vertex_count, 6)?;
+            mesh.normals = Some(
+                data.chunks_exact(6)
+                    .map(|n| {
+                        let c = |i: usize| {
+                            i16::from_le_bytes([n[i], n[i + 1]]) as f32 / i16::MAX as f32
+                        };
+                        Vector3::new(c(0), c(2), c(4))
+                    })
+                    .collect(),
+            );
+        }
+        if flags & HAS_UVS != 0 {
+            let uvs = reader.floats(vertex_count * 2)?;
+            mesh.uvs = Some(
+                uvs.chunks_exact(2)
+                    .map(|uv| Vector2::new(uv[0], uv[1]))
+                    .collect(),
+            );
+        }
+        if flags & HAS_COLORS != 0 {
+            let data = reader.take_items(vertex_count, 4)?;
+            mesh.colors = Some(
+                data.chunks_exact(4)
+                    .map(|c| Vector4::from_iterator(c.iter().map(|&c| c as f32 / 255.0)))
+                    .collect(),
+            );
+        }
+        if !reader.bytes.is_empty() {
+            return Err(SnapshotError::InvalidData(format!(
+                "{} trailing bytes",
+                reader.bytes.len()
+            )));
+        }
+        Ok(mesh)
+    }
+}
+
+/// Maps coordinates to and from integers between the corners of a box.
+struct Quantizer {
+    min: Vector3<f32>,
+    step: Vector3<f32>,
+    levels: f32,
+    /// The number of bytes per quantized coordinate.
+    width: usize,
+}
+
+impl Quantizer {
+    fn new(bits: u8, min: Vector3<f32>, max: Vector3<f32>) -> Quantizer {
+        let levels = ((1u32 << bits) - 1) as f32;
+        Quantizer {
+            min,
+            step: (max - min) / levels,
+            levels,
+            width: if bits <= 8 { 1 } else { 2 },
+        }
+    }
+
+    fn write(&self, out: &mut Vec<u8>, axis: usize, value: f32) {
+        let q = if self.step[axis] > 0.0 {
+            ((value - self.min[axis]) / self.step[axis])
+                .round()
+                .clamp(0.0, self.levels) as u16
+        } else {
+            0
+        };
+        match self.width {
+            1 => out.push(q as u8),
+            _ => out.extend_from_slice(&q.to_le_bytes()),
+        }
+    }
+
+    fn read(&self, axis: usize, value: &[u8]) -> f32 {
+        let q = match value {
+            [q] => *q as u16,
+            _ => u16::from_le_bytes([value[0], value[1]]),
+        };
+        self.min[axis] + q as f32 * self.step[axis]
+    }
+}
+
+/// Returns the corners of the bounding box of a set of points.
+fn bounds(points: &[Vector3<f32>]) -> (Vector3<f32>, Vector3<f32>) {
+    let first = points.first().copied().unwrap_or_else(Vector3::zeros);
+    points
+        .iter()
+        .fold((first, first), |(min, max), p| (min.inf(p), max.sup(p)))
+}
+
+fn write_floats(out: &mut Vec<u8>, values: &[f32]) {
+    for value in values {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+/// Maps signed integers to unsigned ones so that small magnitudes stay small.
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+/// Writes an unsigned LEB128 integer, 7 bits per byte.
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Reads the sections of a snapshot from the front of a byte slice.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SnapshotError> {
+        if len > self.bytes.len() {
+            return Err(SnapshotError::UnexpectedEnd);
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    /// Takes `count` items of `size` bytes each.
+    fn take_items(&mut self, count: usize, size: usize) -> Result<&'a [u8], SnapshotError> {
+        let len = count
+            .checked_mul(size)
+            .ok_or(SnapshotError::UnexpectedEnd)?;
+        self.take(len)
+    }
+
+    fn u8(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, SnapshotError> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, SnapshotError> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn floats(&mut self, count: usize) -> Result<Vec<f32>, SnapshotError> {
+        Ok(self
+            .take_items(count, 4)?
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect())
+    }
+
+    fn varint(&mut self) -> Result<u64, SnapshotError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(SnapshotError::InvalidData("overlong integer".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A strip of `n` triangles in the xy-plane, with vertices that have
+    // been moved by a simulation step.
+    fn strip(n: u32) -> Mesh {
+        let positions: Vec<f32> = (0..n + 2)
+            .flat_map(|i| [i as f32 * 0.5, (i % 2) as f32, 0.25])
+            .collect();
+        let indices: Vec<u32> = (0..n)
+            .flat_map(|i| match i % 2 {
+                0 => [i, i + 1, i + 2],
+                _ => [i + 1, i, i + 2],
+            })
+            .collect();
+        let mut mesh = Mesh::new(&positions, &indices).unwrap();
+        for i in 0..mesh.vertex_count() {
+            mesh.old_positions[i] = mesh.positions[i] - Vector3::new(0.0, 0.01 * i as f32, 0.0);
+            mesh.inverse_masses[i] = 1.0 / (1.0 + i as f32);
+        }
+        mesh.inverse_masses[0] = 0.0;
+        mesh
+    }
+
+    #[test]
+    fn test_lossless_round_trip() {
+        let mut mesh = strip(6);
+        let count = mesh.vertex_count();
+        mesh.uvs = Some((0..count).map(|i| Vector2::new(i as f32, 0.5)).collect());
+        let options = SnapshotOptions {
+            positions: PositionEncoding::Float32,
+            simulation_state: true,
+        };
+
+        let decoded = Mesh::from_snapshot(&mesh.to_snapshot(&options)).unwrap();
+        assert_eq!(decoded.indices, mesh.indices);
+        assert_eq!(decoded.uvs, mesh.uvs);
+        assert!(decoded.normals.is_none() && decoded.colors.is_none());
+        assert_eq!(decoded.positions, mesh.positions);
+        assert_eq!(decoded.old_positions, mesh.old_positions);
+        assert_eq!(decoded.inverse_masses, mesh.inverse_masses);
+    }
+
+    #[test]
+    fn test_quantized_round_trip_is_close_and_small() {
+        let mut mesh = strip(100);
+        mesh.compute_normals(crate::attributes::NormalWeighting::Area);
+        let count = mesh.vertex_count();
+        mesh.colors = Some(vec![Vector4::new(1.0, 0.5, 0.0, 1.0); count]);
+        let options = SnapshotOptions {
+            simulation_state: false,
+            ..SnapshotOptions::default()
+        };
+
+        let bytes = mesh.to_snapshot(&options);
+        // Header and bounds, two bytes per coordinate, one byte per index
+        // delta, six bytes per normal and four per color.
+        assert_eq!(
+            bytes.len(),
+            17 + 24 + count * 6 + 300 + count * 6 + count * 4
+        );
+
+        let decoded = Mesh::from_snapshot(&bytes).unwrap();
+        assert_eq!(decoded.indices, mesh.indices);
+        // The box is 50.5 units wide, so 16 bits resolve about 0.0008.
+        for (a, b) in decoded.positions.iter().zip(&mesh.positions) {
+            assert!((a - b).abs().max() < 5e-4);
+        }
+        assert_eq!(decoded.old_positions, decoded.positions);
+        assert!(decoded.inverse_masses.iter().all(|&w| w == 1.0));
+        let normals = decoded.normals.unwrap();
+        assert!((normals[0] - mesh.normals.unwrap()[0]).norm() < 1e-4);
+        assert!((decoded.colors.unwrap()[0].y - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_coarse_quantization_uses_one_byte() {
+        let mesh = strip(2);
+        let options = SnapshotOptions {
+            positions: PositionEncoding::Quantized { bits: 8 },
+            simulation_state: true,
+        };
+        let bytes = mesh.to_snapshot(&options);
+        assert_eq!(bytes.len(), 17 + 24 + 8 * 3 + 4 * 4 + 6);
+        let decoded = Mesh::from_snapshot(&bytes).unwrap();
+        let error = (decoded.old_positions[3] - mesh.old_positions[3]).norm();
+        assert!(error < 0.01);
+    }
+
+    #[test]
+    fn test_rejects_invalid_snapshots() {
+        let bytes = strip(2).to_snapshot(&SnapshotOptions::default());
+        assert_eq!(
+            Mesh::from_snapshot(b"nope").unwrap_err(),
+            SnapshotError::BadMagic
+        );
+        assert_eq!(
+            Mesh::from_snapshot(&bytes[..bytes.len() - 1]).unwrap_err(),
+            SnapshotError::UnexpectedEnd
+        );
+
+        let mut newer = bytes.clone();
+        newer[4] = 9;
+        assert_eq!(
+            Mesh::from_snapshot(&newer).unwrap_err(),
+            SnapshotError::UnsupportedVersion { version: 9 }
+        );
+
+        let mut trailing = bytes;
+        trailing.push(0);
+        assert!(matches!(
+            Mesh::from_snapshot(&trailing),
+            Err(SnapshotError::InvalidData(_))
+        ));
+    }
+
+    #[test]
+    fn test_rejects_overflowing_index_delta() {
+        let mesh = strip(2);
+        assert!(mesh.indices[1] > 0);
+        let bytes = mesh.to_snapshot(&SnapshotOptions::default());
+        // Without other attributes the six one-byte index deltas come last.
+        // Replace the third with the largest delta.
+        let deltas = bytes.len() - 6;
+        let mut crafted = bytes[..deltas + 2].to_vec();
+        write_varint(&mut crafted, zigzag(i64::MAX));
+        crafted.extend_from_slice(&bytes[deltas + 3..]);
+        assert!(matches!(
+            Mesh::from_snapshot(&crafted),
+            Err(SnapshotError::InvalidData(_))
+        ));
+    }
+
+    #[test]
+    fn test_reads_masses_from_version_1() {
+        let mut mesh = strip(2);
+        mesh.inverse_masses = vec![0.5, 2.0, 1.0, 4.0];
+        let options = SnapshotOptions {
+            positions: PositionEncoding::Float32,
+            simulation_state: true,
+        };
+        // Version 1 has the same layout, with masses in place of the
+        // inverse masses.
+        let mut bytes = mesh.to_snapshot(&options);
+        bytes[4] = 1;
+        let decoded = Mesh::from_snapshot(&bytes).unwrap();
+        assert_eq!(decoded.inverse_masses, vec![2.0, 0.5, 1.0, 0.25]);
+        assert_eq!(decoded.old_positions, mesh.old_positions);
+
+        let mut immovable = mesh.clone();
+        immovable.inverse_masses[0] = 0.0;
+        let mut bytes = immovable.to_snapshot(&options);
+        bytes[4] = 1;
+        assert_eq!(Mesh::from_snapshot(&bytes).unwrap().inverse_masses[0], 0.0);
+    }
+}
@@ -0,0 +1,367 @@
+//! Reading and writing meshes in the STL format.
+//!
+//! STL stores each triangle with its own three corners and a face normal,
+//! and nothing else. When reading, corners at exactly the same position are
+//! merged into one vertex so the mesh is connected; the face normals are
+//! ignored, since they can be recomputed from the corners. When writing,
+//! each face normal is computed from the current positions.
+
+use crate::{Mesh, MeshError};
+use nalgebra::Vector3;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+/// The size of the header that starts a binary STL file.
+const HEADER_SIZE: usize = 80;
+
+/// The size of one triangle in a binary STL file: a normal, three corners
+/// and a 16-bit attribute.
+const TRIANGLE_SIZE: usize = 50;
+
+/// An error that can occur while reading an STL file.
+#[derive(Debug)]
+pub enum StlError {
+    /// The underlying reader failed.
+    Io(io::Error),
+    /// A line of an ASCII file could not be parsed.
+    Parse {
+        /// The 1-based line number where the error occurred.
+        line: usize,
+        /// A description of what went wrong.
+        message: String,
+    },
+    /// A binary file is shorter or longer than its triangle count implies.
+    Truncated {
+        /// The number of bytes the triangle count implies.
+        expected: usize,
+        /// The number of bytes in the file.
+        actual: usize,
+    },
+    /// The file parsed, but does not describe a valid mesh.
+    Mesh(MeshError),
+}
+
+impl fmt::Display for StlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StlError::Io(err) => write!(f, "I/O error while reading STL: {}", err),
+            StlError::Parse { line, message } => {
+                write!(f, "STL parse error on line {}: {}", line, message)
+            }
+            StlError::Truncated { expected, actual } => write!(
+                f,
+                "binary STL should have {} bytes, but has {}",
+                expected, actual
+            ),
+            StlError::Mesh(err) => write!(f, "invalid STL mesh: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for StlError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StlError::Io(err) => Some(err),
+            StlError::Parse { .. } | StlError::Truncated { .. } => None,
+            StlError::Mesh(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for StlError {
+    fn from(err: io::Error) -> Self {
+        StlError::Io(err)
+    }
+}
+
+impl From<MeshError> for StlError {
+    fn from(err: MeshError) -> Self {
+        StlError::Mesh(err)
+    }
+}
+
+/// The encoding of an STL file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StlFormat {
+    /// The `solid ... endsolid` text encoding.
+    Ascii,
+    /// The compact binary encoding, which most tools expect.
+    Binary,
+}
+
+impl Mesh {
+    /// Reads a `Mesh` from an STL file in either encoding.
+    ///
+    /// A file is read as binary if its size matches the triangle count in
+    /// its header, and as ASCII otherwise, since some binary files also
+    /// start with `solid`.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The source of the STL data.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the new `Mesh`, or an `StlError` if the file could
+    /// not be read or parsed.
+    pub fn read_stl<R: Read>(mut reader: R) -> Result<Mesh, StlError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let corners = if is_binary(&data) || !data.starts_with(b"solid") {
+            read_binary(&data)?
+        } else {
+            read_ascii(&data)?
+        };
+        Ok(weld_corners(&corners)?)
+    }
+
+    /// Writes the triangles of the mesh as an STL file.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - The destination for the STL data.
+    /// * `format` - The encoding to write.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Result` indicating whether writing succeeded.
+    pub fn write_stl<W: Write>(&self, mut writer: W, format: StlFormat) -> io::Result<()> {
+        let triangles = self.indices.chunks_exact(3).map(|triangle| {
+            let [a, b, c] = [0, 1, 2].map(|i| self.positions[triangle[i] as usize]);
+            let normal = (b - a).cross(&(c - a)).try_normalize(0.0);
+            (normal.unwrap_or_default(), [a, b, c])
+        });
+        match format {
+            StlFormat::Ascii => {
+                writeln!(writer, "solid mesh")?;
+                for (normal, corners) in triangles {
+                    writeln!(
+                        writer,
+                        "facet normal {} {} {}",
+                        normal.x, normal.y, normal.z
+                    )?;
+                    writeln!(writer, "  outer loop")?;
+                    for corner in corners {
+                        writeln!(writer, "    vertex {} {} {}", corner.x, corner.y, corner.z)?;
+                    }
+                    writeln!(writer, "  endloop")?;
+                    writeln!(writer, "endfacet")?;
+                }
+                writeln!(writer, "endsolid mesh")
+            }
+            StlFormat::Binary => {
+                let count = u32::try_from(self.indices.len() / 3).map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "too many triangles for STL")
+                })?;
+                writer.write_all(&[0; HEADER_SIZE])?;
+                writer.write_all(&count.to_le_bytes())?;
+                for (normal, corners) in triangles {
+                    for vector in std::iter::once(normal).chain(corners) {
+                        for c in vector.iter() {
+                            writer.write_all(&c.to_le_bytes())?;
+                        }
+                    }
+                    writer.write_all(&[0, 0])?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Returns `true` if the size of the data matches the triangle count of a
+/// binary STL header.
+fn is_binary(data: &[u8]) -> bool {
+    binary_size(data).is_some_and(|size| size == data.len())
+}
+
+fn binary_size(data: &[u8]) -> Option<usize> {
+    let count = data.get(HEADER_SIZE..HEADER_SIZE + 4)?;
+    let count = u32::from_le_bytes(count.try_into().unwrap()) as usize;
+    count
+        .checked_mul(TRIANGLE_SIZE)?
+        .checked_add(HEADER_SIZE + 4)
+}
+
+fn read_binary(data: &[u8]) -> Result<Vec<Vector3<f32>>, StlError> {
+    let expected = binary_size(data).unwrap_or(HEADER_SIZE + 4);
+    if expected != data.len() {
+        return Err(StlError::Truncated {
+            expected,
+            actual: data.len(),
+        });
+    }
+    let float = |bytes: &[u8]| f32::from_le_bytes(bytes.try_into().unwrap());
+    Ok(data[HEADER_SIZE + 4..]
+        .chunks_exact(TRIANGLE_SIZE)
+        .flat_map(|triangle| {
+            // Skip the face normal and stop before the attribute.
+            triangle[12..48]
+                .chunks_exact(12)
+                .map(|c| Vector3::new(float(&c[0..4]), float(&c[4..8]), float(&c[8..12])))
+        })
+        .collect())
+}
+
+fn read_ascii(data: &[u8]) -> Result<Vec<Vector3<f32>>, StlError> {
+    let text = std::str::from_utf8(data).map_err(|_| parse_error(1, "file is not valid UTF-8"))?;
+    let mut corners = Vec::new();
+    let mut loop_start = None;
+    for (number, line) in text.lines().enumerate() {
+        let number = number + 1;
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("outer") => loop_start = Some(corners.len()),
+            Some("vertex") => {
+                if loop_start.is_none() {
+                    return Err(parse_error(number, "vertex outside of a loop"));
+                }
+                let coordinates = tokens
+                    .map(|token| {
+                        token.parse::<f32>().map_err(|_| {
+                            parse_error(number, &format!("invalid number `{}`", token))
+                        })
+                    })
+                    .collect::<Result<Vec<f32>, StlError>>()?;
+                match coordinates.as_slice() {
+                    [x, y, z] => corners.push(Vector3::new(*x, *y, *z)),
+                    _ => return Err(parse_error(number, "a vertex needs three coordinates")),
+                }
+            }
+            Some("endloop") => {
+                let start = loop_start
+                    .take()
+                    .ok_or_else(|| parse_error(number, "endloop without outer loop"))?;
+                if corners.len() - start != 3 {
+                    return Err(parse_error(number, "a facet needs exactly three vertices"));
+                }
+            }
+            _ => {}
+        }
+    }
+    if loop_start.is_some() {
+        return Err(parse_error(text.lines().count(), "unterminated loop"));
+    }
+    Ok(corners)
+}
+
+fn parse_error(line: usize, message: &str) -> StlError {
+    StlError::Parse {
+        line,
+        message: message.to_string(),
+    }
+}
+
+/// Builds a mesh from a triangle soup, merging corners whose positions are
+/// bit-for-bit equal.
+fn weld_corners(corners: &[Vector3<f32>]) -> Result<Mesh, MeshError> {
+    let mut vertices: HashMap<[u32; 3], u32> = HashMap::new();
+    let mut positions = Vec::new();
+    let indices: Vec<u32> = corners
+        .iter()
+        .map(|corner| {
+            // Adding zero turns -0.0 into 0.0, so both weld together.
+            let key = corner.map(|c| (c + 0.0).to_bits());
+            *vertices.entry(key.into()).or_insert_with(|| {
+                positions.extend(corner.iter());
+                (positions.len() / 3 - 1) as u32
+            })
+        })
+        .collect();
+    Mesh::new(&positions, &indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TETRAHEDRON: &str = "\
+solid tetrahedron
+facet normal 0 0 -1
+  outer loop
+    vertex 0 0 0
+    vertex 0 1 0
+    vertex 1 0 0
+  endloop
+endfacet
+facet normal 0 -1 0
+  outer loop
+    vertex 0 0 0
+    vertex 1 0 0
+    vertex 0 0 1
+  endloop
+endfacet
+facet normal -1 0 0
+  outer loop
+    vertex 0 0 0
+    vertex 0 0 1
+    vertex 0 1 0
+  endloop
+endfacet
+facet normal 1 1 1
+  outer loop
+    vertex 1 0 0
+    vertex 0 1 0
+    vertex -0 0 1
+  endloop
+endfacet
+endsolid tetrahedron
+";
+
+    #[test]
+    fn test_read_ascii_welds_corners() {
+        let mesh = Mesh::read_stl(TETRAHEDRON.as_bytes()).unwrap();
+        assert_eq!(mesh.vertex_count(), 4);
+        assert_eq!(mesh.indices.len(), 12);
+        assert_eq!(mesh.topology().boundary_loops().len(), 0);
+        assert!((mesh.volume() - 1.0 / 6.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_round_trip_in_both_formats() {
+        let mesh = Mesh::read_stl(TETRAHEDRON.as_bytes()).unwrap();
+        for format in [StlFormat::Ascii, StlFormat::Binary] {
+            let mut buffer = Vec::new();
+            mesh.write_stl(&mut buffer, format).unwrap();
+            let loaded = Mesh::read_stl(buffer.as_slice()).unwrap();
+            assert_eq!(loaded.positions, mesh.positions, "{:?}", format);
+            assert_eq!(loaded.indices, mesh.indices);
+        }
+
+        let mut binary = Vec::new();
+        mesh.write_stl(&mut binary, StlFormat::Binary).unwrap();
+        assert_eq!(binary.len(), 84 + 4 * 50);
+        // The face normal of the first triangle points down.
+        assert_eq!(&binary[84..96], &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 128, 191]);
+    }
+
+    #[test]
+    fn test_binary_header_may_start_with_solid() {
+        let mesh = Mesh::new(&[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0], &[0, 1, 2]).unwrap();
+        let mut binary = Vec::new();
+        mesh.write_stl(&mut binary, StlFormat::Binary).unwrap();
+        binary[..5].copy_from_slice(b"solid");
+        let loaded = Mesh::read_stl(binary.as_slice()).unwrap();
+        assert_eq!(loaded.positions, mesh.positions);
+    }
+
+    #[test]
+    fn test_read_reports_errors() {
+        let bad = TETRAHEDRON.replace("vertex 0 1 0\n    vertex 1 0 0", "vertex 0 1 0");
+        match Mesh::read_stl(bad.as_bytes()) {
+            Err(StlError::Parse { line, .. }) => assert_eq!(line, 6),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+        let mut binary = vec![0; 84];
+        binary[80] = 2;
+        binary.extend([0; 50]);
+        assert!(matches!(
+            Mesh::read_stl(binary.as_slice()),
+            Err(StlError::Truncated {
+                expected: 184,
+                actual: 134
+            })
+        ));
+    }
+}
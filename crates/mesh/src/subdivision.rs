@@ -0,0 +1,376 @@
+//! Loop subdivision of triangle meshes.
+
+use crate::morph::MorphTarget;
+use crate::skinning::SkinWeights;
+use crate::topology::Topology;
+use crate::Mesh;
+use nalgebra::Vector3;
+use std::collections::{HashMap, HashSet};
+use std::ops::{AddAssign, Mul};
+
+/// A subdivided mesh together with how it depends on the coarse mesh.
+///
+/// Every fine vertex is a fixed weighted sum of coarse vertices, so after the
+/// coarse mesh has been deformed (for example by the physics simulation) the
+/// fine mesh can be updated with [`Subdivision::apply`] instead of being
+/// subdivided again.
+#[derive(Clone, Debug)]
+pub struct Subdivision {
+    /// The refined mesh. Its first vertices correspond to the coarse
+    /// vertices, in the same order.
+    pub mesh: Mesh,
+    /// For each fine vertex, the coarse vertices it is computed from and
+    /// their weights. The weights of each stencil sum to one.
+    pub stencils: Vec<Vec<(u32, f32)>>,
+}
+
+impl Subdivision {
+    /// Recomputes the fine vertex positions from a deformed coarse mesh.
+    ///
+    /// Both the current and previous positions are updated, so the fine
+    /// vertices inherit the velocity of the coarse ones.
+    ///
+    /// # Arguments
+    ///
+    /// * `coarse` - The mesh that was subdivided, with updated positions.
+    pub fn apply(&mut self, coarse: &Mesh) {
+        for (v, stencil) in self.stencils.iter().enumerate() {
+            self.mesh.positions[v] = evaluate(stencil, |i| coarse.positions[i]);
+            self.mesh.old_positions[v] = evaluate(stencil, |i| coarse.old_positions[i]);
+        }
+    }
+}
+
+impl Mesh {
+    /// Refines the mesh with Loop subdivision.
+    ///
+    /// Each level splits every triangle into four and moves the vertices
+    /// towards a smooth limit surface. Boundary edges, edges shared by more
+    /// than two triangles and the given crease edges stay sharp: vertices on
+    /// them are only smoothed along the crease, and vertices where three or
+    /// more crease edges meet do not move.
+    ///
+    /// Texture coordinates, colors and morph targets are interpolated with the
+    /// same weights as the positions, and skin weights are mixed with them,
+    /// keeping the four strongest joints. Normals are not carried over; call
+    /// `compute_normals` on the result if they are needed.
+    ///
+    /// The vertices that correspond to coarse vertices keep their inverse
+    /// masses, so pinned vertices stay pinned. The vertices added on edges
+    /// get an inverse mass of 1.
+    ///
+    /// # Arguments
+    ///
+    /// * `levels` - How many times to subdivide.
+    /// * `creases` - Edges of this mesh, as vertex pairs in either order,
+    ///   that should stay sharp.
+    ///
+    /// # Returns
+    ///
+    /// A `Subdivision` holding the refined mesh and its stencils.
+    pub fn subdivide_loop(&self, levels: usize, creases: &[[usize; 2]]) -> Subdivision {
+        let mut indices = self.indices.clone();
+        let mut vertex_count = self.vertex_count();
+        let mut creases: HashSet<(usize, usize)> =
+            creases.iter().map(|&[a, b]| (a.min(b), a.max(b))).collect();
+        let mut stencils: Vec<Vec<(u32, f32)>> =
+            (0..vertex_count as u32).map(|i| vec![(i, 1.0)]).collect();
+
+        for _ in 0..levels {
+            let topology = Topology::new(vertex_count, &indices);
+            let level = loop_level(&topology, &indices, &creases);
+            stencils = level
+                .stencils
+                .iter()
+                .map(|stencil| compose(stencil, &stencils))
+                .collect();
+            indices = level.indices;
+            creases = level.creases;
+            vertex_count = stencils.len();
+        }
+
+        let mesh = Mesh {
+            positions: stencils
+                .iter()
+                .map(|stencil| evaluate(stencil, |i| self.positions[i]))
+                .collect(),
+            old_positions: stencils
+                .iter()
+                .map(|stencil| evaluate(stencil, |i| self.old_positions[i]))
+                .collect(),
+            accelerations: vec![Vector3::zeros(); stencils.len()],
+            inverse_masses: (0..stencils.len())
+                .map(|v| self.inverse_masses.get(v).copied().unwrap_or(1.0))
+                .collect(),
+            indices,
+            normals: None,
+            uvs: interpolate(&self.uvs, &stencils),
+            colors: interpolate(&self.colors, &stencils),
+            morph_targets: self
+                .morph_targets
+                .iter()
+                .map(|target| MorphTarget {
+                    name: target.name.clone(),
+                    deltas: stencils
+                        .iter()
+                        .map(|stencil| evaluate(stencil, |i| target.deltas[i]))
+                        .collect(),
+                })
+                .collect(),
+            skin_weights: self.skin_weights.as_ref().map(|skin_weights| {
+                stencils
+                    .iter()
+                    .map(|stencil| {
+                        SkinWeights::blend(
+                            stencil.iter().map(|&(i, w)| (skin_weights[i as usize], w)),
+                        )
+                    })
+                    .collect()
+            }),
+        };
+        Subdivision { mesh, stencils }
+    }
+}
+
+/// The result of one subdivision step, expressed in terms of the previous level.
+struct LoopLevel {
+    indices: Vec<u32>,
+    creases: HashSet<(usize, usize)>,
+    stencils: Vec<Vec<(u32, f32)>>,
+}
+
+/// Performs one level of Loop subdivision. The new vertex for edge `e` gets
+/// index `vertex_count + e`.
+fn loop_level(
+    topology: &Topology,
+    indices: &[u32],
+    creases: &HashSet<(usize, usize)>,
+) -> LoopLevel {
+    let vertex_count = topology.vertex_count();
+    let edges = topology.edges();
+    let sharp: Vec<bool> = (0..edges.len())
+        .map(|e| {
+            topology.edge_half_edges(e).len() != 2 || creases.contains(&(edges[e][0], edges[e][1]))
+        })
+        .collect();
+
+    let mut stencils = Vec::with_capacity(vertex_count + edges.len());
+    for v in 0..vertex_count {
+        let ring = topology.one_ring(v);
+        let sharp_neighbors: Vec<usize> = ring
+            .iter()
+            .copied()
+            .filter(|&n| topology.find_edge(v, n).is_some_and(|e| sharp[e]))
+            .collect();
+        let stencil = match sharp_neighbors.len() {
+            _ if ring.is_empty() => vec![(v as u32, 1.0)],
+            2 => vec![
+                (v as u32, 0.75),
+                (sharp_neighbors[0] as u32, 0.125),
+                (sharp_neighbors[1] as u32, 0.125),
+            ],
+            n if n > 2 => vec![(v as u32, 1.0)],
+            _ => {
+                let valence = ring.len();
+                let beta = if valence == 3 {
+                    3.0 / 16.0
+                } else {
+                    3.0 / (8.0 * valence as f32)
+                };
+                let mut stencil = vec![(v as u32, 1.0 - valence as f32 * beta)];
+                stencil.extend(ring.iter().map(|&n| (n as u32, beta)));
+                stencil
+            }
+        };
+        stencils.push(stencil);
+    }
+
+    for (e, &[a, b]) in edges.iter().enumerate() {
+        let stencil = if sharp[e] {
+            vec![(a as u32, 0.5), (b as u32, 0.5)]
+        } else {
+            let half_edges = topology.edge_half_edges(e);
+            vec![
+                (a as u32, 0.375),
+                (b as u32, 0.375),
+                (topology.opposite_vertex(half_edges[0]) as u32, 0.125),
+                (topology.opposite_vertex(half_edges[1]) as u32, 0.125),
+            ]
+        };
+        stencils.push(stencil);
+    }
+
+    let midpoint = |a: u32, b: u32| {
+        let edge = topology
+            .find_edge(a as usize, b as usize)
+            .expect("triangle edges are in the topology");
+        (vertex_count + edge) as u32
+    };
+    let mut fine_indices = Vec::with_capacity(indices.len() * 4);
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [triangle[0], triangle[1], triangle[2]];
+        let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+        fine_indices.extend_from_slice(&[a, ab, ca, ab, b, bc, ca, bc, c, ab, bc, ca]);
+    }
+
+    let mut fine_creases = HashSet::new();
+    for (e, &[a, b]) in edges.iter().enumerate() {
+        if creases.contains(&(a, b)) {
+            let mid = vertex_count + e;
+            fine_creases.insert((a, mid));
+            fine_creases.insert((b, mid));
+        }
+    }
+
+    LoopLevel {
+        indices: fine_indices,
+        creases: fine_creases,
+        stencils,
+    }
+}
+
+/// Rewrites a stencil over the previous level as a stencil over the coarse mesh.
+fn compose(stencil: &[(u32, f32)], previous: &[Vec<(u32, f32)>]) -> Vec<(u32, f32)> {
+    let mut weights: HashMap<u32, f32> = HashMap::new();
+    for &(i, w) in stencil {
+        for &(j, v) in &previous[i as usize] {
+            *weights.entry(j).or_insert(0.0) += w * v;
+        }
+    }
+    let mut composed: Vec<(u32, f32)> = weights.into_iter().collect();
+    composed.sort_unstable_by_key(|&(i, _)| i);
+    composed
+}
+
+/// Evaluates every stencil on an optional attribute channel.
+fn interpolate<T>(channel: &Option<Vec<T>>, stencils: &[Vec<(u32, f32)>]) -> Option<Vec<T>>
+where
+    T: Mul<f32, Output = T> + AddAssign + Copy,
+{
+    channel.as_ref().map(|values| {
+        stencils
+            .iter()
+            .map(|stencil| evaluate(stencil, |i| values[i]))
+            .collect()
+    })
+}
+
+/// Computes the weighted sum of a stencil.
+fn evaluate<T, F>(stencil: &[(u32, f32)], value: F) -> T
+where
+    T: Mul<f32, Output = T> + AddAssign + Copy,
+    F: Fn(usize) -> T,
+{
+    let (first, rest) = stencil.split_first().expect("stencils are never empty");
+    let mut sum = value(first.0 as usize) * first.1;
+    for &(i, w) in rest {
+        sum += value(i as usize) * w;
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Vector3;
+
+    fn octahedron() -> Mesh {
+        let positions = vec![
+            1.0, 0.0, 0.0, // 0: +x
+            0.0, 1.0, 0.0, // 1: +y
+            -1.0, 0.0, 0.0, // 2: -x
+            0.0, -1.0, 0.0, // 3: -y
+            0.0, 0.0, 1.0, // 4: +z
+            0.0, 0.0, -1.0, // 5: -z
+        ];
+        let indices = vec![
+            0, 1, 4, 1, 2, 4, 2, 3, 4, 3, 0, 4, // upper half
+            1, 0, 5, 2, 1, 5, 3, 2, 5, 0, 3, 5, // lower half
+        ];
+        Mesh::new(&positions, &indices).unwrap()
+    }
+
+    // Returns the fine vertex created on the coarse edge a-b by the first level.
+    fn edge_vertex(coarse: &Mesh, subdivision: &Subdivision, a: usize, b: usize) -> Vector3<f32> {
+        let edge = coarse.topology().find_edge(a, b).unwrap();
+        subdivision.mesh.positions[coarse.vertex_count() + edge]
+    }
+
+    #[test]
+    fn test_loop_interior_rules() {
+        let coarse = octahedron();
+        let subdivision = coarse.subdivide_loop(1, &[]);
+        assert_eq!(subdivision.mesh.vertex_count(), 6 + 12);
+        assert_eq!(subdivision.mesh.indices.len(), 32 * 3);
+        assert!(subdivision.mesh.validate().is_valid());
+
+        // Valence 4 gives beta = 3/32 and the neighbours cancel out.
+        let corner = subdivision.mesh.positions[0];
+        assert!((corner - Vector3::new(0.625, 0.0, 0.0)).norm() < 1e-6);
+        // 3/8 of each end point plus 1/8 of the opposite vertices +z and -z.
+        let edge = edge_vertex(&coarse, &subdivision, 0, 1);
+        assert!((edge - Vector3::new(0.375, 0.375, 0.0)).norm() < 1e-6);
+    }
+
+    #[test]
+    fn test_loop_boundary_rules() {
+        let triangle =
+            Mesh::new(&[0.0, 0.0, 0.0, 4.0, 0.0, 0.0, 0.0, 4.0, 0.0], &[0, 1, 2]).unwrap();
+        let subdivision = triangle.subdivide_loop(1, &[]);
+        assert_eq!(subdivision.mesh.indices.len(), 4 * 3);
+        let p = &subdivision.mesh.positions;
+        // Corner: 3/4 of itself and 1/8 of each boundary neighbour.
+        assert!((p[1] - Vector3::new(3.0, 0.5, 0.0)).norm() < 1e-6);
+        // Boundary edges are split at their midpoint.
+        let edge = edge_vertex(&triangle, &subdivision, 0, 1);
+        assert_eq!(edge, Vector3::new(2.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_loop_creases_stay_sharp() {
+        let equator = [[0, 1], [1, 2], [2, 3], [3, 0]];
+        let subdivision = octahedron().subdivide_loop(2, &equator);
+        // The equator vertices are smoothed only along the equator, so they
+        // stay in the z = 0 plane at every level.
+        for (v, stencil) in subdivision.stencils.iter().enumerate() {
+            if stencil.iter().all(|&(i, _)| i < 4) {
+                assert!(subdivision.mesh.positions[v].z.abs() < 1e-6);
+            }
+        }
+        let corner = subdivision.mesh.positions[0];
+        assert!((corner - Vector3::new(0.6875, 0.0, 0.0)).norm() < 1e-6);
+    }
+
+    #[test]
+    fn test_loop_keeps_pinned_vertices() {
+        let mut coarse = octahedron();
+        coarse.inverse_masses[4] = 0.0;
+        coarse.inverse_masses[5] = 0.5;
+        let subdivision = coarse.subdivide_loop(2, &[]);
+        let inverse_masses = &subdivision.mesh.inverse_masses;
+        assert_eq!(inverse_masses[..6], [1.0, 1.0, 1.0, 1.0, 0.0, 0.5]);
+        assert!(inverse_masses[6..].iter().all(|&w| w == 1.0));
+    }
+
+    #[test]
+    fn test_apply_matches_subdividing_again() {
+        let mut coarse = octahedron();
+        let mut subdivision = coarse.subdivide_loop(2, &[]);
+        for stencil in &subdivision.stencils {
+            let total: f32 = stencil.iter().map(|&(_, w)| w).sum();
+            assert!((total - 1.0).abs() < 1e-5);
+        }
+
+        coarse.positions[4].z = 2.0;
+        subdivision.apply(&coarse);
+        let expected = coarse.subdivide_loop(2, &[]);
+        for (a, b) in subdivision
+            .mesh
+            .positions
+            .iter()
+            .zip(&expected.mesh.positions)
+        {
+            assert!((a - b).norm() < 1e-5);
+        }
+    }
+}
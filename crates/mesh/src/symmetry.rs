@@ -0,0 +1,320 @@
+//! Detection of the mirror symmetry plane of a mesh and the vertex
+//! correspondence across it.
+
+use crate::bvh::Bvh;
+use crate::Mesh;
+use nalgebra::{Matrix3, SymmetricEigen, Vector3};
+
+/// The most refinement passes [`Mesh::detect_symmetry`] makes.
+const MAX_REFINEMENTS: usize = 20;
+
+/// A plane made of the points `p` with `normal.dot(p) == offset`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Plane {
+    /// The unit normal of the plane.
+    pub normal: Vector3<f32>,
+    /// The signed distance of the plane from the origin along `normal`.
+    pub offset: f32,
+}
+
+impl Plane {
+    /// Creates the plane through a point with a given normal.
+    ///
+    /// # Arguments
+    ///
+    /// * `normal` - The normal of the plane. It does not have to be unit length.
+    /// * `point` - A point on the plane.
+    pub fn new(normal: Vector3<f32>, point: &Vector3<f32>) -> Plane {
+        let normal = normal.normalize();
+        Plane {
+            normal,
+            offset: normal.dot(point),
+        }
+    }
+
+    /// Returns the distance of a point from the plane, positive on the side
+    /// the normal points to.
+    pub fn signed_distance(&self, point: &Vector3<f32>) -> f32 {
+        self.normal.dot(point) - self.offset
+    }
+
+    /// Returns the mirror image of a point.
+    pub fn reflect_point(&self, point: &Vector3<f32>) -> Vector3<f32> {
+        point - self.normal * (2.0 * self.signed_distance(point))
+    }
+
+    /// Returns the mirror image of a direction or displacement.
+    pub fn reflect_vector(&self, vector: &Vector3<f32>) -> Vector3<f32> {
+        vector - self.normal * (2.0 * self.normal.dot(vector))
+    }
+}
+
+/// The result of [`Mesh::detect_symmetry`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Symmetry {
+    /// The symmetry plane. The normal is oriented so that its largest
+    /// component is positive.
+    pub plane: Plane,
+    /// The root mean square distance from the mirror image of each vertex to
+    /// the surface, which is zero for a perfectly symmetric mesh.
+    pub error: f32,
+}
+
+impl Mesh {
+    /// Finds the plane the mesh is most nearly mirror symmetric across, such
+    /// as the sagittal plane of a face.
+    ///
+    /// The planes through the vertex centroid perpendicular to the principal
+    /// axes of the vertices are tried first. The best of them is then refined
+    /// by matching each vertex with the point of the surface nearest to its
+    /// mirror image and fitting the plane to the matches (iterative closest
+    /// point), until the fit stops improving. Matching against the surface
+    /// rather than the vertices makes the result independent of how the two
+    /// sides are tessellated.
+    ///
+    /// # Returns
+    ///
+    /// The detected `Symmetry`, or `None` if the mesh has no vertices.
+    pub fn detect_symmetry(&self) -> Option<Symmetry> {
+        let count = self.vertex_count();
+        if count == 0 {
+            return None;
+        }
+        let centroid = self.positions.iter().sum::<Vector3<f32>>() / count as f32;
+        let covariance = self
+            .positions
+            .iter()
+            .map(|p| (p - centroid) * (p - centroid).transpose())
+            .sum::<Matrix3<f32>>();
+        let axes = SymmetricEigen::new(covariance).eigenvectors;
+
+        let bvh = self.bvh();
+        let mut best = (0..3)
+            .map(|i| {
+                let plane = Plane::new(axes.column(i).into(), &centroid);
+                self.symmetry(&bvh, plane)
+            })
+            .min_by(|a, b| a.error.total_cmp(&b.error))
+            .expect("there are three axes");
+
+        for _ in 0..MAX_REFINEMENTS {
+            let Some(plane) = self.refine_plane(&bvh, &best.plane) else {
+                break;
+            };
+            let candidate = self.symmetry(&bvh, plane);
+            if candidate.error >= best.error {
+                break;
+            }
+            best = candidate;
+        }
+
+        let plane = best.plane;
+        let largest = plane.normal.iamax();
+        if plane.normal[largest] < 0.0 {
+            best.plane = Plane {
+                normal: -plane.normal,
+                offset: -plane.offset,
+            };
+        }
+        Some(best)
+    }
+
+    /// Matches each vertex with its mirror image across a plane.
+    ///
+    /// # Arguments
+    ///
+    /// * `plane` - The mirror plane, such as the one found by
+    ///   [`Mesh::detect_symmetry`].
+    /// * `tolerance` - How far the vertex nearest to a mirror image may be
+    ///   from it.
+    ///
+    /// # Returns
+    ///
+    /// For each vertex, the vertex nearest to its mirror image, or `None` if
+    /// there is no vertex within `tolerance`. Vertices on the plane map to
+    /// themselves.
+    pub fn mirror_map(&self, plane: &Plane, tolerance: f32) -> Vec<Option<usize>> {
+        let bvh = self.bvh();
+        self.positions
+            .iter()
+            .map(|p| {
+                let (v, distance) = *bvh
+                    .k_nearest_vertices(self, &plane.reflect_point(p), 1)
+                    .first()?;
+                (distance <= tolerance).then_some(v)
+            })
+            .collect()
+    }
+
+    /// Pairs each vertex with the point of the surface closest to its mirror
+    /// image and the unit normal of the surface there, or with the nearest
+    /// vertex and a zero normal if the mesh has no triangles.
+    fn mirror_matches<'a>(
+        &'a self,
+        bvh: &'a Bvh,
+        plane: &'a Plane,
+    ) -> impl Iterator<Item = (Vector3<f32>, Vector3<f32>, Vector3<f32>)> + 'a {
+        self.positions.iter().map(move |p| {
+            let image = plane.reflect_point(p);
+            match bvh.closest_point(self, &image) {
+                Some(surface) => {
+                    let t = surface.triangle;
+                    let [a, b, c] =
+                        [0, 1, 2].map(|i| self.positions[self.indices[t * 3 + i] as usize]);
+                    let normal = (b - a).cross(&(c - a)).try_normalize(0.0);
+                    (*p, surface.point, normal.unwrap_or_default())
+                }
+                None => {
+                    let v = bvh
+                        .nearest_vertex(self, &image)
+                        .expect("the mesh has vertices");
+                    (*p, self.positions[v], Vector3::zeros())
+                }
+            }
+        })
+    }
+
+    fn symmetry(&self, bvh: &Bvh, plane: Plane) -> Symmetry {
+        let squared: f32 = self
+            .mirror_matches(bvh, &plane)
+            .map(|(p, q, _)| (plane.reflect_point(&p) - q).norm_squared())
+            .sum();
+        Symmetry {
+            plane,
+            error: (squared / self.vertex_count() as f32).sqrt(),
+        }
+    }
+
+    /// Takes one Gauss-Newton step towards the plane whose mirror images lie
+    /// on the surface.
+    ///
+    /// Each mirror image is pulled onto the tangent plane of the surface at
+    /// its match, which lets it slide along the surface, and weakly towards
+    /// the match itself, which keeps the step well defined where the surface
+    /// is flat. The unknowns are the tilt of the normal along two directions
+    /// perpendicular to it, and the change of the offset.
+    fn refine_plane(&self, bvh: &Bvh, plane: &Plane) -> Option<Plane> {
+        const POINT_WEIGHT: f32 = 0.001;
+        let n = plane.normal;
+        let u = n
+            .cross(&Vector3::x())
+            .try_normalize(1e-3)
+            .unwrap_or_else(|| n.cross(&Vector3::y()).normalize());
+        let v = n.cross(&u);
+
+        let mut normal_matrix = Matrix3::zeros();
+        let mut gradient = Vector3::zeros();
+        for (p, q, m) in self.mirror_matches(bvh, plane) {
+            let s = plane.signed_distance(&p);
+            // The derivatives of the mirror image of `p` by each unknown.
+            let jacobian = Matrix3::from_columns(&[
+                -2.0 * (n * u.dot(&p) + u * s),
+                -2.0 * (n * v.dot(&p) + v * s),
+                2.0 * n,
+            ]);
+            let residual = plane.reflect_point(&p) - q;
+            let row = jacobian.transpose() * m;
+            normal_matrix += row * row.transpose() + jacobian.transpose() * jacobian * POINT_WEIGHT;
+            gradient += row * residual.dot(&m) + jacobian.transpose() * residual * POINT_WEIGHT;
+        }
+        let step = normal_matrix.cholesky()?.solve(&-gradient);
+        Some(Plane {
+            normal: (n + u * step.x + v * step.y).normalize(),
+            offset: plane.offset + step.z,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{Isometry3, Point3};
+
+    const COLUMNS: usize = 9;
+    const ROWS: usize = 13;
+
+    // A bumpy grid that is only symmetric across x = 0. Vertex
+    // `row * COLUMNS + column` mirrors vertex `row * COLUMNS + (8 - column)`.
+    fn face() -> Mesh {
+        let mut positions = Vec::new();
+        for row in 0..ROWS {
+            for column in 0..COLUMNS {
+                let x = column as f32 * 0.5 - 2.0;
+                let y = row as f32 * 0.5 - 3.0;
+                positions.extend([x, y, 0.3 * x * x + 0.05 * y * y * y]);
+            }
+        }
+        let mut indices = Vec::new();
+        for row in 0..ROWS as u32 - 1 {
+            for column in 0..COLUMNS as u32 - 1 {
+                let v = row * COLUMNS as u32 + column;
+                let above = v + COLUMNS as u32;
+                indices.extend([v, v + 1, above + 1, v, above + 1, above]);
+            }
+        }
+        Mesh::new(&positions, &indices).unwrap()
+    }
+
+    #[test]
+    fn test_plane_reflection() {
+        let plane = Plane::new(Vector3::new(0.0, 2.0, 0.0), &Vector3::new(5.0, 1.0, 0.0));
+        assert_eq!(plane.offset, 1.0);
+        let point = Vector3::new(3.0, 4.0, 1.0);
+        assert_eq!(plane.signed_distance(&point), 3.0);
+        assert_eq!(plane.reflect_point(&point), Vector3::new(3.0, -2.0, 1.0));
+        assert_eq!(
+            plane.reflect_vector(&Vector3::new(1.0, 1.0, 0.0)),
+            Vector3::new(1.0, -1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_detect_symmetry_of_moved_mesh() {
+        let mut mesh = face();
+        let transform = Isometry3::new(Vector3::new(1.0, -2.0, 0.5), Vector3::new(0.3, 0.2, 0.1));
+        for p in &mut mesh.positions {
+            *p = transform.transform_point(&Point3::from(*p)).coords;
+        }
+
+        let symmetry = mesh.detect_symmetry().unwrap();
+        assert!(symmetry.error < 1e-4, "{}", symmetry.error);
+        let normal = transform.transform_vector(&Vector3::x());
+        assert!((symmetry.plane.normal - normal).norm() < 1e-4);
+        let origin = transform.translation.vector;
+        assert!(symmetry.plane.signed_distance(&origin).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_detect_symmetry_with_uneven_sampling() {
+        // Sampling the right half twice moves the centroid off the plane and
+        // tilts the principal axes, which the refinement has to undo.
+        let face = face();
+        let right: Vec<f32> = face
+            .positions
+            .iter()
+            .filter(|p| p.x > 0.0)
+            .flat_map(|p| p.iter().copied().collect::<Vec<_>>())
+            .collect();
+        let positions = [face.get_vertex_positions_flat(), right].concat();
+        let mesh = Mesh::new(&positions, &face.indices).unwrap();
+
+        let symmetry = mesh.detect_symmetry().unwrap();
+        assert!(symmetry.error < 1e-4, "{}", symmetry.error);
+        assert!((symmetry.plane.normal - Vector3::x()).norm() < 1e-4);
+        assert!(symmetry.plane.offset.abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_mirror_map() {
+        let mesh = face();
+        let plane = Plane::new(Vector3::x(), &Vector3::zeros());
+        let mirror = mesh.mirror_map(&plane, 1e-4);
+        for (v, &m) in mirror.iter().enumerate() {
+            let (row, column) = (v / COLUMNS, v % COLUMNS);
+            assert_eq!(m, Some(row * COLUMNS + COLUMNS - 1 - column));
+        }
+
+        let shifted = Plane::new(Vector3::x(), &Vector3::new(0.1, 0.0, 0.0));
+        assert!(mesh.mirror_map(&shifted, 0.1).iter().all(Option::is_none));
+    }
+}
@@ -0,0 +1,290 @@
+//! Connectivity information derived from the triangle indices of a mesh.
+
+use crate::Mesh;
+use std::collections::HashMap;
+
+/// One directed side of a triangle.
+///
+/// Half-edge `3 * f + k` belongs to triangle `f` and runs from its corner `k`
+/// to corner `(k + 1) % 3`, so the next and previous half-edges around a
+/// triangle can be found by index arithmetic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HalfEdge {
+    /// The vertex the half-edge starts at.
+    pub origin: usize,
+    /// The vertex the half-edge ends at.
+    pub target: usize,
+    /// The triangle the half-edge belongs to.
+    pub face: usize,
+    /// The undirected edge this half-edge lies on.
+    pub edge: usize,
+    /// The opposite half-edge in the neighbouring triangle. This is `None` on
+    /// boundary edges, on edges shared by more than two triangles, and where
+    /// the two triangles have inconsistent winding.
+    pub twin: Option<usize>,
+}
+
+/// Adjacency information for a triangle mesh.
+///
+/// `Topology` answers questions such as "which triangles touch this vertex",
+/// "what are the one-ring neighbours of a vertex" and "where are the
+/// boundaries". It only depends on the triangle indices, so it can be built
+/// once and reused while the vertex positions change.
+///
+/// Non-manifold input is tolerated: an edge may have any number of
+/// triangles, in which case its half-edges simply have no twin.
+#[derive(Clone, Debug, Default)]
+pub struct Topology {
+    half_edges: Vec<HalfEdge>,
+    edges: Vec<[usize; 2]>,
+    edge_lookup: HashMap<(usize, usize), usize>,
+    edge_half_edges: Vec<Vec<usize>>,
+    vertex_faces: Vec<Vec<usize>>,
+    vertex_neighbors: Vec<Vec<usize>>,
+}
+
+impl Topology {
+    /// Builds the topology of a triangle list.
+    ///
+    /// Any trailing indices that do not form a full triangle are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `vertex_count` - The number of vertices the indices refer to.
+    /// * `indices` - The triangle indices, three per triangle.
+    ///
+    /// # Returns
+    ///
+    /// A new `Topology` instance.
+    pub fn new(vertex_count: usize, indices: &[u32]) -> Topology {
+        let triangle_count = indices.len() / 3;
+        let mut topology = Topology {
+            half_edges: Vec::with_capacity(triangle_count * 3),
+            vertex_faces: vec![Vec::new(); vertex_count],
+            vertex_neighbors: vec![Vec::new(); vertex_count],
+            ..Topology::default()
+        };
+
+        for (face, triangle) in indices.chunks_exact(3).enumerate() {
+            for k in 0..3 {
+                let origin = triangle[k] as usize;
+                let target = triangle[(k + 1) % 3] as usize;
+                let key = (origin.min(target), origin.max(target));
+                let edge = *topology.edge_lookup.entry(key).or_insert_with(|| {
+                    topology.edges.push([key.0, key.1]);
+                    topology.edge_half_edges.push(Vec::new());
+                    topology.vertex_neighbors[key.0].push(key.1);
+                    topology.vertex_neighbors[key.1].push(key.0);
+                    topology.edges.len() - 1
+                });
+                topology.edge_half_edges[edge].push(topology.half_edges.len());
+                topology.half_edges.push(HalfEdge {
+                    origin,
+                    target,
+                    face,
+                    edge,
+                    twin: None,
+                });
+                let faces = &mut topology.vertex_faces[origin];
+                if faces.last() != Some(&face) {
+                    faces.push(face);
+                }
+            }
+        }
+
+        for half_edges in &topology.edge_half_edges {
+            if let [a, b] = half_edges[..] {
+                if topology.half_edges[a].origin == topology.half_edges[b].target {
+                    topology.half_edges[a].twin = Some(b);
+                    topology.half_edges[b].twin = Some(a);
+                }
+            }
+        }
+        topology
+    }
+
+    /// Returns all half-edges, three per triangle.
+    pub fn half_edges(&self) -> &[HalfEdge] {
+        &self.half_edges
+    }
+
+    /// Returns the half-edge that follows `half_edge` around its triangle.
+    pub fn next(&self, half_edge: usize) -> usize {
+        half_edge - half_edge % 3 + (half_edge + 1) % 3
+    }
+
+    /// Returns the half-edge that precedes `half_edge` around its triangle.
+    pub fn prev(&self, half_edge: usize) -> usize {
+        half_edge - half_edge % 3 + (half_edge + 2) % 3
+    }
+
+    /// Returns the corner of the triangle that is not on `half_edge`.
+    pub fn opposite_vertex(&self, half_edge: usize) -> usize {
+        self.half_edges[self.next(half_edge)].target
+    }
+
+    /// Returns the unique undirected edges as `[low, high]` vertex pairs, in
+    /// the order they first appear in the index list.
+    pub fn edges(&self) -> &[[usize; 2]] {
+        &self.edges
+    }
+
+    /// Returns the index of the edge between two vertices, if there is one.
+    pub fn find_edge(&self, a: usize, b: usize) -> Option<usize> {
+        self.edge_lookup.get(&(a.min(b), a.max(b))).copied()
+    }
+
+    /// Returns the half-edges that lie on an edge, one per adjacent triangle.
+    pub fn edge_half_edges(&self, edge: usize) -> &[usize] {
+        &self.edge_half_edges[edge]
+    }
+
+    /// Returns the triangles adjacent to an edge.
+    pub fn edge_faces(&self, edge: usize) -> impl Iterator<Item = usize> + '_ {
+        self.edge_half_edges[edge]
+            .iter()
+            .map(move |&h| self.half_edges[h].face)
+    }
+
+    /// Returns `true` if exactly one triangle uses the edge.
+    pub fn is_boundary_edge(&self, edge: usize) -> bool {
+        self.edge_half_edges[edge].len() == 1
+    }
+
+    /// Returns `true` if the edge is used by at most two triangles.
+    pub fn is_manifold_edge(&self, edge: usize) -> bool {
+        self.edge_half_edges[edge].len() <= 2
+    }
+
+    /// Returns the number of vertices the topology was built for.
+    pub fn vertex_count(&self) -> usize {
+        self.vertex_faces.len()
+    }
+
+    /// Returns the triangles that use a vertex.
+    pub fn vertex_faces(&self, vertex: usize) -> &[usize] {
+        &self.vertex_faces[vertex]
+    }
+
+    /// Returns the vertices that share an edge with a vertex.
+    pub fn one_ring(&self, vertex: usize) -> &[usize] {
+        &self.vertex_neighbors[vertex]
+    }
+
+    /// Returns `true` if the vertex lies on a boundary edge.
+    pub fn is_boundary_vertex(&self, vertex: usize) -> bool {
+        self.vertex_neighbors[vertex].iter().any(|&other| {
+            self.find_edge(vertex, other)
+                .is_some_and(|edge| self.is_boundary_edge(edge))
+        })
+    }
+
+    /// Returns the boundary loops of the mesh.
+    ///
+    /// Each loop is a list of vertices ordered along the boundary half-edges,
+    /// without repeating the first vertex at the end. A boundary that cannot
+    /// be closed (because of inconsistent winding) is returned as an open chain.
+    pub fn boundary_loops(&self) -> Vec<Vec<usize>> {
+        let boundary: Vec<usize> = (0..self.half_edges.len())
+            .filter(|&h| self.is_boundary_edge(self.half_edges[h].edge))
+            .collect();
+        let mut outgoing: HashMap<usize, Vec<usize>> = HashMap::new();
+        for &h in &boundary {
+            outgoing
+                .entry(self.half_edges[h].origin)
+                .or_default()
+                .push(h);
+        }
+
+        let mut visited = vec![false; self.half_edges.len()];
+        let mut loops = Vec::new();
+        for &start in &boundary {
+            if visited[start] {
+                continue;
+            }
+            let mut chain = Vec::new();
+            let mut current = Some(start);
+            while let Some(h) = current {
+                visited[h] = true;
+                chain.push(self.half_edges[h].origin);
+                current = outgoing
+                    .get(&self.half_edges[h].target)
+                    .and_then(|candidates| candidates.iter().copied().find(|&c| !visited[c]));
+            }
+            loops.push(chain);
+        }
+        loops
+    }
+}
+
+impl Mesh {
+    /// Builds the adjacency information for the mesh's triangles.
+    ///
+    /// # Returns
+    ///
+    /// A new `Topology` instance.
+    pub fn topology(&self) -> Topology {
+        Topology::new(self.vertex_count(), &self.indices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A square made of two triangles sharing the diagonal 0-2.
+    fn square() -> Topology {
+        Topology::new(4, &[0, 1, 2, 0, 2, 3])
+    }
+
+    #[test]
+    fn test_edges_and_twins() {
+        let topology = square();
+        assert_eq!(topology.edges().len(), 5);
+        let diagonal = topology.find_edge(2, 0).unwrap();
+        assert!(!topology.is_boundary_edge(diagonal));
+        assert_eq!(
+            topology.edge_faces(diagonal).collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+
+        // Half-edge 1 runs 1 -> 2, half-edge 2 runs 2 -> 0 and its twin is 0 -> 2.
+        assert_eq!(topology.half_edges()[2].twin, Some(3));
+        assert_eq!(topology.half_edges()[0].twin, None);
+        assert_eq!(topology.next(2), 0);
+        assert_eq!(topology.prev(3), 5);
+        assert_eq!(topology.opposite_vertex(2), 1);
+    }
+
+    #[test]
+    fn test_vertex_adjacency() {
+        let topology = square();
+        assert_eq!(topology.vertex_faces(0), &[0, 1]);
+        assert_eq!(topology.vertex_faces(1), &[0]);
+        let mut ring = topology.one_ring(0).to_vec();
+        ring.sort();
+        assert_eq!(ring, vec![1, 2, 3]);
+        assert!(topology.is_boundary_vertex(0));
+    }
+
+    #[test]
+    fn test_boundary_loops() {
+        let loops = square().boundary_loops();
+        assert_eq!(loops, vec![vec![0, 1, 2, 3]]);
+
+        // A closed tetrahedron has no boundary.
+        let tetrahedron = Topology::new(4, &[0, 2, 1, 0, 1, 3, 1, 2, 3, 2, 0, 3]);
+        assert!(tetrahedron.boundary_loops().is_empty());
+        assert!(tetrahedron.half_edges().iter().all(|h| h.twin.is_some()));
+    }
+
+    #[test]
+    fn test_non_manifold_edge() {
+        // Three triangles share the edge 0-1.
+        let topology = Topology::new(5, &[0, 1, 2, 1, 0, 3, 0, 1, 4]);
+        let edge = topology.find_edge(0, 1).unwrap();
+        assert!(!topology.is_manifold_edge(edge));
+        assert_eq!(topology.edge_half_edges(edge).len(), 3);
+        assert!(topology.half_edges()[0].twin.is_none());
+    }
+}
@@ -0,0 +1,153 @@
+//! Checks for problems that make a mesh unsuitable for simulation.
+
+use crate::topology::Topology;
+use crate::Mesh;
+use std::collections::HashSet;
+
+/// The problems found by [`Mesh::validate`].
+///
+/// Each list holds the indices of the offending elements in ascending order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ValidationReport {
+    /// Triangles that refer to a vertex that does not exist.
+    pub out_of_range_triangles: Vec<usize>,
+    /// Vertices with a NaN or infinite coordinate.
+    pub non_finite_vertices: Vec<usize>,
+    /// Triangles that repeat a vertex or have zero area.
+    pub degenerate_triangles: Vec<usize>,
+    /// Triangles that use the same three vertices as an earlier triangle,
+    /// regardless of winding.
+    pub duplicate_triangles: Vec<usize>,
+    /// Edges shared by more than two triangles, as `[low, high]` vertex pairs.
+    pub non_manifold_edges: Vec<[usize; 2]>,
+    /// Vertices that are not used by any triangle.
+    pub isolated_vertices: Vec<usize>,
+}
+
+impl ValidationReport {
+    /// Returns `true` if no problems were found.
+    pub fn is_valid(&self) -> bool {
+        *self == ValidationReport::default()
+    }
+}
+
+impl Mesh {
+    /// Checks the mesh for problems that `Mesh::new` does not reject.
+    ///
+    /// The mesh fields are public and can be edited after construction, so
+    /// this also re-checks indices and coordinates. Triangles with
+    /// out-of-range indices are excluded from the remaining checks, and
+    /// triangles that repeat a vertex are not counted towards non-manifold edges.
+    ///
+    /// # Returns
+    ///
+    /// A `ValidationReport` listing every problem found.
+    pub fn validate(&self) -> ValidationReport {
+        let vertex_count = self.vertex_count();
+        let mut report = ValidationReport {
+            non_finite_vertices: self
+                .positions
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| !p.iter().all(|c| c.is_finite()))
+                .map(|(i, _)| i)
+                .collect(),
+            ..ValidationReport::default()
+        };
+
+        let mut manifold_indices = Vec::with_capacity(self.indices.len());
+        let mut used = vec![false; vertex_count];
+        let mut seen = HashSet::new();
+        for (t, triangle) in self.indices.chunks_exact(3).enumerate() {
+            if triangle.iter().any(|&i| i as usize >= vertex_count) {
+                report.out_of_range_triangles.push(t);
+                continue;
+            }
+            for &i in triangle {
+                used[i as usize] = true;
+            }
+
+            let mut key = [triangle[0], triangle[1], triangle[2]];
+            key.sort_unstable();
+            let repeats_vertex = key[0] == key[1] || key[1] == key[2];
+            if !repeats_vertex {
+                manifold_indices.extend_from_slice(triangle);
+            }
+            if repeats_vertex || self.triangle_has_zero_area(triangle) {
+                report.degenerate_triangles.push(t);
+            }
+            if !seen.insert(key) {
+                report.duplicate_triangles.push(t);
+            }
+        }
+
+        let topology = Topology::new(vertex_count, &manifold_indices);
+        report.non_manifold_edges = (0..topology.edges().len())
+            .filter(|&e| !topology.is_manifold_edge(e))
+            .map(|e| topology.edges()[e])
+            .collect();
+        report.non_manifold_edges.sort_unstable();
+        report.isolated_vertices = (0..vertex_count).filter(|&v| !used[v]).collect();
+        report
+    }
+
+    /// Returns `true` if the triangle's area is negligible relative to its size.
+    pub(crate) fn triangle_has_zero_area(&self, triangle: &[u32]) -> bool {
+        let a = self.positions[triangle[0] as usize];
+        let b = self.positions[triangle[1] as usize];
+        let c = self.positions[triangle[2] as usize];
+        let (ab, ac) = (b - a, c - a);
+        // Compare against the edge lengths so the test does not depend on scale.
+        let scale = ab.norm_squared().max(ac.norm_squared());
+        ab.cross(&ac).norm() <= f32::EPSILON * scale
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_clean_mesh() {
+        let mesh = Mesh::new(
+            &[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0],
+            &[0, 1, 2, 0, 2, 3],
+        )
+        .unwrap();
+        assert!(mesh.validate().is_valid());
+    }
+
+    #[test]
+    fn test_validate_reports_problems() {
+        let positions = [
+            0.0, 0.0, 0.0, // 0
+            1.0, 0.0, 0.0, // 1
+            0.0, 1.0, 0.0, // 2
+            2.0, 0.0, 0.0, // 3, collinear with 0 and 1
+            0.0, 0.0, 1.0, // 4
+            0.0, 0.0, -1.0, // 5
+            5.0, 5.0, 5.0, // 6, unused
+        ];
+        let indices = [
+            0, 1, 2, // 0
+            2, 1, 0, // 1, duplicate of 0
+            0, 1, 3, // 2, zero area
+            1, 1, 2, // 3, repeated vertex
+            0, 1, 4, // 4, third triangle on edge 0-1
+            1, 0, 5, // 5, fourth triangle on edge 0-1
+        ];
+        let mut mesh = Mesh::new(&positions, &indices).unwrap();
+        mesh.indices.extend_from_slice(&[0, 1, 9]);
+        mesh.positions[5].x = f32::NAN;
+
+        let report = mesh.validate();
+        assert!(!report.is_valid());
+        assert_eq!(report.out_of_range_triangles, vec![6]);
+        assert_eq!(report.non_finite_vertices, vec![5]);
+        assert_eq!(report.degenerate_triangles, vec![2, 3]);
+        assert_eq!(report.duplicate_triangles, vec![1]);
+        assert_eq!(report.non_manifold_edges, vec![[0, 1]]);
+        assert_eq!(report.isolated_vertices, vec![6]);
+    }
+}
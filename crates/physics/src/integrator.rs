@@ -0,0 +1,472 @@
+//! Time integration schemes for the simulation.
+//!
+//! The mesh only stores the current and previous position of each vertex.
+//! Integrators that work with velocities derive them as the difference of
+//! the two over the time step, and store the new velocity back by setting
+//! the previous position to `position - velocity * dt`, so any integrator can
+//! pick up where another one left off.
+
+use crate::{Attachment, Spring};
+use mesh::Mesh;
+use nalgebra::Vector3;
+
+/// The forces acting on the vertices of a mesh during one time step.
+pub struct Forces<'a> {
+    /// The springs pulling the vertices together.
+    pub springs: &'a [Spring],
+    /// The gravity vector applied to all vertices.
+    pub gravity: Vector3<f32>,
+    /// The attachments of the vertices. Tethered vertices are pulled towards
+    /// their targets; other attachments exert no force.
+    pub attachments: &'a [(usize, Attachment)],
+    /// The animated target of each vertex.
+    pub targets: &'a [Vector3<f32>],
+    /// The inverse mass of each vertex. Vertices with an inverse mass of zero
+    /// are not accelerated and are not moved by the integrators.
+    pub inverse_masses: &'a [f32],
+    /// The length of the time step, in seconds.
+    pub time_step: f32,
+}
+
+impl Forces<'_> {
+    /// Computes the acceleration of every vertex.
+    ///
+    /// The damping of a spring acts on how much its ends move apart over one
+    /// time step.
+    ///
+    /// # Arguments
+    ///
+    /// * `positions` - The position of each vertex.
+    /// * `velocities` - The velocity of each vertex.
+    /// * `accelerations` - Receives the acceleration of each vertex.
+    pub fn accelerations(
+        &self,
+        positions: &[Vector3<f32>],
+        velocities: &[Vector3<f32>],
+        accelerations: &mut [Vector3<f32>],
+    ) {
+        for (acceleration, &inverse_mass) in accelerations.iter_mut().zip(self.inverse_masses) {
+            *acceleration = if inverse_mass == 0.0 {
+                Vector3::zeros()
+            } else {
+                self.gravity
+            };
+        }
+
+        for spring in self.springs {
+            let (a, b) = (spring.vertex_a_index, spring.vertex_b_index);
+            let delta = positions[a] - positions[b];
+            let distance = delta.magnitude();
+            // Avoid division by zero if vertices are at the same position
+            if distance > 1e-6 {
+                let direction = delta / distance;
+                let stretch = distance - spring.rest_length;
+                let spring_force = spring.stiffness * stretch * direction;
+                let relative_velocity = (velocities[a] - velocities[b]) * self.time_step;
+                let damping_force = spring.damping * relative_velocity.dot(&direction) * direction;
+                let total_force = spring_force + damping_force;
+
+                accelerations[a] -= total_force * self.inverse_masses[a];
+                accelerations[b] += total_force * self.inverse_masses[b];
+            }
+        }
+
+        for (i, stiffness, target) in self.tethers() {
+            accelerations[i] += stiffness * (target - positions[i]) * self.inverse_masses[i];
+        }
+    }
+
+    /// Returns each tethered vertex with its stiffness and target.
+    fn tethers(&self) -> impl Iterator<Item = (usize, f32, Vector3<f32>)> + '_ {
+        self.attachments.iter().filter_map(|&(i, attachment)| {
+            match (attachment, self.targets.get(i)) {
+                (Attachment::Tethered { stiffness }, Some(&target)) => Some((i, stiffness, target)),
+                _ => None,
+            }
+        })
+    }
+}
+
+/// Advances the vertices of a mesh by one time step under a set of forces.
+pub trait Integrator {
+    /// Moves the vertices of `mesh` by one time step of `forces.time_step`,
+    /// leaving vertices with an inverse mass of zero where they are. The
+    /// accelerations used are left in `mesh.accelerations`.
+    fn step(&self, mesh: &mut Mesh, forces: &Forces);
+}
+
+/// Position Verlet integration: cheap and good at conserving energy, but
+/// stiff springs need small time steps.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Verlet;
+
+impl Integrator for Verlet {
+    fn step(&self, mesh: &mut Mesh, forces: &Forces) {
+        let dt = forces.time_step;
+        let velocities = velocities(mesh, dt);
+        forces.accelerations(&mesh.positions, &velocities, &mut mesh.accelerations);
+
+        let dt2 = dt * dt;
+        for (((position, old_position), acceleration), &inverse_mass) in mesh
+            .positions
+            .iter_mut()
+            .zip(&mut mesh.old_positions)
+            .zip(&mesh.accelerations)
+            .zip(forces.inverse_masses)
+        {
+            if inverse_mass == 0.0 {
+                continue;
+            }
+            let previous = *position;
+            *position += (*position - *old_position) + acceleration * dt2;
+            *old_position = previous;
+        }
+    }
+}
+
+/// Semi-implicit (symplectic) Euler integration: the velocity is updated
+/// first and then moves the vertex. As cheap as Verlet, with similar
+/// stability.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SemiImplicitEuler;
+
+impl Integrator for SemiImplicitEuler {
+    fn step(&self, mesh: &mut Mesh, forces: &Forces) {
+        let dt = forces.time_step;
+        let mut velocities = velocities(mesh, dt);
+        forces.accelerations(&mesh.positions, &velocities, &mut mesh.accelerations);
+        for (velocity, acceleration) in velocities.iter_mut().zip(&mesh.accelerations) {
+            *velocity += acceleration * dt;
+        }
+        store(mesh, &velocities, forces.inverse_masses, dt);
+    }
+}
+
+/// Classical fourth-order Runge-Kutta integration: accurate for smooth
+/// motion at four force evaluations per step, but no more stable than the
+/// explicit schemes for stiff springs.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Rk4;
+
+impl Integrator for Rk4 {
+    fn step(&self, mesh: &mut Mesh, forces: &Forces) {
+        let dt = forces.time_step;
+        let count = mesh.vertex_count();
+        let start = velocities(mesh, dt);
+
+        // Each stage evaluates the derivatives at the state reached by
+        // following the previous stage for `scale * dt`.
+        let mut position_rate = vec![Vector3::zeros(); count];
+        let mut velocity_rate = vec![Vector3::zeros(); count];
+        let mut position_sum = vec![Vector3::zeros(); count];
+        let mut velocity_sum = vec![Vector3::zeros(); count];
+        let mut positions = mesh.positions.clone();
+        let mut velocities = start.clone();
+        for (scale, weight) in [(0.0, 1.0), (0.5, 2.0), (0.5, 2.0), (1.0, 1.0)] {
+            for v in 0..count {
+                if forces.inverse_masses[v] != 0.0 {
+                    positions[v] = mesh.positions[v] + position_rate[v] * (scale * dt);
+                    velocities[v] = start[v] + velocity_rate[v] * (scale * dt);
+                }
+            }
+            position_rate.clone_from(&velocities);
+            forces.accelerations(&positions, &velocities, &mut velocity_rate);
+            for v in 0..count {
+                position_sum[v] += position_rate[v] * weight;
+                velocity_sum[v] += velocity_rate[v] * weight;
+            }
+        }
+
+        for v in 0..count {
+            mesh.accelerations[v] = velocity_sum[v] / 6.0;
+            if forces.inverse_masses[v] != 0.0 {
+                let position = mesh.positions[v] + position_sum[v] * (dt / 6.0);
+                let velocity = start[v] + velocity_sum[v] * (dt / 6.0);
+                mesh.positions[v] = position;
+                mesh.old_positions[v] = position - velocity * dt;
+            }
+        }
+    }
+}
+
+/// Backward Euler integration, solved with one Newton step (Baraff and
+/// Witkin). The linear system is solved by conjugate gradient on the
+/// Jacobian of the spring and tether forces. It stays stable with stiff
+/// springs and large time steps, at the cost of extra damping.
+#[derive(Clone, Copy, Debug)]
+pub struct ImplicitEuler {
+    /// The most conjugate gradient iterations per step.
+    pub max_iterations: usize,
+    /// The residual, relative to the right-hand side, at which the solver stops.
+    pub tolerance: f32,
+}
+
+impl Default for ImplicitEuler {
+    fn default() -> Self {
+        Self {
+            max_iterations: 50,
+            tolerance: 1e-4,
+        }
+    }
+}
+
+impl Integrator for ImplicitEuler {
+    fn step(&self, mesh: &mut Mesh, forces: &Forces) {
+        let dt = forces.time_step;
+        let mut velocities = velocities(mesh, dt);
+        forces.accelerations(&mesh.positions, &velocities, &mut mesh.accelerations);
+        let jacobian = Jacobian::new(&mesh.positions, forces);
+
+        // Solve (M - dt D - dt² K) Δv = dt (f + dt K v) for the change of
+        // velocity, where K and D are the derivatives of the forces by the
+        // positions and velocities.
+        let mut stiffness_velocity = vec![Vector3::zeros(); velocities.len()];
+        jacobian.add_stiffness_product(&velocities, 1.0, &mut stiffness_velocity);
+        let rhs: Vec<Vector3<f32>> = (0..velocities.len())
+            .map(|v| match forces.inverse_masses[v] {
+                0.0 => Vector3::zeros(),
+                w => (mesh.accelerations[v] / w + stiffness_velocity[v] * dt) * dt,
+            })
+            .collect();
+        let change = conjugate_gradient(
+            |p, out| jacobian.system_product(p, dt, out),
+            &rhs,
+            self.max_iterations,
+            self.tolerance,
+        );
+        for (velocity, change) in velocities.iter_mut().zip(&change) {
+            *velocity += change;
+        }
+        store(mesh, &velocities, forces.inverse_masses, dt);
+    }
+}
+
+/// The derivatives of the spring and tether forces, linearized at the
+/// current positions.
+struct Jacobian<'a> {
+    forces: &'a Forces<'a>,
+    /// For each spring, the unit direction from its second vertex to its
+    /// first and the clamped transverse stiffness factor `1 - rest / length`.
+    springs: Vec<(Vector3<f32>, f32)>,
+}
+
+impl<'a> Jacobian<'a> {
+    fn new(positions: &[Vector3<f32>], forces: &'a Forces<'a>) -> Self {
+        let springs = forces
+            .springs
+            .iter()
+            .map(|spring| {
+                let delta = positions[spring.vertex_a_index] - positions[spring.vertex_b_index];
+                let distance = delta.magnitude();
+                if distance > 1e-6 {
+                    // Dropping the transverse term of compressed springs
+                    // keeps the system positive definite.
+                    let transverse = (1.0 - spring.rest_length / distance).max(0.0);
+                    (delta / distance, transverse)
+                } else {
+                    (Vector3::zeros(), 0.0)
+                }
+            })
+            .collect();
+        Self { forces, springs }
+    }
+
+    /// Adds `scale` times the product of the stiffness matrix K with `p` to
+    /// `out`.
+    fn add_stiffness_product(&self, p: &[Vector3<f32>], scale: f32, out: &mut [Vector3<f32>]) {
+        for (spring, &(direction, transverse)) in self.forces.springs.iter().zip(&self.springs) {
+            let (a, b) = (spring.vertex_a_index, spring.vertex_b_index);
+            let q = p[a] - p[b];
+            let along = direction * direction.dot(&q);
+            let force = -(along + (q - along) * transverse) * (spring.stiffness * scale);
+            out[a] += force;
+            out[b] -= force;
+        }
+        for (i, stiffness, _) in self.forces.tethers() {
+            out[i] -= p[i] * (stiffness * scale);
+        }
+    }
+
+    /// Computes `(M - dt D - dt² K) p`, leaving the rows of immovable
+    /// vertices at zero.
+    fn system_product(&self, p: &[Vector3<f32>], dt: f32, out: &mut [Vector3<f32>]) {
+        out.fill(Vector3::zeros());
+        self.add_stiffness_product(p, -dt * dt, out);
+        let damping_scale = dt * self.forces.time_step;
+        for (spring, &(direction, _)) in self.forces.springs.iter().zip(&self.springs) {
+            let (a, b) = (spring.vertex_a_index, spring.vertex_b_index);
+            let force =
+                direction * (direction.dot(&(p[a] - p[b])) * spring.damping * damping_scale);
+            out[a] += force;
+            out[b] -= force;
+        }
+        for ((out, p), &inverse_mass) in out.iter_mut().zip(p).zip(self.forces.inverse_masses) {
+            *out = if inverse_mass == 0.0 {
+                Vector3::zeros()
+            } else {
+                *out + p / inverse_mass
+            };
+        }
+    }
+}
+
+/// Solves a symmetric positive definite system given as a matrix-vector
+/// product, starting from zero.
+fn conjugate_gradient(
+    product: impl Fn(&[Vector3<f32>], &mut [Vector3<f32>]),
+    rhs: &[Vector3<f32>],
+    max_iterations: usize,
+    tolerance: f32,
+) -> Vec<Vector3<f32>> {
+    let dot = |a: &[Vector3<f32>], b: &[Vector3<f32>]| -> f32 {
+        a.iter().zip(b).map(|(a, b)| a.dot(b)).sum()
+    };
+    let mut x = vec![Vector3::zeros(); rhs.len()];
+    let mut residual = rhs.to_vec();
+    let mut direction = residual.clone();
+    let mut product_direction = vec![Vector3::zeros(); rhs.len()];
+    let mut residual_squared = dot(&residual, &residual);
+    let threshold = residual_squared * tolerance * tolerance;
+    for _ in 0..max_iterations {
+        if residual_squared <= threshold || residual_squared == 0.0 {
+            break;
+        }
+        product(&direction, &mut product_direction);
+        let curvature = dot(&direction, &product_direction);
+        if curvature <= 0.0 {
+            break;
+        }
+        let alpha = residual_squared / curvature;
+        for v in 0..x.len() {
+            x[v] += direction[v] * alpha;
+            residual[v] -= product_direction[v] * alpha;
+        }
+        let next = dot(&residual, &residual);
+        let beta = next / residual_squared;
+        residual_squared = next;
+        for (direction, residual) in direction.iter_mut().zip(&residual) {
+            *direction = residual + *direction * beta;
+        }
+    }
+    x
+}
+
+/// Returns the velocity of each vertex over the last time step.
+fn velocities(mesh: &Mesh, dt: f32) -> Vec<Vector3<f32>> {
+    mesh.positions
+        .iter()
+        .zip(&mesh.old_positions)
+        .map(|(position, old_position)| (position - old_position) / dt)
+        .collect()
+}
+
+/// Moves each movable vertex by its new velocity over the time step and
+/// records the velocity in its previous position.
+fn store(mesh: &mut Mesh, velocities: &[Vector3<f32>], inverse_masses: &[f32], dt: f32) {
+    for (((position, old_position), velocity), &inverse_mass) in mesh
+        .positions
+        .iter_mut()
+        .zip(&mut mesh.old_positions)
+        .zip(velocities)
+        .zip(inverse_masses)
+    {
+        if inverse_mass != 0.0 {
+            *old_position = *position;
+            *position += velocity * dt;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SpringKind;
+
+    const STIFFNESS: f32 = 100.0;
+
+    // A unit mass on a spring of rest length 1 to a fixed vertex, released
+    // from rest at length 1.5. It oscillates as 1 + 0.5 cos(10 t).
+    fn oscillator() -> (Mesh, Vec<Spring>) {
+        let mut mesh = Mesh::new(&[0.0, 0.0, 0.0, 1.5, 0.0, 0.0], &[]).unwrap();
+        mesh.inverse_masses[0] = 0.0;
+        let spring = Spring {
+            vertex_a_index: 0,
+            vertex_b_index: 1,
+            rest_length: 1.0,
+            stiffness: STIFFNESS,
+            damping: 0.0,
+            kind: SpringKind::Structural,
+            plastic_strain: 0.0,
+        };
+        (mesh, vec![spring])
+    }
+
+    fn simulate(integrator: &dyn Integrator, time_step: f32, steps: usize) -> Mesh {
+        let (mut mesh, springs) = oscillator();
+        let inverse_masses = mesh.inverse_masses.clone();
+        let forces = Forces {
+            springs: &springs,
+            gravity: Vector3::zeros(),
+            attachments: &[],
+            targets: &[],
+            inverse_masses: &inverse_masses,
+            time_step,
+        };
+        for _ in 0..steps {
+            integrator.step(&mut mesh, &forces);
+        }
+        mesh
+    }
+
+    fn integrators() -> Vec<(&'static str, Box<dyn Integrator>)> {
+        vec![
+            ("verlet", Box::new(Verlet)),
+            ("semi-implicit euler", Box::new(SemiImplicitEuler)),
+            ("rk4", Box::new(Rk4)),
+            ("implicit euler", Box::new(ImplicitEuler::default())),
+        ]
+    }
+
+    #[test]
+    fn test_integrators_follow_oscillator() {
+        let time = 0.5;
+        let expected = 1.0 + 0.5 * (STIFFNESS.sqrt() * time).cos();
+        for (name, integrator) in integrators() {
+            let mesh = simulate(integrator.as_ref(), 0.001, 500);
+            assert_eq!(mesh.positions[0], Vector3::zeros(), "{}", name);
+            let error = (mesh.positions[1].x - expected).abs();
+            assert!(error < 0.02, "{}: error {}", name, error);
+        }
+    }
+
+    #[test]
+    fn test_rk4_is_most_accurate() {
+        let time = 0.5;
+        let expected = 1.0 + 0.5 * (STIFFNESS.sqrt() * time).cos();
+        let errors: Vec<f32> = integrators()
+            .iter()
+            .map(|(_, integrator)| {
+                (simulate(integrator.as_ref(), 0.02, 25).positions[1].x - expected).abs()
+            })
+            .collect();
+        assert!(errors[2] < 1e-3, "{:?}", errors);
+        assert!(
+            errors.iter().all(|&error| error >= errors[2]),
+            "{:?}",
+            errors
+        );
+    }
+
+    #[test]
+    fn test_implicit_euler_is_stable_at_large_time_steps() {
+        // A step longer than the period of the oscillator.
+        for (name, integrator) in integrators() {
+            let x = simulate(integrator.as_ref(), 0.7, 50).positions[1].x;
+            if name == "implicit euler" {
+                assert!((x - 1.0).abs() < 0.01, "{}", x);
+            } else {
+                assert!(!x.is_finite() || (x - 1.0).abs() > 1.0, "{}: {}", name, x);
+            }
+        }
+    }
+}
@@ -1,6 +1,20 @@
+pub mod integrator;
+pub mod material;
+pub mod timestep;
+pub mod xpbd;
+
+use integrator::{Forces, Integrator, Verlet};
+use material::Material;
+use mesh::components::Components;
+use mesh::topology::Topology;
 use mesh::Mesh;
 use nalgebra::Vector3;
 use std::collections::HashSet;
+use xpbd::Xpbd;
+
+/// The smallest angle, in radians, between the two edges from a vertex to
+/// the ends of a bending spring across it.
+const MIN_BENDING_ANGLE: f32 = 2.0 * std::f32::consts::FRAC_PI_3;
 
 /// Represents a spring connecting two vertices in a mesh.
 ///
@@ -17,12 +31,104 @@ pub struct Spring {
     pub stiffness: f32,
     /// The damping factor of the spring, used to reduce oscillations.
     pub damping: f32,
+    /// What the spring holds in shape.
+    pub kind: SpringKind,
+    /// The permanent relative change of the rest length from plastic
+    /// deformation. It is kept on top of the rest shape when
+    /// `Physics::set_rest_shape` changes it.
+    pub plastic_strain: f32,
+}
+
+/// The role of a spring in holding the mesh in shape.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SpringKind {
+    /// Runs along an edge of the mesh and keeps its length.
+    Structural,
+    /// Joins the opposite corners of two triangles that share an edge, the
+    /// other diagonal of the quad they form, and keeps the pair from
+    /// shearing.
+    Shear,
+    /// Joins two neighbours of a vertex that lie roughly on a straight line
+    /// through it, and keeps the surface from folding at the vertex.
+    Bending,
+}
+
+/// The stiffness and damping given to new springs of one kind.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpringParameters {
+    /// The stiffness of the springs.
+    pub stiffness: f32,
+    /// The damping factor of the springs.
+    pub damping: f32,
+}
+
+/// Which springs `Physics::init_springs` creates, and their parameters.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpringConfig {
+    /// The parameters of the springs along the edges.
+    pub structural: SpringParameters,
+    /// The parameters of the shear springs, or `None` for no shear springs.
+    pub shear: Option<SpringParameters>,
+    /// The parameters of the bending springs, or `None` for no bending
+    /// springs.
+    pub bending: Option<SpringParameters>,
+}
+
+impl Default for SpringConfig {
+    /// Creates a configuration with structural springs only.
+    fn default() -> Self {
+        Self {
+            structural: SpringParameters {
+                stiffness: 1000.0,
+                damping: 10.0,
+            },
+            shear: None,
+            bending: None,
+        }
+    }
+}
+
+impl SpringConfig {
+    /// Returns the parameters of the springs of one kind, or `None` if
+    /// springs of that kind are not created.
+    pub fn parameters(&self, kind: SpringKind) -> Option<SpringParameters> {
+        match kind {
+            SpringKind::Structural => Some(self.structural),
+            SpringKind::Shear => self.shear,
+            SpringKind::Bending => self.bending,
+        }
+    }
+}
+
+/// How an attached vertex follows its animated target.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Attachment {
+    /// The vertex moves exactly with its target and ignores all forces.
+    Pinned,
+    /// The vertex is pulled towards its target by a spring of zero rest
+    /// length, so it lags behind and jiggles around the animation.
+    Tethered {
+        /// The stiffness of the spring pulling the vertex to its target.
+        stiffness: f32,
+    },
+}
+
+/// How the simulation keeps the mesh in shape.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Solver {
+    /// Spring forces along the edges, integrated explicitly. Stiff springs
+    /// need small time steps.
+    #[default]
+    Springs,
+    /// Position-based constraints, which stay stable at any time step. The
+    /// springs are ignored.
+    Xpbd(Xpbd),
 }
 
 /// Manages the physics simulation for a mesh.
 ///
 /// This includes handling springs, gravity, and updating vertex positions
-/// using Verlet integration.
+/// with an `Integrator`, Verlet integration by default.
 pub struct Physics {
     /// A vector of `Spring` structs that define the connections in the mesh.
     pub springs: Vec<Spring>,
@@ -30,6 +136,25 @@ pub struct Physics {
     pub time_step: f32,
     /// The gravity vector applied to all vertices in the simulation.
     pub gravity: Vector3<f32>,
+    /// The vertices that follow an animated target, and how. Attachments of
+    /// vertices that the updated mesh does not have are ignored.
+    pub attachments: Vec<(usize, Attachment)>,
+    /// The animated target of each vertex, such as the skinned positions of
+    /// the mesh. Attachments of vertices without a target have no effect.
+    pub targets: Vec<Vector3<f32>>,
+    /// Whether the mesh is held together by the springs or by constraints.
+    pub solver: Solver,
+    /// How the vertices are moved under the forces, `Verlet` by default.
+    pub integrator: Box<dyn Integrator>,
+    /// The kinds of springs `init_springs` creates.
+    pub spring_config: SpringConfig,
+    /// The material of each vertex, or empty if no material has been
+    /// assigned and the springs keep their own parameters.
+    pub materials: Vec<Material>,
+    /// The rest position of each vertex, from the last call to
+    /// `init_springs`, `init_constraints` or `set_rest_shape`. The masses
+    /// of materials are computed from it.
+    pub rest_positions: Vec<Vector3<f32>>,
 }
 
 impl Default for Physics {
@@ -39,6 +164,13 @@ impl Default for Physics {
             springs: Vec::new(),
             time_step: 0.01,
             gravity: Vector3::new(0.0, -9.81, 0.0),
+            attachments: Vec::new(),
+            targets: Vec::new(),
+            solver: Solver::Springs,
+            integrator: Box::new(Verlet),
+            spring_config: SpringConfig::default(),
+            materials: Vec::new(),
+            rest_positions: Vec::new(),
         }
     }
 }
@@ -51,46 +183,376 @@ impl Physics {
 
     /// Initializes the springs for the physics simulation from a given mesh.
     ///
-    /// This method creates a spring for each unique edge in the mesh's triangles.
+    /// This method creates a structural spring for each unique edge in the
+    /// mesh's triangles, and shear and bending springs if `spring_config`
+    /// asks for them.
     ///
     /// # Arguments
     ///
     /// * `mesh` - A reference to the `Mesh` to create springs from.
     pub fn init_springs(&mut self, mesh: &Mesh) {
-        let mut existing_springs = HashSet::new();
-        for triangle in mesh.indices.chunks_exact(3) {
-            let (a, b, c) = (
-                triangle[0] as usize,
-                triangle[1] as usize,
-                triangle[2] as usize,
+        self.init_springs_with_topology(mesh, &mesh.topology());
+    }
+
+    /// Initializes the springs from a mesh whose topology has already been built.
+    ///
+    /// This avoids rebuilding the edge list when the caller keeps a
+    /// `Topology` around for other purposes.
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh` - A reference to the `Mesh` to create springs from.
+    /// * `topology` - The topology of `mesh`.
+    pub fn init_springs_with_topology(&mut self, mesh: &Mesh, topology: &Topology) {
+        self.rest_positions.clone_from(&mesh.positions);
+        let config = self.spring_config;
+        // Each pair of vertices gets at most one spring, of the first kind
+        // that joins them.
+        let mut joined = HashSet::new();
+        let mut add = |springs: &mut Vec<Spring>, a: usize, b: usize, kind, parameters| {
+            if a == b || !joined.insert((a.min(b), a.max(b))) {
+                return;
+            }
+            let SpringParameters { stiffness, damping } = parameters;
+            springs.push(Spring {
+                vertex_a_index: a.min(b),
+                vertex_b_index: a.max(b),
+                rest_length: (mesh.positions[a] - mesh.positions[b]).magnitude(),
+                stiffness,
+                damping,
+                kind,
+                plastic_strain: 0.0,
+            });
+        };
+
+        for &[v1_idx, v2_idx] in topology.edges() {
+            add(
+                &mut self.springs,
+                v1_idx,
+                v2_idx,
+                SpringKind::Structural,
+                config.structural,
             );
-            let edges = [(a, b), (b, c), (c, a)];
-            for (v1_idx, v2_idx) in edges.iter() {
-                let (v1_idx, v2_idx) = if v1_idx < v2_idx {
-                    (*v1_idx, *v2_idx)
-                } else {
-                    (*v2_idx, *v1_idx)
-                };
-                if existing_springs.insert((v1_idx, v2_idx)) {
-                    let rest_length = (mesh.vertices[v1_idx].position
-                        - mesh.vertices[v2_idx].position)
-                        .magnitude();
-                    self.springs.push(Spring {
-                        vertex_a_index: v1_idx,
-                        vertex_b_index: v2_idx,
-                        rest_length,
-                        stiffness: 1000.0,
-                        damping: 10.0,
-                    });
+        }
+
+        if let Some(parameters) = config.shear {
+            for (h, half_edge) in topology.half_edges().iter().enumerate() {
+                if let Some(twin) = half_edge.twin.filter(|&twin| twin > h) {
+                    let (a, b) = (topology.opposite_vertex(h), topology.opposite_vertex(twin));
+                    add(&mut self.springs, a, b, SpringKind::Shear, parameters);
+                }
+            }
+        }
+
+        if let Some(parameters) = config.bending {
+            let min_cosine = MIN_BENDING_ANGLE.cos();
+            for center in 0..topology.vertex_count() {
+                let ring = topology.one_ring(center);
+                let directions: Vec<Option<Vector3<f32>>> = ring
+                    .iter()
+                    .map(|&v| (mesh.positions[v] - mesh.positions[center]).try_normalize(1e-12))
+                    .collect();
+                for (i, &a) in ring.iter().enumerate() {
+                    let Some(from) = directions[i] else {
+                        continue;
+                    };
+                    // The neighbour on the far side of the straightest line
+                    // from `a` through the vertex.
+                    let straightest = ring
+                        .iter()
+                        .zip(&directions)
+                        .filter_map(|(&c, to)| Some((c, from.dot(&(*to)?))))
+                        .filter(|&(c, cosine)| c != a && cosine <= min_cosine)
+                        .min_by(|x, y| x.1.total_cmp(&y.1));
+                    if let Some((c, _)) = straightest {
+                        add(&mut self.springs, a, c, SpringKind::Bending, parameters);
+                    }
                 }
             }
         }
     }
 
+    /// Sets the stiffness and damping of every spring of one kind, and of
+    /// the springs of that kind created from now on.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The kind of springs to change.
+    /// * `stiffness` - The new stiffness of the springs.
+    /// * `damping` - The new damping factor of the springs.
+    pub fn set_spring_parameters(&mut self, kind: SpringKind, stiffness: f32, damping: f32) {
+        let parameters = SpringParameters { stiffness, damping };
+        match kind {
+            SpringKind::Structural => self.spring_config.structural = parameters,
+            SpringKind::Shear => self.spring_config.shear = Some(parameters),
+            SpringKind::Bending => self.spring_config.bending = Some(parameters),
+        }
+        for spring in self.springs.iter_mut().filter(|spring| spring.kind == kind) {
+            spring.stiffness = stiffness;
+            spring.damping = damping;
+        }
+    }
+
+    /// Switches the simulation to the XPBD solver, with a distance constraint
+    /// along each edge of the mesh and a bending constraint across each edge
+    /// between two triangles.
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh` - A reference to the `Mesh` to create constraints from.
+    /// * `distance_compliance` - The inverse stiffness of the edges.
+    /// * `bending_compliance` - The inverse stiffness of the folds between
+    ///   triangles.
+    pub fn init_constraints(
+        &mut self,
+        mesh: &Mesh,
+        distance_compliance: f32,
+        bending_compliance: f32,
+    ) {
+        let topology = mesh.topology();
+        let mut xpbd = Xpbd::new();
+        xpbd.add_distance_constraints(mesh, &topology, distance_compliance);
+        xpbd.add_bending_constraints(mesh, &topology, bending_compliance);
+        self.solver = Solver::Xpbd(xpbd);
+        self.rest_positions.clone_from(&mesh.positions);
+    }
+
+    /// Changes the shape the springs pull the mesh towards.
+    ///
+    /// Each spring takes its rest length from the distance between its
+    /// vertices in `positions`, stretched or shrunk by its plastic strain so
+    /// permanent deformation survives the change. Passing the result of
+    /// `Mesh::blend_morph_targets` lets an expression drive the rest shape
+    /// while the simulation keeps adding its own motion on top.
+    ///
+    /// # Arguments
+    ///
+    /// * `positions` - The rest position of each vertex of the simulated mesh.
+    pub fn set_rest_shape(&mut self, positions: &[Vector3<f32>]) {
+        self.rest_positions.clear();
+        self.rest_positions.extend_from_slice(positions);
+        for spring in &mut self.springs {
+            let length =
+                (positions[spring.vertex_a_index] - positions[spring.vertex_b_index]).magnitude();
+            spring.rest_length = length * (1.0 + spring.plastic_strain);
+        }
+        if let Solver::Xpbd(xpbd) = &mut self.solver {
+            xpbd.set_rest_shape(positions);
+        }
+    }
+
+    /// Sets the stiffness and damping of all springs of one island of the
+    /// mesh, so separate pieces such as the eyes and the skin can be given
+    /// different material parameters.
+    ///
+    /// # Arguments
+    ///
+    /// * `components` - The connected components of the simulated mesh, from
+    ///   `Mesh::connected_components`.
+    /// * `island` - The component whose springs to change.
+    /// * `stiffness` - The new stiffness of the springs.
+    /// * `damping` - The new damping factor of the springs.
+    pub fn set_island_parameters(
+        &mut self,
+        components: &Components,
+        island: usize,
+        stiffness: f32,
+        damping: f32,
+    ) {
+        // Springs only join corners of adjacent triangles, so both ends
+        // always lie in the same island.
+        for spring in &mut self.springs {
+            if components.vertex_labels[spring.vertex_a_index] == island {
+                spring.stiffness = stiffness;
+                spring.damping = damping;
+            }
+        }
+    }
+
+    /// Assigns a material to a group of vertices, such as the vertices of a
+    /// region selected in the UI, and applies the materials to the mesh.
+    ///
+    /// Vertices that have no material yet get `Material::default()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh` - The simulated mesh, whose inverse masses are updated.
+    /// * `vertices` - The vertices to assign the material to. Out of range
+    ///   indices are ignored.
+    /// * `material` - The new material of the vertices.
+    pub fn set_material(&mut self, mesh: &mut Mesh, vertices: &[usize], material: Material) {
+        self.materials
+            .resize(mesh.vertex_count(), Material::default());
+        for &v in vertices {
+            if let Some(slot) = self.materials.get_mut(v) {
+                *slot = material;
+            }
+        }
+        self.apply_materials(mesh);
+    }
+
+    /// Blends a material into the material of every vertex by a painted
+    /// weight, and applies the materials to the mesh.
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh` - The simulated mesh, whose inverse masses are updated.
+    /// * `material` - The material to paint.
+    /// * `weights` - How much of `material` each vertex takes, from 0 to 1.
+    ///   Vertices beyond the end of `weights` are left unchanged.
+    pub fn paint_material(&mut self, mesh: &mut Mesh, material: Material, weights: &[f32]) {
+        self.materials
+            .resize(mesh.vertex_count(), Material::default());
+        for (slot, &weight) in self.materials.iter_mut().zip(weights) {
+            *slot = slot.lerp(&material, weight.clamp(0.0, 1.0));
+        }
+        self.apply_materials(mesh);
+    }
+
+    /// Sets the spring parameters and vertex masses from the materials.
+    ///
+    /// Each spring takes the average stiffness and damping of the materials
+    /// of its two vertices, scaled for shear and bending springs by their
+    /// ratio to the structural springs in `spring_config`. This replaces
+    /// parameters set with `set_spring_parameters` or
+    /// `set_island_parameters`. The inverse mass of every movable vertex is
+    /// replaced with one from the density of its material and the area of
+    /// the triangles around it in `rest_positions`, or in the current
+    /// positions if there is no rest shape for the mesh. Vertices with an
+    /// inverse mass of zero stay immovable. Does nothing if no material has
+    /// been assigned.
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh` - The simulated mesh, whose inverse masses are updated.
+    pub fn apply_materials(&mut self, mesh: &mut Mesh) {
+        if self.materials.len() != mesh.vertex_count() {
+            return;
+        }
+        let structural = self.spring_config.structural;
+        for spring in &mut self.springs {
+            let a = &self.materials[spring.vertex_a_index];
+            let b = &self.materials[spring.vertex_b_index];
+            let parameters = self
+                .spring_config
+                .parameters(spring.kind)
+                .unwrap_or(structural);
+            let ratio = |value: f32, base: f32| if base > 0.0 { value / base } else { 1.0 };
+            spring.stiffness = (a.stiffness + b.stiffness)
+                * 0.5
+                * ratio(parameters.stiffness, structural.stiffness);
+            spring.damping =
+                (a.damping + b.damping) * 0.5 * ratio(parameters.damping, structural.damping);
+        }
+
+        // A third of the area of each triangle belongs to each corner. The
+        // areas come from the rest shape, so the masses do not depend on
+        // how the mesh is deformed when the materials are assigned.
+        let positions = if self.rest_positions.len() == mesh.vertex_count() {
+            &self.rest_positions
+        } else {
+            &mesh.positions
+        };
+        let mut areas = vec![0.0; mesh.vertex_count()];
+        for triangle in mesh.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| positions[triangle[i] as usize]);
+            let area = (b - a).cross(&(c - a)).norm() / 6.0;
+            for &v in triangle {
+                areas[v as usize] += area;
+            }
+        }
+        let with_area: Vec<f32> = areas.iter().copied().filter(|&a| a > 0.0).collect();
+        let mean_area = with_area.iter().sum::<f32>() / with_area.len().max(1) as f32;
+        for ((inverse_mass, material), &area) in mesh
+            .inverse_masses
+            .iter_mut()
+            .zip(&self.materials)
+            .zip(&areas)
+        {
+            if *inverse_mass == 0.0 {
+                continue;
+            }
+            let relative_area = if area > 0.0 { area / mean_area } else { 1.0 };
+            let mass = material.density * relative_area;
+            *inverse_mass = if mass > 0.0 { 1.0 / mass } else { 0.0 };
+        }
+    }
+
+    /// Deforms plastic materials permanently where they are held stretched
+    /// or compressed, by moving the rest length of each spring beyond the
+    /// yield strain of its material towards its current length.
+    ///
+    /// Call this after each `update`. It does nothing for springs whose
+    /// materials have no plasticity.
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh` - The simulated mesh.
+    pub fn apply_plasticity(&mut self, mesh: &Mesh) {
+        if self.materials.len() != mesh.vertex_count() {
+            return;
+        }
+        for spring in &mut self.springs {
+            let a = &self.materials[spring.vertex_a_index];
+            let b = &self.materials[spring.vertex_b_index];
+            let plasticity = (a.plasticity + b.plasticity) * 0.5;
+            if plasticity <= 0.0 || spring.rest_length <= 0.0 {
+                continue;
+            }
+            let yield_length = spring.rest_length * (a.yield_strain + b.yield_strain) * 0.5;
+            let length = (mesh.positions[spring.vertex_a_index]
+                - mesh.positions[spring.vertex_b_index])
+                .magnitude();
+            let excess = length - spring.rest_length;
+            let beyond_yield = excess.signum() * (excess.abs() - yield_length).max(0.0);
+            // The rest length before any plastic deformation.
+            let elastic_length = spring.rest_length / (1.0 + spring.plastic_strain);
+            spring.rest_length += beyond_yield * (plasticity * self.time_step).min(1.0);
+            spring.plastic_strain = spring.rest_length / elastic_length - 1.0;
+        }
+    }
+
+    /// Attaches a vertex to its animated target, replacing any previous
+    /// attachment of the vertex.
+    ///
+    /// The vertex is not checked against a mesh here; `update` ignores the
+    /// attachment for as long as the mesh has no such vertex.
+    ///
+    /// # Arguments
+    ///
+    /// * `vertex` - The vertex to attach.
+    /// * `attachment` - How the vertex follows its target.
+    pub fn attach(&mut self, vertex: usize, attachment: Attachment) {
+        self.detach(vertex);
+        self.attachments.push((vertex, attachment));
+    }
+
+    /// Removes the attachment of a vertex, letting it move freely.
+    pub fn detach(&mut self, vertex: usize) {
+        self.attachments.retain(|&(v, _)| v != vertex);
+    }
+
+    /// Sets the animated target of every vertex for the following updates.
+    ///
+    /// Call this every frame with the animated shape of the mesh, for
+    /// example the result of `Mesh::skin_positions`, so pinned and tethered
+    /// vertices follow the animation while the rest of the mesh is simulated.
+    ///
+    /// # Arguments
+    ///
+    /// * `targets` - The target position of each vertex.
+    pub fn set_targets(&mut self, targets: &[Vector3<f32>]) {
+        self.targets.clear();
+        self.targets.extend_from_slice(targets);
+    }
+
     /// Updates the physics simulation by one time step.
     ///
-    /// This method applies gravity and spring forces to the vertices and then
-    /// updates their positions using Verlet integration.
+    /// This method applies gravity, spring and tether forces to the vertices
+    /// and then updates their positions with the integrator. With the
+    /// XPBD solver the springs are skipped, and the constraints are solved
+    /// after the integration instead. Vertices with an inverse mass of zero do
+    /// not move, and pinned vertices are moved to their targets instead.
     ///
     /// # Arguments
     ///
@@ -99,51 +561,51 @@ impl Physics {
     ///   vertex that is being dragged by the user. This vertex will not be
     ///   affected by the physics simulation.
     pub fn update(&self, mesh: &mut Mesh, dragged_vertex_index: Option<usize>) {
-        // Apply gravity
-        for (i, vertex) in &mut mesh.vertices.iter_mut().enumerate() {
-            if Some(i) == dragged_vertex_index {
-                continue;
-            }
-            vertex.acceleration = self.gravity;
-        }
-
-        // Apply spring forces
-        for spring in &self.springs {
-            let vertex_a = mesh.vertices[spring.vertex_a_index];
-            let vertex_b = mesh.vertices[spring.vertex_b_index];
-            let delta = vertex_a.position - vertex_b.position;
-            let distance = delta.magnitude();
-            // Avoid division by zero if vertices are at the same position
-            if distance > 1e-6 {
-                let direction = delta.normalize();
-                let stretch = distance - spring.rest_length;
-                let spring_force = spring.stiffness * stretch * direction;
-                let relative_velocity = (vertex_a.position - vertex_a.old_position)
-                    - (vertex_b.position - vertex_b.old_position);
-                let damping_force = spring.damping * relative_velocity.dot(&direction) * direction;
-                let total_force = spring_force + damping_force;
-
-                if Some(spring.vertex_a_index) != dragged_vertex_index && vertex_a.mass > 0.0 {
-                    mesh.vertices[spring.vertex_a_index].acceleration -=
-                        total_force / vertex_a.mass;
-                }
-                if Some(spring.vertex_b_index) != dragged_vertex_index && vertex_b.mass > 0.0 {
-                    mesh.vertices[spring.vertex_b_index].acceleration +=
-                        total_force / vertex_b.mass;
-                }
+        // The inverse mass of each vertex, or zero for vertices that the
+        // simulation does not move.
+        let mut inverse_masses = mesh.inverse_masses.clone();
+        if let Some(i) = dragged_vertex_index {
+            inverse_masses[i] = 0.0;
+        }
+        let attachments: Vec<(usize, Attachment)> = self
+            .attachments
+            .iter()
+            .copied()
+            .filter(|&(i, _)| i < mesh.vertex_count())
+            .collect();
+        for &(i, attachment) in &attachments {
+            if attachment == Attachment::Pinned && i < self.targets.len() {
+                inverse_masses[i] = 0.0;
             }
         }
 
-        // Verlet integration
-        for (i, vertex) in &mut mesh.vertices.iter_mut().enumerate() {
-            if Some(i) == dragged_vertex_index {
-                continue;
+        let springs: &[Spring] = match self.solver {
+            Solver::Springs => &self.springs,
+            Solver::Xpbd(_) => &[],
+        };
+        let forces = Forces {
+            springs,
+            gravity: self.gravity,
+            attachments: &attachments,
+            targets: &self.targets,
+            inverse_masses: &inverse_masses,
+            time_step: self.time_step,
+        };
+        self.integrator.step(mesh, &forces);
+
+        if let Solver::Xpbd(xpbd) = &self.solver {
+            xpbd.solve(mesh, &inverse_masses, self.time_step);
+        }
+
+        // Move pinned vertices with their targets. The previous position is
+        // kept, so the springs see the animation as velocity and damp it.
+        for &(i, attachment) in &attachments {
+            if attachment == Attachment::Pinned && Some(i) != dragged_vertex_index {
+                if let Some(&target) = self.targets.get(i) {
+                    mesh.old_positions[i] = mesh.positions[i];
+                    mesh.positions[i] = target;
+                }
             }
-            let old_position = vertex.position;
-            vertex.position = vertex.position
-                + (vertex.position - vertex.old_position)
-                + vertex.acceleration * self.time_step * self.time_step;
-            vertex.old_position = old_position;
         }
     }
 }
@@ -151,8 +613,8 @@ impl Physics {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use nalgebra::Vector3;
     use mesh::Mesh;
+    use nalgebra::Vector3;
 
     #[test]
     fn test_physics_new() {
@@ -206,19 +668,21 @@ mod tests {
             rest_length: 0.5, // Spring is stretched
             stiffness: 100.0,
             damping: 0.0,
+            kind: SpringKind::Structural,
+            plastic_strain: 0.0,
         });
 
         physics.update(&mut mesh, None);
 
         // Vertex 0 should have moved right, and vertex 1 left
-        assert!(mesh.vertices[0].position.x > 0.0);
-        assert!(mesh.vertices[1].position.x < 1.0);
+        assert!(mesh.positions[0].x > 0.0);
+        assert!(mesh.positions[1].x < 1.0);
     }
 
     #[test]
     fn test_update_immovable_vertex() {
         let mut mesh = create_test_mesh(vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0], vec![]);
-        mesh.vertices[0].mass = 0.0; // Make vertex 0 immovable
+        mesh.inverse_masses[0] = 0.0; // Make vertex 0 immovable
 
         let mut physics = Physics::new();
         physics.springs.push(Spring {
@@ -227,14 +691,16 @@ mod tests {
             rest_length: 0.5,
             stiffness: 100.0,
             damping: 0.0,
+            kind: SpringKind::Structural,
+            plastic_strain: 0.0,
         });
 
         physics.update(&mut mesh, None);
 
         // Vertex 0 should not have moved
-        assert_eq!(mesh.vertices[0].position.x, 0.0);
+        assert_eq!(mesh.positions[0].x, 0.0);
         // Vertex 1 should have moved
-        assert!(mesh.vertices[1].position.x < 1.0);
+        assert!(mesh.positions[1].x < 1.0);
     }
 
     #[test]
@@ -247,13 +713,389 @@ mod tests {
             rest_length: 0.5,
             stiffness: 100.0,
             damping: 0.0,
+            kind: SpringKind::Structural,
+            plastic_strain: 0.0,
         });
 
         // This should not panic due to division by zero.
         // The `normalize()` on a zero vector results in a zero vector, so no force is applied.
         physics.update(&mut mesh, None);
 
-        assert_eq!(mesh.vertices[0].position.x, 0.0);
-        assert_eq!(mesh.vertices[1].position.x, 0.0);
+        assert_eq!(mesh.positions[0].x, 0.0);
+        assert_eq!(mesh.positions[1].x, 0.0);
+    }
+
+    #[test]
+    fn test_set_rest_shape_updates_rest_lengths() {
+        let mut mesh = create_test_mesh(
+            vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+            vec![0, 1, 2],
+        );
+        let mut physics = Physics::new();
+        physics.gravity = Vector3::zeros();
+        physics.init_springs(&mesh);
+
+        let mut rest: Vec<Vector3<f32>> = mesh.positions.clone();
+        rest[1].x = 2.0;
+        physics.set_rest_shape(&rest);
+        let spring = physics
+            .springs
+            .iter()
+            .find(|s| s.vertex_a_index + s.vertex_b_index == 1)
+            .unwrap();
+        assert_eq!(spring.rest_length, 2.0);
+
+        // The compressed springs push the vertices apart towards the new shape.
+        physics.update(&mut mesh, None);
+        assert!(mesh.positions[1].x > 1.0);
+    }
+
+    #[test]
+    fn test_set_island_parameters() {
+        // Two separate triangles.
+        let mesh = create_test_mesh(
+            vec![
+                0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, //
+                5.0, 0.0, 0.0, 6.0, 0.0, 0.0, 5.0, 1.0, 0.0,
+            ],
+            vec![0, 1, 2, 3, 4, 5],
+        );
+        let mut physics = Physics::new();
+        physics.init_springs(&mesh);
+        let components = mesh.connected_components();
+        physics.set_island_parameters(&components, 1, 50.0, 2.0);
+
+        for spring in &physics.springs {
+            if spring.vertex_a_index >= 3 {
+                assert_eq!((spring.stiffness, spring.damping), (50.0, 2.0));
+            } else {
+                assert_eq!((spring.stiffness, spring.damping), (1000.0, 10.0));
+            }
+        }
+    }
+
+    #[test]
+    fn test_pinned_vertex_follows_target() {
+        let mut mesh = create_test_mesh(vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0], vec![]);
+        let mut physics = Physics::new();
+        physics.attach(0, Attachment::Pinned);
+        physics.set_targets(&[Vector3::new(0.0, 2.0, 0.0), Vector3::new(1.0, 0.0, 0.0)]);
+
+        physics.update(&mut mesh, None);
+
+        assert_eq!(mesh.positions[0], Vector3::new(0.0, 2.0, 0.0));
+        assert_eq!(mesh.old_positions[0], Vector3::zeros());
+        // The unattached vertex falls under gravity.
+        assert!(mesh.positions[1].y < 0.0);
+
+        physics.detach(0);
+        physics.update(&mut mesh, None);
+        assert!(mesh.positions[0].y > 2.0);
+    }
+
+    #[test]
+    fn test_update_ignores_attachments_beyond_the_mesh() {
+        let mut mesh = create_test_mesh(vec![0.0, 0.0, 0.0], vec![]);
+        let mut physics = Physics::new();
+        physics.attach(1, Attachment::Pinned);
+        physics.attach(2, Attachment::Tethered { stiffness: 100.0 });
+        physics.set_targets(&[Vector3::zeros(), Vector3::x(), Vector3::y()]);
+
+        physics.update(&mut mesh, None);
+
+        assert!(mesh.positions[0].y < 0.0);
+        assert_eq!(physics.attachments.len(), 2);
+    }
+
+    #[test]
+    fn test_tethered_vertex_is_pulled_to_target() {
+        let mut mesh = create_test_mesh(vec![0.0, 0.0, 0.0], vec![]);
+        let mut physics = Physics::new();
+        physics.gravity = Vector3::zeros();
+        physics.attach(0, Attachment::Tethered { stiffness: 100.0 });
+        physics.set_targets(&[Vector3::x()]);
+
+        physics.update(&mut mesh, None);
+
+        let x = mesh.positions[0].x;
+        assert!(x > 0.0 && x < 1.0);
+    }
+
+    #[test]
+    fn test_update_gravity_moves_mesh_rigidly() {
+        let mut mesh = create_test_mesh(
+            vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+            vec![0, 1, 2],
+        );
+        let mut physics = Physics::new();
+        physics.init_springs(&mesh);
+        let centroid = mesh.centroid().unwrap();
+        let area = mesh.surface_area();
+
+        for _ in 0..10 {
+            physics.update(&mut mesh, None);
+        }
+
+        // Gravity accelerates every vertex equally, so the springs stay at
+        // rest and the triangle falls without deforming.
+        let drop = centroid - mesh.centroid().unwrap();
+        assert!(drop.y > 0.0);
+        assert!(drop.x.abs() < 1e-6 && drop.z.abs() < 1e-6);
+        assert!((mesh.surface_area() - area).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_xpbd_is_stable_at_large_time_steps() {
+        let positions: Vec<f32> = (0..16)
+            .flat_map(|v| [(v % 4) as f32, (v / 4) as f32, 0.0])
+            .collect();
+        let mut indices = Vec::new();
+        for y in 0..3 {
+            for x in 0..3 {
+                let v = y * 4 + x;
+                indices.extend([v, v + 1, v + 5, v, v + 5, v + 4]);
+            }
+        }
+        let rest = create_test_mesh(positions, indices);
+
+        // A stalled frame hands the simulation a tenth of a second.
+        let mut physics = Physics::new();
+        physics.time_step = 0.1;
+        physics.init_springs(&rest);
+        let mut mesh = rest.clone();
+        mesh.positions[0].z = 0.5;
+        for _ in 0..20 {
+            physics.update(&mut mesh, Some(15));
+        }
+        assert!(mesh
+            .positions
+            .iter()
+            .any(|p| !p.norm().is_finite() || p.norm() > 100.0));
+
+        physics.init_constraints(&rest, 0.0, 0.01);
+        assert!(matches!(physics.solver, Solver::Xpbd(_)));
+        let mut mesh = rest.clone();
+        mesh.positions[0].z = 0.5;
+        for _ in 0..20 {
+            physics.update(&mut mesh, Some(15));
+        }
+        assert!(mesh.positions.iter().all(|p| p.norm() < 10.0));
+        // The edges keep close to their rest lengths while the sheet hangs
+        // from the held corner.
+        for &[a, b] in rest.topology().edges() {
+            let length = (mesh.positions[a] - mesh.positions[b]).norm();
+            let rest_length = (rest.positions[a] - rest.positions[b]).norm();
+            assert!((length - rest_length).abs() < 0.1 * rest_length);
+        }
+    }
+
+    // A 4 x 4 grid of unit squares in the x-z plane, vertex `z * 4 + x`,
+    // each square split along the diagonal from `v` to `v + 5`.
+    fn sheet() -> Mesh {
+        let positions: Vec<f32> = (0..16)
+            .flat_map(|v| [(v % 4) as f32, 0.0, (v / 4) as f32])
+            .collect();
+        let mut indices = Vec::new();
+        for z in 0..3 {
+            for x in 0..3 {
+                let v = z * 4 + x;
+                indices.extend([v, v + 5, v + 1, v, v + 4, v + 5]);
+            }
+        }
+        create_test_mesh(positions, indices)
+    }
+
+    fn has_spring(physics: &Physics, a: usize, b: usize, kind: SpringKind) -> bool {
+        physics.springs.iter().any(|spring| {
+            (spring.vertex_a_index, spring.vertex_b_index) == (a, b) && spring.kind == kind
+        })
+    }
+
+    #[test]
+    fn test_init_shear_and_bending_springs() {
+        let mesh = sheet();
+        let mut physics = Physics::new();
+        physics.set_spring_parameters(SpringKind::Shear, 200.0, 2.0);
+        physics.set_spring_parameters(SpringKind::Bending, 50.0, 1.0);
+        physics.init_springs(&mesh);
+
+        let count = |kind| physics.springs.iter().filter(|s| s.kind == kind).count();
+        assert_eq!(count(SpringKind::Structural), 33);
+        // One shear spring across each interior edge: 33 edges, 12 of
+        // them on the boundary.
+        assert_eq!(count(SpringKind::Shear), 21);
+        assert!(has_spring(&physics, 1, 4, SpringKind::Shear));
+        // Bending springs skip over vertex 5 along the rows, columns and
+        // diagonals.
+        assert!(has_spring(&physics, 4, 6, SpringKind::Bending));
+        assert!(has_spring(&physics, 1, 9, SpringKind::Bending));
+        assert!(has_spring(&physics, 0, 10, SpringKind::Bending));
+        assert!(!has_spring(&physics, 0, 2, SpringKind::Shear));
+
+        physics.set_spring_parameters(SpringKind::Bending, 80.0, 3.0);
+        for spring in &physics.springs {
+            let expected = match spring.kind {
+                SpringKind::Structural => (1000.0, 10.0),
+                SpringKind::Shear => (200.0, 2.0),
+                SpringKind::Bending => (80.0, 3.0),
+            };
+            assert_eq!((spring.stiffness, spring.damping), expected);
+        }
+    }
+
+    #[test]
+    fn test_bending_springs_resist_folding() {
+        // Fold the last row of the sheet straight up along the line z = 2.
+        // This turns whole triangles, so no edge changes length.
+        let unfolds = |config: SpringConfig| {
+            let rest = sheet();
+            let mut physics = Physics::new();
+            physics.gravity = Vector3::zeros();
+            physics.spring_config = config;
+            physics.init_springs(&rest);
+            let mut mesh = rest.clone();
+            for v in 12..16 {
+                mesh.teleport_vertex(v, Vector3::new((v % 4) as f32, 1.0, 2.0));
+            }
+            physics.update(&mut mesh, None);
+            mesh.positions[13].z - 2.0
+        };
+
+        let structural = SpringConfig::default();
+        assert!(unfolds(structural).abs() < 1e-6);
+        let bending = SpringParameters {
+            stiffness: 500.0,
+            damping: 0.0,
+        };
+        let stiff = SpringConfig {
+            bending: Some(bending),
+            ..structural
+        };
+        assert!(unfolds(stiff) > 0.0);
+    }
+
+    #[test]
+    fn test_set_material_scales_springs_by_kind() {
+        let mut mesh = sheet();
+        let mut physics = Physics::new();
+        physics.set_spring_parameters(SpringKind::Shear, 500.0, 5.0);
+        physics.init_springs(&mesh);
+        physics.set_material(&mut mesh, &[0, 1, 4, 5], Material::CARTILAGE);
+
+        let spring = |a, b| {
+            physics
+                .springs
+                .iter()
+                .find(|s| (s.vertex_a_index, s.vertex_b_index) == (a, b))
+                .unwrap()
+        };
+        // Both ends cartilage, then one end cartilage and one skin.
+        assert_eq!(spring(0, 1).stiffness, 5000.0);
+        assert_eq!(spring(0, 5).stiffness, 5000.0);
+        assert_eq!(spring(1, 2).stiffness, 3000.0);
+        assert_eq!(spring(1, 2).damping, 17.5);
+        assert_eq!(spring(1, 4).kind, SpringKind::Shear);
+        assert_eq!(spring(1, 4).stiffness, 2500.0);
+    }
+
+    #[test]
+    fn test_paint_material_sets_masses_from_density() {
+        let mut mesh = sheet();
+        let mut physics = Physics::new();
+        physics.init_springs(&mesh);
+        let mut weights = vec![0.0; 16];
+        weights[5] = 0.5;
+        let heavy = Material {
+            density: 3.0,
+            ..Material::SKIN
+        };
+        physics.paint_material(&mut mesh, heavy, &weights);
+
+        assert_eq!(physics.materials[5].density, 2.0);
+        assert_eq!(physics.materials[6], Material::SKIN);
+        // Interior vertices have six triangles around them, corners one or
+        // two, so interior vertices are heavier at the same density.
+        assert!(mesh.inverse_masses[6] < mesh.inverse_masses[0]);
+        assert!((mesh.inverse_masses[5] * 2.0 - mesh.inverse_masses[6]).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_materials_keep_pinned_vertices_and_rest_masses() {
+        let mut mesh = sheet();
+        let mut physics = Physics::new();
+        physics.init_springs(&mesh);
+        physics.set_material(&mut mesh, &[], Material::SKIN);
+        let rest_masses = mesh.inverse_masses.clone();
+
+        // Pin a vertex and crumple the sheet, then assign materials again.
+        mesh.inverse_masses[0] = 0.0;
+        for v in 8..16 {
+            let position = mesh.positions[v];
+            mesh.teleport_vertex(v, Vector3::new(position.x * 0.5, 1.0, 0.0));
+        }
+        physics.set_material(&mut mesh, &[5], Material::SKIN);
+        assert_eq!(mesh.inverse_masses[0], 0.0);
+        assert_eq!(mesh.inverse_masses[1..], rest_masses[1..]);
+    }
+
+    #[test]
+    fn test_plasticity_keeps_stretch_beyond_yield() {
+        let mut mesh = create_test_mesh(vec![0.0, 0.0, 0.0, 2.0, 0.0, 0.0], vec![]);
+        let mut physics = Physics::new();
+        physics.springs.push(Spring {
+            vertex_a_index: 0,
+            vertex_b_index: 1,
+            rest_length: 1.0,
+            stiffness: 1000.0,
+            damping: 10.0,
+            kind: SpringKind::Structural,
+            plastic_strain: 0.0,
+        });
+        physics.apply_plasticity(&mesh);
+        assert_eq!(physics.springs[0].rest_length, 1.0);
+
+        let clay = Material {
+            plasticity: 10.0,
+            yield_strain: 0.25,
+            ..Material::SKIN
+        };
+        physics.set_material(&mut mesh, &[0, 1], clay);
+        physics.apply_plasticity(&mesh);
+        // Stretched by 1, of which 0.25 is elastic, at 10% per step.
+        assert!((physics.springs[0].rest_length - 1.075).abs() < 1e-6);
+        for _ in 0..1000 {
+            physics.apply_plasticity(&mesh);
+        }
+        assert!((physics.springs[0].rest_length - 1.6).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_set_rest_shape_keeps_plastic_deformation() {
+        let mut mesh = create_test_mesh(vec![0.0, 0.0, 0.0, 2.0, 0.0, 0.0], vec![]);
+        let mut physics = Physics::new();
+        physics.springs.push(Spring {
+            vertex_a_index: 0,
+            vertex_b_index: 1,
+            rest_length: 1.0,
+            stiffness: 1000.0,
+            damping: 10.0,
+            kind: SpringKind::Structural,
+            plastic_strain: 0.0,
+        });
+        let clay = Material {
+            plasticity: 10.0,
+            yield_strain: 0.25,
+            ..Material::SKIN
+        };
+        physics.set_material(&mut mesh, &[0, 1], clay);
+        physics.apply_plasticity(&mesh);
+        assert!((physics.springs[0].plastic_strain - 0.075).abs() < 1e-6);
+
+        // A new rest shape, such as from a morph target, keeps the dent in
+        // proportion.
+        physics.set_rest_shape(&[Vector3::zeros(), Vector3::new(2.0, 0.0, 0.0)]);
+        assert!((physics.springs[0].rest_length - 2.15).abs() < 1e-5);
+        physics.set_rest_shape(&[Vector3::zeros(), Vector3::new(1.0, 0.0, 0.0)]);
+        assert!((physics.springs[0].rest_length - 1.075).abs() < 1e-5);
     }
 }
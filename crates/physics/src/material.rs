@@ -0,0 +1,121 @@
+//! Material parameters of the simulated tissue, with presets for the
+//! regions of a face.
+
+/// How a region of the mesh responds to forces.
+///
+/// Each vertex has a material. A spring takes the average of the materials
+/// of its two vertices.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Material {
+    /// The stiffness of the structural springs. Shear and bending springs
+    /// are scaled by the ratio of their configured stiffness to the
+    /// structural stiffness.
+    pub stiffness: f32,
+    /// The damping factor of the structural springs, scaled like `stiffness`
+    /// for the other kinds.
+    pub damping: f32,
+    /// The mass of a vertex with the average area around it. Vertices with
+    /// more area around them are heavier in proportion, so the result does
+    /// not depend on the scale or tessellation of the mesh.
+    pub density: f32,
+    /// How fast, per second, a spring stretched or compressed beyond
+    /// `yield_strain` takes its current length as its rest length. 0 keeps
+    /// the material fully elastic.
+    pub plasticity: f32,
+    /// The relative change of length beyond which a spring starts to deform
+    /// permanently.
+    pub yield_strain: f32,
+}
+
+impl Material {
+    /// Soft, elastic skin, matching the default spring parameters.
+    pub const SKIN: Material = Material {
+        stiffness: 1000.0,
+        damping: 10.0,
+        density: 1.0,
+        plasticity: 0.0,
+        yield_strain: 0.5,
+    };
+
+    /// Stiff cartilage, such as in the nose and ears.
+    pub const CARTILAGE: Material = Material {
+        stiffness: 5000.0,
+        damping: 25.0,
+        density: 1.2,
+        plasticity: 0.0,
+        yield_strain: 0.2,
+    };
+
+    /// Softer, fleshier lips that settle into a new shape when held
+    /// stretched far enough.
+    pub const LIPS: Material = Material {
+        stiffness: 400.0,
+        damping: 6.0,
+        density: 0.9,
+        plasticity: 0.1,
+        yield_strain: 0.3,
+    };
+
+    /// Looks up a preset by name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - `"skin"`, `"cartilage"` or `"lips"`.
+    ///
+    /// # Returns
+    ///
+    /// The preset, or `None` if there is no preset with that name.
+    pub fn preset(name: &str) -> Option<Material> {
+        match name {
+            "skin" => Some(Material::SKIN),
+            "cartilage" => Some(Material::CARTILAGE),
+            "lips" => Some(Material::LIPS),
+            _ => None,
+        }
+    }
+
+    /// Blends two materials parameter by parameter.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The material to blend towards.
+    /// * `weight` - How much of `other` to take, from 0 to 1.
+    pub fn lerp(&self, other: &Material, weight: f32) -> Material {
+        let mix = |a: f32, b: f32| a + (b - a) * weight;
+        Material {
+            stiffness: mix(self.stiffness, other.stiffness),
+            damping: mix(self.damping, other.damping),
+            density: mix(self.density, other.density),
+            plasticity: mix(self.plasticity, other.plasticity),
+            yield_strain: mix(self.yield_strain, other.yield_strain),
+        }
+    }
+}
+
+impl Default for Material {
+    /// Returns the skin preset.
+    fn default() -> Self {
+        Material::SKIN
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets() {
+        assert_eq!(Material::preset("skin"), Some(Material::SKIN));
+        assert_eq!(Material::preset("cartilage"), Some(Material::CARTILAGE));
+        assert_eq!(Material::preset("lips"), Some(Material::LIPS));
+        assert_eq!(Material::preset("bone"), None);
+    }
+
+    #[test]
+    fn test_lerp() {
+        let blend = Material::SKIN.lerp(&Material::CARTILAGE, 0.25);
+        assert_eq!(blend.stiffness, 2000.0);
+        assert_eq!(Material::SKIN.lerp(&Material::LIPS, 0.0), Material::SKIN);
+        assert_eq!(Material::SKIN.lerp(&Material::LIPS, 1.0), Material::LIPS);
+    }
+}
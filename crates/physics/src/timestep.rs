@@ -0,0 +1,190 @@
+//! A fixed-timestep scheduler, which runs the simulation at the same rate
+//! whatever the frame rate of the display.
+
+use nalgebra::Vector3;
+
+/// Splits the variable time between rendered frames into fixed simulation
+/// steps.
+///
+/// Time left over at the end of a frame is carried over to the next one, so
+/// the simulation advances by the same steps at any frame rate. `alpha`
+/// tells how far the display time is between the last two steps, for
+/// interpolating the rendered shape.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FixedTimestep {
+    /// The length of one step, in seconds.
+    pub step: f32,
+    /// The number of physics updates each step is split into. More substeps
+    /// keep stiff springs stable.
+    pub substeps: u32,
+    /// The most steps run for one frame. Time beyond that, such as after a
+    /// stalled frame, is dropped, so the simulation slows down instead of
+    /// falling further and further behind.
+    pub max_steps: u32,
+    accumulator: f32,
+}
+
+impl Default for FixedTimestep {
+    /// Creates a scheduler that steps at 60 Hz with two substeps, and runs at
+    /// most four steps per frame.
+    fn default() -> Self {
+        Self::new(1.0 / 60.0, 2, 4)
+    }
+}
+
+impl FixedTimestep {
+    /// Creates a scheduler with no time accumulated.
+    ///
+    /// # Arguments
+    ///
+    /// * `step` - The length of one step, in seconds.
+    /// * `substeps` - The number of physics updates per step.
+    /// * `max_steps` - The most steps run for one frame.
+    pub fn new(step: f32, substeps: u32, max_steps: u32) -> Self {
+        Self {
+            step,
+            substeps,
+            max_steps,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Returns the time step of each physics update, the step divided by the
+    /// number of substeps.
+    pub fn substep(&self) -> f32 {
+        self.step / self.substeps.max(1) as f32
+    }
+
+    /// Adds the time of a frame and takes the whole steps it completes.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_time` - The time since the previous frame, in seconds.
+    ///   Negative and non-finite times are ignored.
+    ///
+    /// # Returns
+    ///
+    /// The number of steps to run, at most `max_steps`. Each step is
+    /// `substeps` physics updates of `substep()` seconds.
+    pub fn advance(&mut self, frame_time: f32) -> u32 {
+        if frame_time.is_finite() && frame_time > 0.0 {
+            self.accumulator += frame_time;
+        }
+        if self.step <= 0.0 {
+            return 0;
+        }
+        let mut steps = 0;
+        while self.accumulator >= self.step && steps < self.max_steps {
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+        if steps == self.max_steps {
+            self.accumulator = self.accumulator.min(self.step);
+        }
+        steps
+    }
+
+    /// Returns how far the display time is past the last step, as a fraction
+    /// of a step between 0 and 1.
+    pub fn alpha(&self) -> f32 {
+        if self.step <= 0.0 {
+            return 0.0;
+        }
+        (self.accumulator / self.step).clamp(0.0, 1.0)
+    }
+
+    /// Drops any accumulated time.
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+    }
+
+    /// Blends the positions before and after the last step by `alpha`.
+    ///
+    /// Rendering the blend rather than the latest step hides the uneven
+    /// number of steps per frame, at the cost of showing the simulation up
+    /// to one step late.
+    ///
+    /// # Arguments
+    ///
+    /// * `previous` - The positions before the last step.
+    /// * `current` - The positions after the last step.
+    ///
+    /// # Returns
+    ///
+    /// The position of each vertex at the display time.
+    pub fn interpolate(
+        &self,
+        previous: &[Vector3<f32>],
+        current: &[Vector3<f32>],
+    ) -> Vec<Vector3<f32>> {
+        let alpha = self.alpha();
+        previous
+            .iter()
+            .zip(current)
+            .map(|(previous, current)| previous.lerp(current, alpha))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Physics, Spring, SpringKind};
+    use mesh::Mesh;
+
+    #[test]
+    fn test_advance_accumulates_and_clamps() {
+        let mut clock = FixedTimestep::new(0.1, 2, 3);
+        assert_eq!(clock.substep(), 0.05);
+        assert_eq!(clock.advance(0.05), 0);
+        assert!((clock.alpha() - 0.5).abs() < 1e-6);
+        assert_eq!(clock.advance(0.1), 1);
+        assert!((clock.alpha() - 0.5).abs() < 1e-5);
+
+        // A stalled frame runs at most three steps and drops the rest.
+        assert_eq!(clock.advance(2.0), 3);
+        assert_eq!(clock.alpha(), 1.0);
+        assert_eq!(clock.advance(f32::NAN), 1);
+        assert_eq!(clock.advance(-1.0), 0);
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let mut clock = FixedTimestep::new(0.1, 1, 4);
+        clock.advance(0.025);
+        let blended = clock.interpolate(&[Vector3::zeros()], &[Vector3::new(4.0, 0.0, 8.0)]);
+        assert!((blended[0] - Vector3::new(1.0, 0.0, 2.0)).norm() < 1e-5);
+    }
+
+    #[test]
+    fn test_simulation_is_independent_of_frame_rate() {
+        let run = |frame_rate: f32| {
+            let mut mesh = Mesh::new(&[0.0, 0.0, 0.0, 1.5, 0.0, 0.0], &[]).unwrap();
+            mesh.inverse_masses[0] = 0.0;
+            let mut physics = Physics::new();
+            physics.springs.push(Spring {
+                vertex_a_index: 0,
+                vertex_b_index: 1,
+                rest_length: 1.0,
+                stiffness: 1000.0,
+                damping: 10.0,
+                kind: SpringKind::Structural,
+                plastic_strain: 0.0,
+            });
+            let mut clock = FixedTimestep::default();
+            physics.time_step = clock.substep();
+            let mut steps = 0;
+            while steps < 60 {
+                let frame_steps = clock.advance(1.0 / frame_rate).min(60 - steps);
+                for _ in 0..frame_steps * clock.substeps {
+                    physics.update(&mut mesh, None);
+                }
+                steps += frame_steps;
+            }
+            mesh.positions[1]
+        };
+        let reference = run(60.0);
+        assert_eq!(run(30.0), reference);
+        assert_eq!(run(144.0), reference);
+    }
+}
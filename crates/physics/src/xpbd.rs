@@ -0,0 +1,528 @@
+//! An extended position-based dynamics (XPBD) constraint solver.
+//!
+//! Instead of turning the deformation of the mesh into forces, XPBD moves the
+//! vertices directly so that they satisfy a set of constraints. Each
+//! constraint has a compliance, the inverse of its stiffness, which is scaled
+//! by the time step so that the material behaves the same whatever the step
+//! size and number of iterations. The solver never adds energy, so it stays
+//! stable for time steps at which the spring model explodes.
+
+use mesh::topology::Topology;
+use mesh::Mesh;
+use nalgebra::Vector3;
+use std::f32::consts::{PI, TAU};
+
+/// A condition the solver pulls the vertices towards.
+///
+/// A compliance of 0 makes the constraint rigid. Larger values make it
+/// softer: the compliance is the inverse of the stiffness, in the units of
+/// the constrained quantity per unit of force.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Constraint {
+    /// Keeps two vertices at a fixed distance.
+    Distance {
+        /// The two vertices.
+        vertices: [usize; 2],
+        /// The distance to keep them at.
+        rest_length: f32,
+        /// The inverse stiffness of the constraint.
+        compliance: f32,
+    },
+    /// Keeps the angle between two triangles that share an edge.
+    Bending {
+        /// The two vertices of the shared edge, followed by the opposite
+        /// corner of each triangle. The first triangle is wound
+        /// `vertices[0], vertices[1], vertices[2]`.
+        vertices: [usize; 4],
+        /// The signed dihedral angle to keep, 0 for flat, in radians.
+        rest_angle: f32,
+        /// The inverse stiffness of the constraint.
+        compliance: f32,
+    },
+    /// Keeps the volume enclosed by a set of triangles, such as a closed
+    /// island of the mesh.
+    Volume {
+        /// The triangles, as vertex triples.
+        triangles: Vec<[usize; 3]>,
+        /// The volume to keep.
+        rest_volume: f32,
+        /// The inverse stiffness of the constraint.
+        compliance: f32,
+    },
+    /// Pulls a vertex towards a fixed point.
+    Pin {
+        /// The vertex.
+        vertex: usize,
+        /// The point the vertex is pulled to.
+        target: Vector3<f32>,
+        /// The inverse stiffness of the constraint.
+        compliance: f32,
+    },
+}
+
+/// The constraints of a mesh and the settings used to solve them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Xpbd {
+    /// The constraints, solved in order.
+    pub constraints: Vec<Constraint>,
+    /// The number of passes over the constraints per time step.
+    pub iterations: usize,
+    /// The fraction of its velocity each vertex loses per second.
+    pub damping: f32,
+}
+
+impl Default for Xpbd {
+    /// Creates a solver without constraints.
+    fn default() -> Self {
+        Self {
+            constraints: Vec::new(),
+            iterations: 10,
+            damping: 0.5,
+        }
+    }
+}
+
+impl Xpbd {
+    /// Creates a solver without constraints.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a distance constraint along each edge of the mesh, keeping the
+    /// current edge lengths.
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh` - The mesh to constrain.
+    /// * `topology` - The topology of `mesh`.
+    /// * `compliance` - The inverse stiffness of the constraints.
+    pub fn add_distance_constraints(&mut self, mesh: &Mesh, topology: &Topology, compliance: f32) {
+        for &[a, b] in topology.edges() {
+            self.constraints.push(Constraint::Distance {
+                vertices: [a, b],
+                rest_length: (mesh.positions[a] - mesh.positions[b]).norm(),
+                compliance,
+            });
+        }
+    }
+
+    /// Adds a bending constraint across each edge shared by two consistently
+    /// wound triangles, keeping the current angles between them.
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh` - The mesh to constrain.
+    /// * `topology` - The topology of `mesh`.
+    /// * `compliance` - The inverse stiffness of the constraints.
+    pub fn add_bending_constraints(&mut self, mesh: &Mesh, topology: &Topology, compliance: f32) {
+        for (h, half_edge) in topology.half_edges().iter().enumerate() {
+            // Visit each pair of triangles once, from its lower half-edge.
+            let Some(twin) = half_edge.twin.filter(|&twin| twin > h) else {
+                continue;
+            };
+            let vertices = [
+                half_edge.origin,
+                half_edge.target,
+                topology.opposite_vertex(h),
+                topology.opposite_vertex(twin),
+            ];
+            self.constraints.push(Constraint::Bending {
+                vertices,
+                rest_angle: dihedral_angle(&mesh.positions, vertices),
+                compliance,
+            });
+        }
+    }
+
+    /// Adds a constraint that keeps the current volume enclosed by all
+    /// triangles of the mesh. The mesh should be closed.
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh` - The mesh to constrain.
+    /// * `compliance` - The inverse stiffness of the constraint.
+    pub fn add_volume_constraint(&mut self, mesh: &Mesh, compliance: f32) {
+        let triangles: Vec<[usize; 3]> = mesh
+            .indices
+            .chunks_exact(3)
+            .map(|t| [t[0] as usize, t[1] as usize, t[2] as usize])
+            .collect();
+        self.constraints.push(Constraint::Volume {
+            rest_volume: enclosed_volume(&mesh.positions, &triangles),
+            triangles,
+            compliance,
+        });
+    }
+
+    /// Adds a constraint that pulls a vertex towards a fixed point.
+    ///
+    /// # Arguments
+    ///
+    /// * `vertex` - The vertex to pin.
+    /// * `target` - The point to pull it to.
+    /// * `compliance` - The inverse stiffness of the constraint.
+    pub fn add_pin(&mut self, vertex: usize, target: Vector3<f32>, compliance: f32) {
+        self.constraints.push(Constraint::Pin {
+            vertex,
+            target,
+            compliance,
+        });
+    }
+
+    /// Changes the shape the constraints pull the mesh towards, like
+    /// `Physics::set_rest_shape` does for springs. Pins keep their targets.
+    ///
+    /// # Arguments
+    ///
+    /// * `positions` - The rest position of each vertex of the simulated mesh.
+    pub fn set_rest_shape(&mut self, positions: &[Vector3<f32>]) {
+        for constraint in &mut self.constraints {
+            match constraint {
+                Constraint::Distance {
+                    vertices: [a, b],
+                    rest_length,
+                    ..
+                } => *rest_length = (positions[*a] - positions[*b]).norm(),
+                Constraint::Bending {
+                    vertices,
+                    rest_angle,
+                    ..
+                } => *rest_angle = dihedral_angle(positions, *vertices),
+                Constraint::Volume {
+                    triangles,
+                    rest_volume,
+                    ..
+                } => *rest_volume = enclosed_volume(positions, triangles),
+                Constraint::Pin { .. } => {}
+            }
+        }
+    }
+
+    /// Moves the vertices of a mesh to satisfy the constraints after an
+    /// unconstrained time step, and damps their velocities.
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh` - The mesh, with its positions advanced by the time step and
+    ///   its previous positions from before it.
+    /// * `inverse_masses` - The inverse mass of each vertex. Vertices with an
+    ///   inverse mass of 0 are not moved.
+    /// * `dt` - The length of the time step, in seconds.
+    pub fn solve(&self, mesh: &mut Mesh, inverse_masses: &[f32], dt: f32) {
+        let dt2 = dt * dt;
+        let mut lambdas = vec![0.0; self.constraints.len()];
+        let mut gradients = vec![Vector3::zeros(); mesh.vertex_count()];
+        for _ in 0..self.iterations {
+            for (constraint, lambda) in self.constraints.iter().zip(&mut lambdas) {
+                let positions = &mut mesh.positions;
+                match constraint {
+                    Constraint::Distance {
+                        vertices: [a, b],
+                        rest_length,
+                        compliance,
+                    } => {
+                        let delta = positions[*a] - positions[*b];
+                        let distance = delta.norm();
+                        if distance > 1e-6 {
+                            let direction = delta / distance;
+                            project(
+                                positions,
+                                inverse_masses,
+                                &[(*a, direction), (*b, -direction)],
+                                distance - rest_length,
+                                compliance / dt2,
+                                lambda,
+                            );
+                        }
+                    }
+                    Constraint::Bending {
+                        vertices,
+                        rest_angle,
+                        compliance,
+                    } => {
+                        if let Some(gradient) = dihedral_gradient(positions, *vertices) {
+                            let angle = dihedral_angle(positions, *vertices);
+                            let terms: [(usize, Vector3<f32>); 4] =
+                                std::array::from_fn(|i| (vertices[i], gradient[i]));
+                            project(
+                                positions,
+                                inverse_masses,
+                                &terms,
+                                wrap_angle(angle - rest_angle),
+                                compliance / dt2,
+                                lambda,
+                            );
+                        }
+                    }
+                    Constraint::Volume {
+                        triangles,
+                        rest_volume,
+                        compliance,
+                    } => {
+                        for &[a, b, c] in triangles {
+                            gradients[a] = Vector3::zeros();
+                            gradients[b] = Vector3::zeros();
+                            gradients[c] = Vector3::zeros();
+                        }
+                        for &[a, b, c] in triangles {
+                            let (pa, pb, pc) = (positions[a], positions[b], positions[c]);
+                            gradients[a] += pb.cross(&pc) / 6.0;
+                            gradients[b] += pc.cross(&pa) / 6.0;
+                            gradients[c] += pa.cross(&pb) / 6.0;
+                        }
+                        let mut terms: Vec<(usize, Vector3<f32>)> = triangles
+                            .iter()
+                            .flatten()
+                            .map(|&v| (v, gradients[v]))
+                            .collect();
+                        terms.sort_unstable_by_key(|&(v, _)| v);
+                        terms.dedup_by_key(|&mut (v, _)| v);
+                        let volume = enclosed_volume(positions, triangles);
+                        project(
+                            positions,
+                            inverse_masses,
+                            &terms,
+                            volume - rest_volume,
+                            compliance / dt2,
+                            lambda,
+                        );
+                    }
+                    Constraint::Pin {
+                        vertex,
+                        target,
+                        compliance,
+                    } => {
+                        let delta = positions[*vertex] - target;
+                        let distance = delta.norm();
+                        if distance > 1e-6 {
+                            project(
+                                positions,
+                                inverse_masses,
+                                &[(*vertex, delta / distance)],
+                                distance,
+                                compliance / dt2,
+                                lambda,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        let keep = (1.0 - self.damping * dt).max(0.0);
+        for ((position, old_position), &inverse_mass) in mesh
+            .positions
+            .iter_mut()
+            .zip(&mesh.old_positions)
+            .zip(inverse_masses)
+        {
+            if inverse_mass != 0.0 {
+                *position = old_position + (*position - old_position) * keep;
+            }
+        }
+    }
+}
+
+/// Moves the vertices of one constraint along its gradient and updates its
+/// accumulated multiplier.
+///
+/// # Arguments
+///
+/// * `positions` - The vertex positions to correct.
+/// * `inverse_masses` - The inverse mass of each vertex.
+/// * `terms` - Each vertex of the constraint and the gradient of the
+///   constraint by its position.
+/// * `value` - The value of the constraint, which the solver drives to 0.
+/// * `compliance` - The compliance of the constraint divided by the square of
+///   the time step.
+/// * `lambda` - The multiplier accumulated over the iterations of this step.
+fn project(
+    positions: &mut [Vector3<f32>],
+    inverse_masses: &[f32],
+    terms: &[(usize, Vector3<f32>)],
+    value: f32,
+    compliance: f32,
+    lambda: &mut f32,
+) {
+    let weight: f32 = terms
+        .iter()
+        .map(|(v, gradient)| inverse_masses[*v] * gradient.norm_squared())
+        .sum();
+    if weight + compliance <= f32::EPSILON {
+        return;
+    }
+    let delta = (-value - compliance * *lambda) / (weight + compliance);
+    *lambda += delta;
+    for (v, gradient) in terms {
+        positions[*v] += gradient * (inverse_masses[*v] * delta);
+    }
+}
+
+/// Returns the signed angle between the normals of the triangles
+/// `a, b, c` and `b, a, d`, which is 0 when they are flat and negative when
+/// `c` and `d` fold towards the normal side.
+fn dihedral_angle(positions: &[Vector3<f32>], [a, b, c, d]: [usize; 4]) -> f32 {
+    let edge = positions[b] - positions[a];
+    let first = edge.cross(&(positions[c] - positions[a]));
+    let second = (positions[d] - positions[a]).cross(&edge);
+    let sine = first.cross(&second).dot(&edge);
+    let cosine = first.dot(&second) * edge.norm();
+    sine.atan2(cosine)
+}
+
+/// Returns the gradient of [`dihedral_angle`] by each of the four vertices,
+/// or `None` if either triangle is degenerate.
+fn dihedral_gradient(
+    positions: &[Vector3<f32>],
+    [a, b, c, d]: [usize; 4],
+) -> Option<[Vector3<f32>; 4]> {
+    let edge = positions[b] - positions[a];
+    let length_squared = edge.norm_squared();
+    let first = edge.cross(&(positions[c] - positions[a]));
+    let second = (positions[d] - positions[a]).cross(&edge);
+    let (first_squared, second_squared) = (first.norm_squared(), second.norm_squared());
+    if length_squared <= f32::EPSILON
+        || first_squared <= f32::EPSILON
+        || second_squared <= f32::EPSILON
+    {
+        return None;
+    }
+    // Moving an opposite corner along its triangle normal turns the triangle
+    // about the shared edge by the distance over its height.
+    let length = length_squared.sqrt();
+    let grad_c = -first * (length / first_squared);
+    let grad_d = -second * (length / second_squared);
+    // The shared edge vertices turn each triangle in the other direction, in
+    // proportion to how far the opposite corner projects from them.
+    let t_c = (positions[c] - positions[a]).dot(&edge) / length_squared;
+    let t_d = (positions[d] - positions[a]).dot(&edge) / length_squared;
+    let grad_a = -(grad_c * (1.0 - t_c) + grad_d * (1.0 - t_d));
+    let grad_b = -(grad_c * t_c + grad_d * t_d);
+    Some([grad_a, grad_b, grad_c, grad_d])
+}
+
+/// Wraps an angle difference into `-PI..=PI`.
+fn wrap_angle(angle: f32) -> f32 {
+    if angle > PI {
+        angle - TAU
+    } else if angle < -PI {
+        angle + TAU
+    } else {
+        angle
+    }
+}
+
+/// Returns the signed volume enclosed by a set of triangles.
+fn enclosed_volume(positions: &[Vector3<f32>], triangles: &[[usize; 3]]) -> f32 {
+    triangles
+        .iter()
+        .map(|&[a, b, c]| positions[a].dot(&positions[b].cross(&positions[c])) / 6.0)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two triangles folded along the edge from vertex 0 to vertex 1.
+    fn hinge(fold: f32) -> Vec<Vector3<f32>> {
+        vec![
+            Vector3::zeros(),
+            Vector3::x(),
+            Vector3::new(0.3, 1.0, fold),
+            Vector3::new(0.6, -1.0, 0.4 * fold),
+        ]
+    }
+
+    #[test]
+    fn test_dihedral_gradient_matches_finite_differences() {
+        let vertices = [0, 1, 2, 3];
+        let positions = hinge(0.7);
+        let gradient = dihedral_gradient(&positions, vertices).unwrap();
+        let h = 1e-3;
+        for v in 0..4 {
+            for axis in 0..3 {
+                let mut plus = positions.clone();
+                let mut minus = positions.clone();
+                plus[v][axis] += h;
+                minus[v][axis] -= h;
+                let numeric = (dihedral_angle(&plus, vertices) - dihedral_angle(&minus, vertices))
+                    / (2.0 * h);
+                assert!(
+                    (numeric - gradient[v][axis]).abs() < 1e-2,
+                    "vertex {} axis {}: {} vs {}",
+                    v,
+                    axis,
+                    numeric,
+                    gradient[v][axis]
+                );
+            }
+        }
+        assert_eq!(dihedral_angle(&hinge(0.0), vertices), 0.0);
+    }
+
+    #[test]
+    fn test_bending_restores_rest_angle() {
+        let rest = Mesh::new(
+            &hinge(0.0)
+                .iter()
+                .flat_map(|p| [p.x, p.y, p.z])
+                .collect::<Vec<_>>(),
+            &[0, 1, 2, 1, 0, 3],
+        )
+        .unwrap();
+        let topology = rest.topology();
+        let mut xpbd = Xpbd::new();
+        xpbd.add_distance_constraints(&rest, &topology, 0.0);
+        xpbd.add_bending_constraints(&rest, &topology, 0.0);
+        assert_eq!(xpbd.constraints.len(), 6);
+
+        let mut mesh = rest.clone();
+        mesh.positions = hinge(0.5);
+        mesh.old_positions = hinge(0.5);
+        xpbd.iterations = 50;
+        xpbd.damping = 0.0;
+        xpbd.solve(&mut mesh, &[0.0, 0.0, 1.0, 1.0], 0.01);
+        let angle = dihedral_angle(&mesh.positions, [0, 1, 2, 3]);
+        assert!(angle.abs() < 1e-3, "{}", angle);
+    }
+
+    #[test]
+    fn test_volume_constraint_restores_volume() {
+        // A tetrahedron wound outwards.
+        let positions = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+        let rest = Mesh::new(&positions, &[0, 2, 1, 0, 1, 3, 0, 3, 2, 1, 2, 3]).unwrap();
+        let mut xpbd = Xpbd::new();
+        xpbd.damping = 0.0;
+        xpbd.add_volume_constraint(&rest, 0.0);
+
+        let mut mesh = rest.clone();
+        for p in &mut mesh.positions {
+            *p *= 0.8;
+        }
+        mesh.old_positions.clone_from(&mesh.positions);
+        xpbd.solve(&mut mesh, &[1.0; 4], 0.01);
+        assert!((mesh.volume() - rest.volume()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_pin_pulls_vertex_to_target() {
+        let mut mesh = Mesh::new(&[1.0, 0.0, 0.0], &[]).unwrap();
+        let mut xpbd = Xpbd::new();
+        xpbd.damping = 0.0;
+        xpbd.add_pin(0, Vector3::zeros(), 0.0);
+        xpbd.solve(&mut mesh, &[1.0], 0.01);
+        assert!(mesh.positions[0].norm() < 1e-6);
+
+        // A compliant pin only pulls part of the way.
+        let mut mesh = Mesh::new(&[1.0, 0.0, 0.0], &[]).unwrap();
+        xpbd.constraints = vec![Constraint::Pin {
+            vertex: 0,
+            target: Vector3::zeros(),
+            compliance: 1e-4,
+        }];
+        xpbd.solve(&mut mesh, &[1.0], 0.01);
+        let x = mesh.positions[0].x;
+        assert!(x > 0.1 && x < 0.9, "{}", x);
+    }
+}
@@ -0,0 +1,110 @@
+use mesh::Mesh;
+use nalgebra::Vector3;
+use physics::{Physics, Spring, SpringKind};
+
+fn spring(vertex_a_index: usize, vertex_b_index: usize, rest_length: f32) -> Spring {
+    Spring {
+        vertex_a_index,
+        vertex_b_index,
+        rest_length,
+        stiffness: 100.0,
+        damping: 0.0,
+        kind: SpringKind::Structural,
+        plastic_strain: 0.0,
+    }
+}
+
+#[test]
+fn test_spring_force() {
+    let mut mesh = Mesh::new(&[0.0, 0.0, 0.0, 2.0, 0.0, 0.0], &[]).unwrap();
+    let mut physics = Physics::new();
+    physics.springs.push(spring(0, 1, 1.0));
+    // Without gravity the spring is the only force.
+    physics.gravity = Vector3::zeros();
+
+    physics.update(&mut mesh, None);
+
+    // The spring is stretched by 1.0 unit, so it pulls the vertices together
+    // with a force of 100.0 * 1.0 = 100.0, and both have an inverse mass of 1.
+    assert_eq!(mesh.accelerations[0], Vector3::new(100.0, 0.0, 0.0));
+    assert_eq!(mesh.accelerations[1], Vector3::new(-100.0, 0.0, 0.0));
+}
+
+#[test]
+fn test_verlet_integration() {
+    let mut mesh = Mesh::new(&[0.0, 0.0, 0.0], &[]).unwrap();
+    let mut physics = Physics::new();
+    physics.time_step = 0.1;
+    physics.gravity = Vector3::new(0.0, -10.0, 0.0);
+
+    physics.update(&mut mesh, None);
+
+    // After 1st step:
+    // pos = 0 + (0 - 0) + (0, -10, 0) * 0.1 * 0.1 = (0, -0.1, 0)
+    assert!((mesh.positions[0].y + 0.1).abs() < 1e-6);
+
+    physics.update(&mut mesh, None);
+
+    // After 2nd step:
+    // pos = -0.1 + (-0.1 - 0) + (0, -10, 0) * 0.1 * 0.1 = -0.1 - 0.1 - 0.1 = -0.3
+    assert!((mesh.positions[0].y + 0.3).abs() < 1e-6);
+}
+
+#[test]
+fn test_oscillation() {
+    let mut mesh = Mesh::new(&[0.0, 0.0, 0.0, 1.1, 0.0, 0.0], &[]).unwrap();
+    // Pin the first vertex.
+    mesh.inverse_masses[0] = 0.0;
+    let mut physics = Physics::new();
+    physics.springs.push(spring(0, 1, 1.0));
+    physics.gravity = Vector3::zeros();
+    physics.time_step = 0.01;
+
+    // Initial position of vertex 1 is 1.1, rest length is 1.0.
+    // It should move towards vertex 0.
+    physics.update(&mut mesh, None);
+    assert!(mesh.positions[1].x < 1.1);
+    assert_eq!(mesh.positions[0], Vector3::zeros());
+
+    // Let it run for a while, it should oscillate around the rest length.
+    for _ in 0..100 {
+        physics.update(&mut mesh, None);
+    }
+    // After 100 steps, it should have oscillated back and be on the other side.
+    assert!(mesh.positions[1].x < 1.0);
+    assert_eq!(mesh.positions[0], Vector3::zeros());
+}
+
+#[test]
+fn test_shear_spring_resists_folding() {
+    // A unit square split along the diagonal from vertex 0 to vertex 2.
+    let square = Mesh::new(
+        &[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0],
+        &[0, 1, 2, 0, 2, 3],
+    )
+    .unwrap();
+    // Fold vertex 3 up by 90 degrees about the diagonal, which keeps every
+    // edge at its length but brings vertex 3 closer to vertex 1.
+    let fold = |physics: &Physics| {
+        let mut mesh = square.clone();
+        mesh.teleport_vertex(3, Vector3::new(0.5, 0.5, 0.5f32.sqrt()));
+        physics.update(&mut mesh, None);
+        mesh.accelerations[3]
+    };
+
+    let mut physics = Physics::new();
+    physics.gravity = Vector3::zeros();
+    physics.init_springs(&square);
+    assert!(fold(&physics).norm() < 1e-3);
+
+    physics.set_spring_parameters(SpringKind::Shear, 100.0, 0.0);
+    physics.init_springs(&square);
+    let shear: Vec<&Spring> = physics
+        .springs
+        .iter()
+        .filter(|s| s.kind == SpringKind::Shear)
+        .collect();
+    assert_eq!(shear.len(), 1);
+    // The shear spring pushes vertex 3 away from vertex 1, unfolding it.
+    assert!(fold(&physics).z > 1.0);
+}
@@ -1,16 +1,29 @@
 use wasm_bindgen::prelude::*;
 
 // Import crates
-use mesh::Mesh;
-use physics::Physics;
-use image_processing;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(not(target_arch = "wasm32"), feature = "detect-faces"))]
 use face_detection;
-
+use mesh::attributes::NormalWeighting;
+use mesh::bvh::Bvh;
+use mesh::cleanup::WeldedMesh;
+use mesh::components::Components;
+use mesh::diff::MeshDiff;
+use mesh::selection::{Falloff, Selection};
+use mesh::smoothing::LaplacianWeights;
+use mesh::snapshot::{PositionEncoding, SnapshotError, SnapshotOptions};
+use mesh::symmetry::{Plane, Symmetry};
+use mesh::{Mesh, MeshError};
+use nalgebra::Vector3;
+use physics::integrator::{ImplicitEuler, Rk4, SemiImplicitEuler, Verlet};
+use physics::material::Material;
+use physics::timestep::FixedTimestep;
+use physics::{Physics, Solver, SpringKind};
+use serde::Serialize;
 
 /// Detects faces in an image. This function is a wrapper around the
 /// `face_detection` crate's `detect_faces` function, and is only available
-/// when not compiling for the `wasm32` target.
+/// with the `detect-faces` feature when not compiling for the `wasm32`
+/// target.
 ///
 /// # Arguments
 ///
@@ -21,20 +34,388 @@ use face_detection;
 /// A `Result` containing a `JsValue` with the bounding boxes of the detected
 /// faces, or a `JsValue` with an error message.
 #[wasm_bindgen]
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(not(target_arch = "wasm32"), feature = "detect-faces"))]
 pub fn detect_faces(image_bytes: &[u8]) -> Result<JsValue, JsValue> {
     let bboxes =
         face_detection::detect_faces(image_bytes).map_err(|e| JsValue::from_str(&e.to_string()))?;
     serde_wasm_bindgen::to_value(&bboxes).map_err(|e| JsValue::from_str(&e.to_string()))
 }
 
+/// The shape of the error object thrown when mesh data is rejected.
+///
+/// On the JavaScript side this is a plain object with a human-readable
+/// `message`, a `kind` naming the `MeshError` variant, and that variant's
+/// fields, e.g. `{ message, kind: "IndexOutOfBounds", triangle, index, vertex_count }`.
+#[derive(Serialize)]
+struct JsMeshError<'a> {
+    message: String,
+    #[serde(flatten)]
+    error: &'a MeshError,
+}
+
+/// Converts a `MeshError` into a structured JavaScript error object.
+fn mesh_error_to_js(error: &MeshError) -> JsValue {
+    let js_error = JsMeshError {
+        message: error.to_string(),
+        error,
+    };
+    to_js_object(&js_error)
+}
+
+/// Serializes a value into a plain JavaScript object.
+fn to_js_object<T: Serialize>(value: &T) -> JsValue {
+    let serializer = serde_wasm_bindgen::Serializer::new().serialize_maps_as_objects(true);
+    value
+        .serialize(&serializer)
+        .unwrap_or_else(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// The result of `FaceController::pick`, as seen from JavaScript.
+#[derive(Serialize)]
+struct PickResult {
+    /// The render vertex of the hit triangle closest to the hit point.
+    vertex: u32,
+    /// The hit point.
+    point: [f32; 3],
+    /// The distance along the ray, in multiples of the ray direction.
+    distance: f32,
+}
+
+/// A connected piece of the mesh, as returned by `FaceController::islands`.
+#[derive(Serialize)]
+struct IslandInfo {
+    /// The number of physics vertices in the island.
+    vertex_count: usize,
+    /// The number of triangles in the island.
+    triangle_count: usize,
+    /// The corner of the bounding box with the smallest coordinates.
+    min: [f32; 3],
+    /// The corner of the bounding box with the largest coordinates.
+    max: [f32; 3],
+    /// The total area of the triangles of the island.
+    surface_area: f32,
+}
+
+/// Reads a 3D vector from a JavaScript array.
+fn vector_from_js(values: &[f32], name: &str) -> Result<Vector3<f32>, JsValue> {
+    match values {
+        [x, y, z] => Ok(Vector3::new(*x, *y, *z)),
+        _ => Err(JsValue::from_str(&format!(
+            "{} must have 3 components, got {}",
+            name,
+            values.len()
+        ))),
+    }
+}
+
+/// The result of `FaceController::symmetry_plane`, as seen from JavaScript.
+#[derive(Serialize)]
+struct SymmetryInfo {
+    /// The unit normal of the plane.
+    normal: [f32; 3],
+    /// The distance of the plane from the origin along `normal`.
+    offset: f32,
+    /// The RMS distance between the mirrored mesh and the mesh.
+    error: f32,
+}
+
+/// The result of `FaceController::diff_report`, as seen from JavaScript.
+#[derive(Serialize)]
+struct DiffInfo {
+    /// The largest distance a vertex moved from its rest position.
+    max_displacement: f32,
+    /// The RMS distance the vertices moved from their rest positions.
+    rms_displacement: f32,
+    /// The Hausdorff distance between the rest and the current surface.
+    hausdorff_distance: f32,
+    /// The strain of the most stretched triangle.
+    max_stretch: f32,
+    /// The strain of the most compressed triangle, as a negative number.
+    max_compression: f32,
+    /// The largest relative area change of any triangle, in either direction.
+    max_area_change: f32,
+}
+
+/// A region of the physics mesh that moves with a drag.
+struct Region {
+    /// The vertices of the region and how strongly each follows the cursor.
+    selection: Selection,
+    /// The position of each selected vertex when the drag started.
+    start: Vec<Vector3<f32>>,
+}
+
+impl Region {
+    /// Selects the region around a vertex of the mesh.
+    fn new(mesh: &Mesh, center: usize, radius: f32, falloff: Falloff) -> Region {
+        let selection = mesh.select_geodesic(center, radius, falloff);
+        Region {
+            start: selection
+                .vertices
+                .iter()
+                .map(|&v| mesh.positions[v])
+                .collect(),
+            selection,
+        }
+    }
+
+    /// Blends each vertex of the region towards its start position moved by
+    /// `offset`, by its selection weight.
+    fn displace(&self, mesh: &mut Mesh, offset: &Vector3<f32>) {
+        for ((vertex, weight), start) in self.selection.iter().zip(&self.start) {
+            let position = &mut mesh.positions[vertex];
+            *position += (start + offset - *position) * weight;
+        }
+    }
+}
+
+/// A drag in progress, moving a region of the physics mesh with the cursor.
+struct Drag {
+    /// The physics vertex under the cursor.
+    vertex: usize,
+    /// The position of `vertex` when the drag started.
+    anchor: Vector3<f32>,
+    /// The current displacement of the cursor from `anchor`.
+    offset: Vector3<f32>,
+    /// The dragged region.
+    region: Region,
+    /// The region around the mirror image of `vertex`, in symmetric mode,
+    /// with the plane it is mirrored across.
+    mirror: Option<(Plane, Region)>,
+}
+
+/// The mirror symmetry of the physics mesh in its rest shape.
+struct Mirror {
+    symmetry: Symmetry,
+    /// For each physics vertex, its mirror image, if it has one.
+    map: Vec<Option<usize>>,
+}
+
+/// The distance within which render vertices are welded into one physics vertex.
+const WELD_TOLERANCE: f32 = 1e-5;
+
+/// How far the mirror image of a vertex may be from its mirror vertex, as a
+/// fraction of the diagonal of the bounding box of the mesh.
+const MIRROR_TOLERANCE: f32 = 0.01;
+
+/// The stiffness and damping of the shear and bending springs, which keep
+/// the face from folding along its edges.
+const SHEAR_STIFFNESS: f32 = 500.0;
+const SHEAR_DAMPING: f32 = 5.0;
+const BENDING_STIFFNESS: f32 = 200.0;
+const BENDING_DAMPING: f32 = 2.0;
+
+/// The inverse stiffness of the edges and of the folds between triangles
+/// when the XPBD solver is selected.
+const DISTANCE_COMPLIANCE: f32 = 1e-4;
+const BENDING_COMPLIANCE: f32 = 1e-2;
+
+/// The shrinking and inflating factors used by the smoothing brush.
+const SMOOTHING_LAMBDA: f32 = 0.5;
+const SMOOTHING_MU: f32 = -0.53;
+
 /// A controller for the 3D face mesh, handling user interactions and physics.
+///
+/// The physics runs on a welded copy of the mesh, so vertices that were split
+/// along UV seams move together. Vertex ids and buffers seen from JavaScript
+/// always refer to the original (render) vertices.
 #[wasm_bindgen]
 pub struct FaceController {
-    mesh: Mesh,
+    welded: WeldedMesh,
+    /// For each physics vertex, the first render vertex welded into it.
+    render_vertices: Vec<u32>,
+    bvh: Bvh,
+    /// The connected pieces of the physics mesh, such as the skin and eyes.
+    components: Components,
     physics: Physics,
+    /// Splits the time passed to `tick` into fixed physics steps.
+    clock: FixedTimestep,
+    /// Whether the vertex buffer blends the last two steps by the time left
+    /// over in the clock.
+    interpolate: bool,
+    /// The physics positions before the last step, kept while interpolating.
+    previous_positions: Vec<Vector3<f32>>,
+    /// Whether welding merged no vertices, in which case the render buffers
+    /// are views into the physics mesh and `vertex_positions` and
+    /// `vertex_normals` stay empty, unless the positions are interpolated.
+    identity_weld: bool,
     vertex_positions: Vec<f32>,
-    dragged_vertex_index: Option<u32>,
+    vertex_normals: Vec<f32>,
+    drag: Option<Drag>,
+    drag_radius: f32,
+    drag_falloff: Falloff,
+    symmetric_drag: bool,
+    /// The symmetry of the mesh, detected the first time it is needed.
+    mirror: Option<Mirror>,
+    /// The physics mesh as loaded, before any morph target is applied.
+    rest_positions: Vec<Vector3<f32>>,
+    morph_weights: Vec<f32>,
+}
+
+impl FaceController {
+    /// Sets up the physics simulation for a mesh and wraps both in a controller.
+    fn from_mesh(mesh: Mesh) -> FaceController {
+        let welded = mesh.weld_vertices(WELD_TOLERANCE);
+        let mut physics = Physics::new();
+        physics.set_spring_parameters(SpringKind::Shear, SHEAR_STIFFNESS, SHEAR_DAMPING);
+        physics.set_spring_parameters(SpringKind::Bending, BENDING_STIFFNESS, BENDING_DAMPING);
+        physics.init_springs(&welded.mesh);
+        let mut render_vertices = vec![0; welded.mesh.vertex_count()];
+        for (render, &physics_vertex) in welded.remap.iter().enumerate().rev() {
+            render_vertices[physics_vertex as usize] = render as u32;
+        }
+
+        let mut controller = FaceController {
+            bvh: welded.mesh.bvh(),
+            components: welded.mesh.connected_components(),
+            identity_weld: welded.is_identity(),
+            rest_positions: welded.mesh.positions.clone(),
+            morph_weights: vec![0.0; welded.mesh.morph_targets.len()],
+            welded,
+            render_vertices,
+            physics,
+            clock: FixedTimestep::default(),
+            interpolate: false,
+            previous_positions: Vec::new(),
+            vertex_positions: Vec::new(),
+            vertex_normals: Vec::new(),
+            drag: None,
+            drag_radius: 0.0,
+            drag_falloff: Falloff::default(),
+            symmetric_drag: false,
+            mirror: None,
+        };
+        controller.update_buffers();
+        controller
+    }
+
+    /// Pulls the dragged region towards its displaced position. Each vertex
+    /// moves by its selection weight of the way, so the vertex under the
+    /// cursor follows it exactly and the rest of the region is blended with
+    /// the simulation. In symmetric mode the mirrored region follows the
+    /// mirrored offset.
+    fn apply_drag(&mut self) {
+        let Some(drag) = &self.drag else {
+            return;
+        };
+        let mesh = &mut self.welded.mesh;
+        drag.region.displace(mesh, &drag.offset);
+        if let Some((plane, region)) = &drag.mirror {
+            region.displace(mesh, &plane.reflect_vector(&drag.offset));
+        }
+    }
+
+    /// Detects the symmetry of the rest shape of the physics mesh, unless it
+    /// is already known.
+    fn mirror(&mut self) -> Result<&Mirror, JsValue> {
+        if self.mirror.is_none() {
+            let rest = self.rest_mesh();
+            let symmetry = rest
+                .detect_symmetry()
+                .ok_or_else(|| JsValue::from_str("the mesh has no vertices"))?;
+            let diagonal = rest
+                .bounding_box()
+                .map_or(0.0, |bounds| bounds.size().norm());
+            let map = rest.mirror_map(&symmetry.plane, diagonal * MIRROR_TOLERANCE);
+            self.mirror = Some(Mirror { symmetry, map });
+        }
+        Ok(self.mirror.as_ref().expect("the mirror was just detected"))
+    }
+
+    /// Returns a copy of the physics mesh in its rest shape.
+    fn rest_mesh(&self) -> Mesh {
+        let mut rest = self.welded.mesh.clone();
+        rest.positions.clone_from(&self.rest_positions);
+        rest
+    }
+
+    /// Compares the rest shape of the physics mesh with its current shape.
+    fn rest_diff(&self) -> MeshDiff {
+        self.rest_mesh()
+            .diff(&self.welded.mesh)
+            .expect("the rest mesh has the same topology")
+    }
+
+    /// Recomputes the normals of the physics mesh, refits the picking
+    /// hierarchy, and copies positions and normals out to the render vertex
+    /// buffers unless they are views into the physics mesh. With
+    /// interpolation on, the vertex buffer shows the blend of the last two
+    /// steps.
+    fn update_buffers(&mut self) {
+        self.welded.mesh.compute_normals(NormalWeighting::Area);
+        self.bvh.refit(&self.welded.mesh);
+        if self.interpolate {
+            let positions = self
+                .clock
+                .interpolate(&self.previous_positions, &self.welded.mesh.positions);
+            self.vertex_positions = self
+                .welded
+                .gather(&positions)
+                .iter()
+                .flat_map(|p| p.iter().cloned())
+                .collect();
+        } else if !self.identity_weld {
+            self.vertex_positions = self.welded.render_positions_flat();
+        }
+        if self.identity_weld {
+            return;
+        }
+        self.vertex_normals = self
+            .welded
+            .gather(self.welded.mesh.normals.as_deref().unwrap_or_default())
+            .iter()
+            .flat_map(|n| n.iter().cloned())
+            .collect();
+    }
+
+    /// Updates the buffers after the positions were changed outside the
+    /// simulation, showing the new shape without interpolation.
+    fn settle(&mut self) {
+        if self.interpolate {
+            self.previous_positions
+                .clone_from(&self.welded.mesh.positions);
+        }
+        self.update_buffers();
+    }
+
+    /// Looks up a material preset by name.
+    fn material_preset(name: &str) -> Result<Material, JsValue> {
+        Material::preset(name)
+            .ok_or_else(|| JsValue::from_str(&format!("unknown material \"{}\"", name)))
+    }
+
+    /// Assigns a material to the physics vertices behind some render
+    /// vertices, or fails without assigning anything if one of them does not
+    /// exist.
+    fn assign_material(&mut self, vertex_ids: &[u32], material: Material) -> Result<(), JsValue> {
+        let vertices = vertex_ids
+            .iter()
+            .map(|&id| self.require_physics_vertex(id))
+            .collect::<Result<Vec<usize>, JsValue>>()?;
+        self.physics
+            .set_material(&mut self.welded.mesh, &vertices, material);
+        Ok(())
+    }
+
+    /// Returns the physics vertex that a render vertex was welded into, or
+    /// `None` if there is no such render vertex.
+    fn physics_vertex(&self, vertex_id: u32) -> Option<usize> {
+        self.welded
+            .remap
+            .get(vertex_id as usize)
+            .map(|&vertex| vertex as usize)
+    }
+
+    /// Returns the physics vertex that a render vertex was welded into, or
+    /// an error if there is no such render vertex.
+    fn require_physics_vertex(&self, vertex_id: u32) -> Result<usize, JsValue> {
+        self.physics_vertex(vertex_id).ok_or_else(|| {
+            JsValue::from_str(&format!(
+                "vertex {} does not exist, the mesh has {}",
+                vertex_id,
+                self.welded.remap.len()
+            ))
+        })
+    }
 }
 
 #[wasm_bindgen]
@@ -48,52 +429,697 @@ impl FaceController {
     ///
     /// # Returns
     ///
-    /// A new `FaceController` instance.
+    /// A `Result` containing the new `FaceController`, or a structured error
+    /// object (see `JsMeshError`) if the mesh data is invalid.
     #[wasm_bindgen(constructor)]
-    pub fn new(positions: &[f32], indices: &[u32]) -> FaceController {
-        let mesh = Mesh::new(positions, indices).expect("Failed to create mesh");
-        let mut physics = Physics::new();
-        physics.init_springs(&mesh);
-        let vertex_positions = mesh.get_vertex_positions_flat();
+    pub fn new(positions: &[f32], indices: &[u32]) -> Result<FaceController, JsValue> {
+        let mesh = Mesh::new(positions, indices).map_err(|e| mesh_error_to_js(&e))?;
+        Ok(FaceController::from_mesh(mesh))
+    }
 
-        FaceController {
-            mesh,
-            physics,
-            vertex_positions,
-            dragged_vertex_index: None,
+    /// Creates a new `FaceController` from the bytes of a `.gltf` or `.glb` file.
+    ///
+    /// All triangle primitives in the file are merged into a single mesh.
+    /// Buffers must be embedded in the file.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The contents of the glTF file.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the new `FaceController`, or a `JsValue` with an
+    /// error message if the file could not be loaded. If the file loaded but
+    /// its mesh data is invalid, the error is a structured object as thrown
+    /// by the constructor.
+    #[cfg(feature = "gltf")]
+    pub fn from_gltf(bytes: &[u8]) -> Result<FaceController, JsValue> {
+        let mesh = Mesh::from_gltf_slice(bytes).map_err(|e| match e {
+            mesh::gltf::GltfError::Mesh(err) => mesh_error_to_js(&err),
+            e => JsValue::from_str(&e.to_string()),
+        })?;
+        Ok(FaceController::from_mesh(mesh))
+    }
+
+    /// Checks the current mesh for problems such as degenerate or duplicate
+    /// triangles, non-manifold edges and isolated vertices.
+    ///
+    /// The check runs on the welded physics mesh, so vertex indices in the
+    /// report refer to welded vertices rather than render vertices.
+    ///
+    /// # Returns
+    ///
+    /// An object with one array per kind of problem (`out_of_range_triangles`,
+    /// `non_finite_vertices`, `degenerate_triangles`, `duplicate_triangles`,
+    /// `non_manifold_edges` and `isolated_vertices`), all empty for a clean mesh.
+    pub fn validate_mesh(&self) -> JsValue {
+        to_js_object(&self.welded.mesh.validate())
+    }
+
+    /// Saves the simulated mesh and its simulation state as a binary snapshot.
+    ///
+    /// # Arguments
+    ///
+    /// * `position_bits` - The number of bits per quantized coordinate, from
+    ///   1 to 16, or 0 to store exact 32-bit floats.
+    ///
+    /// # Returns
+    ///
+    /// The snapshot bytes, which `restore` accepts on a controller for the
+    /// same mesh.
+    pub fn snapshot(&self, position_bits: u8) -> Vec<u8> {
+        let positions = match position_bits {
+            0 => PositionEncoding::Float32,
+            bits => PositionEncoding::Quantized { bits },
+        };
+        self.welded.mesh.to_snapshot(&SnapshotOptions {
+            positions,
+            simulation_state: true,
+        })
+    }
+
+    /// Restores the simulation from a snapshot taken with `snapshot`.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The snapshot bytes.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` that fails if the snapshot cannot be decoded or was taken
+    /// from a different mesh.
+    pub fn restore(&mut self, bytes: &[u8]) -> Result<(), JsValue> {
+        let snapshot = Mesh::from_snapshot(bytes).map_err(|e| match e {
+            SnapshotError::Mesh(err) => mesh_error_to_js(&err),
+            e => JsValue::from_str(&e.to_string()),
+        })?;
+        let mesh = &mut self.welded.mesh;
+        if snapshot.vertex_count() != mesh.vertex_count() || snapshot.indices != mesh.indices {
+            return Err(JsValue::from_str(
+                "snapshot was taken from a different mesh",
+            ));
         }
+        mesh.positions = snapshot.positions;
+        mesh.old_positions = snapshot.old_positions;
+        mesh.inverse_masses = snapshot.inverse_masses;
+        self.settle();
+        Ok(())
     }
 
-    /// Advances the physics simulation by a given time step.
+    /// Advances the physics simulation by the time since the last frame.
+    ///
+    /// The time is split into fixed steps (see `set_timestep`), and time
+    /// left over is carried over to the next frame, so the simulation runs
+    /// the same at any frame rate. The vertex normals are recomputed from
+    /// the deformed positions, so the normal buffer is always in sync with
+    /// the vertex buffer.
     ///
     /// # Arguments
     ///
-    /// * `dt` - The time step to advance the simulation by.
+    /// * `dt` - The time since the last frame, in seconds.
     pub fn tick(&mut self, dt: f32) {
-        self.physics.time_step = dt;
-        self.physics.update(
-            &mut self.mesh,
-            self.dragged_vertex_index.map(|i| i as usize),
+        let steps = self.clock.advance(dt);
+        self.physics.time_step = self.clock.substep();
+        let dragged = self.drag.as_ref().map(|drag| drag.vertex);
+        for step in 0..steps {
+            if self.interpolate && step + 1 == steps {
+                self.previous_positions
+                    .clone_from(&self.welded.mesh.positions);
+            }
+            for _ in 0..self.clock.substeps {
+                self.physics.update(&mut self.welded.mesh, dragged);
+                self.physics.apply_plasticity(&self.welded.mesh);
+                self.apply_drag();
+            }
+        }
+        self.update_buffers();
+    }
+
+    /// Sets how the time passed to `tick` is split into physics steps.
+    ///
+    /// # Arguments
+    ///
+    /// * `step` - The length of one step, in seconds.
+    /// * `substeps` - The number of physics updates per step. More substeps
+    ///   keep stiff springs stable.
+    /// * `max_steps` - The most steps run per frame. Time beyond that, such
+    ///   as after a stalled frame, is dropped.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` that fails if the step is not positive or either count is 0.
+    pub fn set_timestep(
+        &mut self,
+        step: f32,
+        substeps: u32,
+        max_steps: u32,
+    ) -> Result<(), JsValue> {
+        if !(step.is_finite() && step > 0.0) || substeps == 0 || max_steps == 0 {
+            return Err(JsValue::from_str(&format!(
+                "invalid timestep: step {}, {} substeps, {} steps per frame",
+                step, substeps, max_steps
+            )));
+        }
+        self.clock = FixedTimestep::new(step, substeps, max_steps);
+        Ok(())
+    }
+
+    /// Turns render interpolation on or off. With interpolation on, the
+    /// vertex buffer blends the last two physics steps by the time left over
+    /// since the last step, which hides the uneven number of steps per frame
+    /// at the cost of showing the simulation up to one step late. The vertex
+    /// buffer is then always a copy.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to interpolate.
+    pub fn set_interpolation(&mut self, enabled: bool) {
+        self.interpolate = enabled;
+        if !enabled {
+            self.previous_positions = Vec::new();
+            self.vertex_positions = Vec::new();
+        }
+        self.settle();
+    }
+
+    /// Finds the vertex under a ray, using the current (deformed) positions.
+    ///
+    /// # Arguments
+    ///
+    /// * `ray_origin` - The `[x, y, z]` start of the ray.
+    /// * `ray_dir` - The `[x, y, z]` direction of the ray.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `undefined` if the ray misses the mesh, or an
+    /// object `{ vertex, point, distance }` with the corner of the hit
+    /// triangle closest to the hit point, the hit point as `[x, y, z]`, and
+    /// the distance along the ray. Fails if either array does not have three
+    /// components.
+    pub fn pick(&self, ray_origin: &[f32], ray_dir: &[f32]) -> Result<JsValue, JsValue> {
+        let origin = vector_from_js(ray_origin, "ray_origin")?;
+        let direction = vector_from_js(ray_dir, "ray_dir")?;
+        let mesh = &self.welded.mesh;
+        let Some(hit) = self.bvh.raycast(mesh, &origin, &direction) else {
+            return Ok(JsValue::UNDEFINED);
+        };
+        let corners = &mesh.indices[hit.triangle * 3..hit.triangle * 3 + 3];
+        let closest = corners
+            .iter()
+            .copied()
+            .min_by(|&a, &b| {
+                let distance = |v: u32| (mesh.positions[v as usize] - hit.point).norm();
+                distance(a).total_cmp(&distance(b))
+            })
+            .expect("triangles have three corners");
+        Ok(to_js_object(&PickResult {
+            vertex: self.render_vertices[closest as usize],
+            point: hit.point.into(),
+            distance: hit.distance,
+        }))
+    }
+
+    /// Relaxes the whole mesh with Taubin smoothing, which removes crumpling
+    /// without shrinking the face.
+    ///
+    /// # Arguments
+    ///
+    /// * `iterations` - How many smoothing passes to apply.
+    pub fn smooth(&mut self, iterations: u32) {
+        self.welded.mesh.smooth_taubin(
+            LaplacianWeights::Uniform,
+            SMOOTHING_LAMBDA,
+            SMOOTHING_MU,
+            iterations as usize,
+            None,
+        );
+        self.settle();
+    }
+
+    /// Relaxes a region of the mesh with Taubin smoothing, for use as a
+    /// smoothing brush. Vertices outside the region do not move.
+    ///
+    /// # Arguments
+    ///
+    /// * `vertex_ids` - The vertices to smooth. IDs that are out of range are
+    ///   ignored.
+    /// * `iterations` - How many smoothing passes to apply.
+    pub fn smooth_region(&mut self, vertex_ids: &[u32], iterations: u32) {
+        let selection: Vec<usize> = vertex_ids
+            .iter()
+            .filter_map(|&id| self.physics_vertex(id))
+            .collect();
+        self.welded.mesh.smooth_taubin(
+            LaplacianWeights::Uniform,
+            SMOOTHING_LAMBDA,
+            SMOOTHING_MU,
+            iterations as usize,
+            Some(&selection),
         );
-        self.vertex_positions = self.mesh.get_vertex_positions_flat();
+        self.settle();
+    }
+
+    /// Describes the connected pieces of the mesh, such as the face shell,
+    /// the eyes and the teeth.
+    ///
+    /// # Returns
+    ///
+    /// An array with one `{ vertex_count, triangle_count, min, max,
+    /// surface_area }` object per island, where `min` and `max` are the
+    /// `[x, y, z]` corners of its bounding box. Islands are numbered by their
+    /// position in the array.
+    pub fn islands(&self) -> JsValue {
+        let islands: Vec<IslandInfo> = self
+            .welded
+            .mesh
+            .islands(&self.components)
+            .into_iter()
+            .map(|island| IslandInfo {
+                vertex_count: island.vertex_count,
+                triangle_count: island.triangle_count,
+                min: island.bounds.min.into(),
+                max: island.bounds.max.into(),
+                surface_area: island.surface_area,
+            })
+            .collect();
+        to_js_object(&islands)
+    }
+
+    /// Returns the island that a vertex belongs to.
+    ///
+    /// # Arguments
+    ///
+    /// * `vertex_id` - The ID of the vertex.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the island, numbered as by `islands`, that fails
+    /// if the vertex does not exist.
+    pub fn island_of(&self, vertex_id: u32) -> Result<usize, JsValue> {
+        let vertex = self.require_physics_vertex(vertex_id)?;
+        Ok(self.components.vertex_labels[vertex])
+    }
+
+    /// Sets the spring stiffness and damping of one island, so that for
+    /// example the eyes can stay rigid while the skin stays soft.
+    ///
+    /// # Arguments
+    ///
+    /// * `island` - The island, as numbered by `islands`.
+    /// * `stiffness` - The new stiffness of its springs.
+    /// * `damping` - The new damping factor of its springs.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` that fails if the island does not exist.
+    pub fn set_island_parameters(
+        &mut self,
+        island: usize,
+        stiffness: f32,
+        damping: f32,
+    ) -> Result<(), JsValue> {
+        if island >= self.components.count {
+            return Err(JsValue::from_str(&format!(
+                "island {} does not exist, the mesh has {}",
+                island, self.components.count
+            )));
+        }
+        self.physics
+            .set_island_parameters(&self.components, island, stiffness, damping);
+        Ok(())
+    }
+
+    /// Returns the names of the morph targets of the mesh, in order.
+    pub fn morph_target_names(&self) -> Vec<String> {
+        self.welded
+            .mesh
+            .morph_targets
+            .iter()
+            .map(|target| target.name.clone())
+            .collect()
+    }
+
+    /// Sets the weight of a morph target.
+    ///
+    /// The weighted combination of all targets becomes the rest shape of the
+    /// springs, so the face eases into the expression while the simulation
+    /// keeps responding to gravity and dragging. All weights start at 0.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the morph target.
+    /// * `weight` - The new weight, usually between 0 and 1.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` that fails if the mesh has no morph target with that name.
+    pub fn set_morph_weight(&mut self, name: &str, weight: f32) -> Result<(), JsValue> {
+        let mesh = &self.welded.mesh;
+        let index = mesh
+            .morph_target_index(name)
+            .ok_or_else(|| JsValue::from_str(&format!("unknown morph target \"{}\"", name)))?;
+        self.morph_weights[index] = weight;
+        let rest_shape = mesh.blend_morph_targets(&self.rest_positions, &self.morph_weights);
+        self.physics.set_rest_shape(&rest_shape);
+        Ok(())
+    }
+
+    /// Sets the stiffness and damping of one kind of spring.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - `"structural"` for the springs along the edges, `"shear"`
+    ///   for the springs across pairs of triangles, or `"bending"` for the
+    ///   springs that keep the surface from folding.
+    /// * `stiffness` - The new stiffness of the springs.
+    /// * `damping` - The new damping factor of the springs.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` that fails if the kind is unknown.
+    pub fn set_spring_parameters(
+        &mut self,
+        kind: &str,
+        stiffness: f32,
+        damping: f32,
+    ) -> Result<(), JsValue> {
+        let kind = match kind {
+            "structural" => SpringKind::Structural,
+            "shear" => SpringKind::Shear,
+            "bending" => SpringKind::Bending,
+            _ => {
+                return Err(JsValue::from_str(&format!(
+                    "unknown spring kind \"{}\"",
+                    kind
+                )))
+            }
+        };
+        self.physics.set_spring_parameters(kind, stiffness, damping);
+        Ok(())
+    }
+
+    /// Assigns a material preset to a group of vertices, such as the
+    /// cartilage of the nose. Vertices start out as skin.
+    ///
+    /// This replaces the spring parameters set with `set_spring_parameters`
+    /// and `set_island_parameters` for the whole mesh.
+    ///
+    /// # Arguments
+    ///
+    /// * `vertex_ids` - The vertices to assign the material to.
+    /// * `preset` - `"skin"`, `"cartilage"` or `"lips"`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` that fails if the preset is unknown or a vertex does not
+    /// exist.
+    pub fn set_material(&mut self, vertex_ids: &[u32], preset: &str) -> Result<(), JsValue> {
+        let material = Self::material_preset(preset)?;
+        self.assign_material(vertex_ids, material)
+    }
+
+    /// Assigns a custom material to a group of vertices, so the UI can tweak
+    /// the parameters live.
+    ///
+    /// # Arguments
+    ///
+    /// * `vertex_ids` - The vertices to assign the material to.
+    /// * `stiffness` - The stiffness of the structural springs. Shear and
+    ///   bending springs keep their ratio to it.
+    /// * `damping` - The damping factor of the structural springs.
+    /// * `density` - The mass of a vertex with the average area around it.
+    /// * `plasticity` - How fast, per second, springs stretched beyond
+    ///   `yield_strain` take their stretched length as their rest length.
+    /// * `yield_strain` - The relative stretch beyond which the material
+    ///   deforms permanently.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` that fails if a vertex does not exist.
+    pub fn set_material_parameters(
+        &mut self,
+        vertex_ids: &[u32],
+        stiffness: f32,
+        damping: f32,
+        density: f32,
+        plasticity: f32,
+        yield_strain: f32,
+    ) -> Result<(), JsValue> {
+        let material = Material {
+            stiffness,
+            damping,
+            density,
+            plasticity,
+            yield_strain,
+        };
+        self.assign_material(vertex_ids, material)
+    }
+
+    /// Blends a material preset into the material of every vertex by a
+    /// painted weight, for soft transitions between regions.
+    ///
+    /// # Arguments
+    ///
+    /// * `preset` - `"skin"`, `"cartilage"` or `"lips"`.
+    /// * `weights` - How much of the preset each vertex takes, from 0 to 1,
+    ///   one value per vertex.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` that fails if the preset is unknown or there is not one
+    /// weight per vertex.
+    pub fn paint_material(&mut self, preset: &str, weights: &[f32]) -> Result<(), JsValue> {
+        let material = Self::material_preset(preset)?;
+        if weights.len() != self.welded.remap.len() {
+            return Err(JsValue::from_str(&format!(
+                "expected {} weights, got {}",
+                self.welded.remap.len(),
+                weights.len()
+            )));
+        }
+        let weights: Vec<f32> = self
+            .render_vertices
+            .iter()
+            .map(|&render| weights[render as usize])
+            .collect();
+        self.physics
+            .paint_material(&mut self.welded.mesh, material, &weights);
+        Ok(())
+    }
+
+    /// Chooses how the simulation keeps the mesh in shape.
+    ///
+    /// # Arguments
+    ///
+    /// * `solver` - `"springs"` for spring forces, or `"xpbd"` for
+    ///   position-based constraints, which stay stable when a stalled frame
+    ///   passes a large `dt` to `tick`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` that fails if the solver is unknown.
+    pub fn set_solver(&mut self, solver: &str) -> Result<(), JsValue> {
+        match solver {
+            "springs" => self.physics.solver = Solver::Springs,
+            "xpbd" => {
+                let mesh = self.rest_mesh();
+                self.physics
+                    .init_constraints(&mesh, DISTANCE_COMPLIANCE, BENDING_COMPLIANCE);
+                let rest_shape =
+                    mesh.blend_morph_targets(&self.rest_positions, &self.morph_weights);
+                self.physics.set_rest_shape(&rest_shape);
+            }
+            _ => {
+                return Err(JsValue::from_str(&format!(
+                    "unknown solver \"{}\", expected \"springs\" or \"xpbd\"",
+                    solver
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    /// Chooses how the simulation moves the vertices under the forces.
+    ///
+    /// # Arguments
+    ///
+    /// * `integrator` - One of `"verlet"`, `"semi_implicit_euler"`, `"rk4"` or
+    ///   `"implicit_euler"`. The implicit scheme is the most stable with stiff
+    ///   springs and large time steps, and RK4 the most accurate.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` that fails if the integrator is unknown.
+    pub fn set_integrator(&mut self, integrator: &str) -> Result<(), JsValue> {
+        self.physics.integrator = match integrator {
+            "verlet" => Box::new(Verlet),
+            "semi_implicit_euler" => Box::new(SemiImplicitEuler),
+            "rk4" => Box::new(Rk4),
+            "implicit_euler" => Box::new(ImplicitEuler::default()),
+            _ => {
+                return Err(JsValue::from_str(&format!(
+                    "unknown integrator \"{}\"",
+                    integrator
+                )))
+            }
+        };
+        Ok(())
+    }
+
+    /// Sets the region that is dragged along with the grabbed vertex.
+    ///
+    /// Vertices within `radius` of the grabbed vertex, measured along the
+    /// surface, follow the cursor with a weight that falls off towards the
+    /// edge of the region. A radius of zero drags a single vertex. The new
+    /// region is used from the next `on_mouse_down`.
+    ///
+    /// # Arguments
+    ///
+    /// * `radius` - The geodesic radius of the region.
+    /// * `falloff` - One of `"constant"`, `"linear"`, `"smooth"` or `"gaussian"`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` that fails if the radius is negative or the falloff is unknown.
+    pub fn set_drag_region(&mut self, radius: f32, falloff: &str) -> Result<(), JsValue> {
+        if radius.is_nan() || radius < 0.0 {
+            return Err(JsValue::from_str(&format!(
+                "drag radius must be non-negative, got {}",
+                radius
+            )));
+        }
+        self.drag_falloff = match falloff {
+            "constant" => Falloff::Constant,
+            "linear" => Falloff::Linear,
+            "smooth" => Falloff::Smooth,
+            "gaussian" => Falloff::Gaussian,
+            _ => {
+                return Err(JsValue::from_str(&format!(
+                    "unknown falloff \"{}\"",
+                    falloff
+                )))
+            }
+        };
+        self.drag_radius = radius;
+        Ok(())
+    }
+
+    /// Turns symmetric dragging on or off.
+    ///
+    /// In symmetric mode, dragging a vertex also drags its mirror image
+    /// across the symmetry plane of the mesh (see `symmetry_plane`) by the
+    /// mirrored offset, together with the region around it. Vertices on the
+    /// plane and vertices without a mirror image are dragged alone. The mode
+    /// is used from the next `on_mouse_down`.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether drags are mirrored.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` that fails if the symmetry of the mesh cannot be detected.
+    pub fn set_symmetric_drag(&mut self, enabled: bool) -> Result<(), JsValue> {
+        if enabled {
+            self.mirror()?;
+        }
+        self.symmetric_drag = enabled;
+        Ok(())
+    }
+
+    /// Detects the plane the mesh is most nearly mirror symmetric across,
+    /// such as the sagittal plane of a face, in its rest shape.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing an object `{ normal, offset, error }` with the
+    /// unit `normal` of the plane as `[x, y, z]`, its `offset` from the origin
+    /// along the normal, and the RMS distance between the mirrored mesh and
+    /// the mesh. Fails if the mesh has no vertices.
+    pub fn symmetry_plane(&mut self) -> Result<JsValue, JsValue> {
+        let symmetry = self.mirror()?.symmetry;
+        Ok(to_js_object(&SymmetryInfo {
+            normal: symmetry.plane.normal.into(),
+            offset: symmetry.plane.offset,
+            error: symmetry.error,
+        }))
+    }
+
+    /// Measures how far the mesh is deformed from its rest shape, the shape
+    /// it was loaded with before any morph target was applied.
+    ///
+    /// # Returns
+    ///
+    /// An object `{ max_displacement, rms_displacement, hausdorff_distance,
+    /// max_stretch, max_compression, max_area_change }`. The strains are
+    /// Green strains of the triangles, so 0 means no change, and the
+    /// compression is negative.
+    pub fn diff_report(&self) -> JsValue {
+        let diff = self.rest_diff();
+        let strain =
+            |pick: fn(f32, f32) -> f32| diff.triangle_strain.iter().copied().fold(0.0, pick);
+        to_js_object(&DiffInfo {
+            max_displacement: diff.max_displacement,
+            rms_displacement: diff.rms_displacement,
+            hausdorff_distance: diff.hausdorff_distance,
+            max_stretch: strain(f32::max),
+            max_compression: strain(f32::min),
+            max_area_change: diff.area_change.iter().fold(0.0, |max, a| a.abs().max(max)),
+        })
+    }
+
+    /// Returns how far each vertex moved from its rest shape, scaled for
+    /// display as a heatmap.
+    ///
+    /// # Arguments
+    ///
+    /// * `max` - The displacement that maps to 1; larger displacements are
+    ///   clamped. Pass 0 to map the largest displacement to 1.
+    ///
+    /// # Returns
+    ///
+    /// One value between 0 and 1 per vertex.
+    pub fn displacement_heatmap(&self, max: f32) -> Vec<f32> {
+        self.welded.gather(&self.rest_diff().heatmap(max))
     }
 
-    /// Handles the mouse down event, starting a drag operation on a vertex.
+    /// Handles the mouse down event, starting a drag operation on a vertex
+    /// and the region around it (see `set_drag_region`).
     ///
     /// # Arguments
     ///
-    /// * `vertex_id` - The ID of the vertex to drag.
+    /// * `vertex_id` - The ID of the vertex to drag. Nothing happens if it is
+    ///   out of range.
     /// * `x` - The new x-coordinate of the vertex.
     /// * `y` - The new y-coordinate of the vertex.
     /// * `z` - The new z-coordinate of the vertex.
     pub fn on_mouse_down(&mut self, vertex_id: u32, x: f32, y: f32, z: f32) {
-        self.dragged_vertex_index = Some(vertex_id);
-        self.mesh.vertices[vertex_id as usize].position.x = x;
-        self.mesh.vertices[vertex_id as usize].position.y = y;
-        self.mesh.vertices[vertex_id as usize].position.z = z;
+        let Some(vertex) = self.physics_vertex(vertex_id) else {
+            return;
+        };
+        let mesh = &self.welded.mesh;
+        let region = Region::new(mesh, vertex, self.drag_radius, self.drag_falloff);
+        let mirror = match (&self.mirror, self.symmetric_drag) {
+            (Some(Mirror { symmetry, map }), true) => {
+                map[vertex].filter(|&image| image != vertex).map(|image| {
+                    let region = Region::new(mesh, image, self.drag_radius, self.drag_falloff);
+                    (symmetry.plane, region)
+                })
+            }
+            _ => None,
+        };
+        let anchor = mesh.positions[vertex];
+        self.drag = Some(Drag {
+            vertex,
+            anchor,
+            offset: Vector3::new(x, y, z) - anchor,
+            region,
+            mirror,
+        });
+        self.apply_drag();
     }
 
-    /// Handles the mouse move event, updating the position of the dragged vertex.
+    /// Handles the mouse move event, moving the dragged vertex and its region.
     ///
     /// # Arguments
     ///
@@ -101,25 +1127,47 @@ impl FaceController {
     /// * `y` - The new y-coordinate of the vertex.
     /// * `z` - The new z-coordinate of the vertex.
     pub fn on_mouse_move(&mut self, x: f32, y: f32, z: f32) {
-        if let Some(vertex_id) = self.dragged_vertex_index {
-            self.mesh.vertices[vertex_id as usize].position.x = x;
-            self.mesh.vertices[vertex_id as usize].position.y = y;
-            self.mesh.vertices[vertex_id as usize].position.z = z;
+        if let Some(drag) = &mut self.drag {
+            drag.offset = Vector3::new(x, y, z) - drag.anchor;
+            self.apply_drag();
         }
     }
 
     /// Handles the mouse up event, ending the drag operation.
     pub fn on_mouse_up(&mut self) {
-        self.dragged_vertex_index = None;
+        self.drag = None;
     }
 
     /// Returns a pointer to the vertex buffer.
     ///
+    /// When no vertices were welded and render interpolation is off, this
+    /// points straight into the position storage of the simulation, so no
+    /// copy is made per frame.
+    ///
     /// # Returns
     ///
     /// A raw pointer to the vertex buffer.
     pub fn get_vertex_buffer_ptr(&self) -> *const f32 {
-        self.vertex_positions.as_ptr()
+        if self.identity_weld && !self.interpolate {
+            self.welded.mesh.positions_flat().as_ptr()
+        } else {
+            self.vertex_positions.as_ptr()
+        }
+    }
+
+    /// Returns a pointer to the vertex normal buffer.
+    ///
+    /// The buffer has the same layout and length as the vertex buffer.
+    ///
+    /// # Returns
+    ///
+    /// A raw pointer to the vertex normal buffer.
+    pub fn get_normal_buffer_ptr(&self) -> *const f32 {
+        if self.identity_weld {
+            self.welded.mesh.normals_flat().as_ptr()
+        } else {
+            self.vertex_normals.as_ptr()
+        }
     }
 
     /// Returns the number of vertices in the mesh.
@@ -128,7 +1176,7 @@ impl FaceController {
     ///
     /// The number of vertices.
     pub fn get_vertex_count(&self) -> usize {
-        self.mesh.vertices.len()
+        self.welded.remap.len()
     }
 }
 
@@ -168,5 +1216,3 @@ pub fn apply_grayscale(image_bytes: &[u8]) -> Result<Vec<u8>, JsValue> {
 pub fn apply_sepia(image_bytes: &[u8]) -> Result<Vec<u8>, JsValue> {
     image_processing::apply_sepia(image_bytes)
 }
-
-
@@ -0,0 +1,364 @@
+#![allow(dead_code)]
+
+use wasm_app::{apply_grayscale, apply_sepia, FaceController};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn get_vertex_positions(controller: &FaceController, num_vertices: usize) -> Vec<f32> {
+    let ptr = controller.get_vertex_buffer_ptr();
+    let slice = unsafe { std::slice::from_raw_parts(ptr, num_vertices * 3) };
+    slice.to_vec()
+}
+
+#[wasm_bindgen_test(unsupported = test)]
+fn test_face_controller_new() {
+    let positions = vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0];
+    let indices = vec![0, 1, 2, 0, 2, 3];
+    let controller = FaceController::new(&positions, &indices).unwrap();
+    assert!(!controller.get_vertex_buffer_ptr().is_null());
+    assert_eq!(controller.get_vertex_count(), 4);
+}
+
+#[wasm_bindgen_test(unsupported = test)]
+fn test_tick() {
+    let positions = vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0];
+    let indices = vec![0, 1, 2, 0, 2, 3];
+    let mut controller = FaceController::new(&positions, &indices).unwrap();
+
+    let initial_positions = get_vertex_positions(&controller, 4);
+    // Long enough for at least one step of the default 60 Hz clock.
+    controller.tick(1.0 / 30.0);
+    let new_positions = get_vertex_positions(&controller, 4);
+
+    assert_ne!(initial_positions, new_positions);
+}
+
+#[wasm_bindgen_test(unsupported = test)]
+fn test_tick_carries_over_partial_steps() {
+    let positions = vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0];
+    let indices = vec![0, 1, 2, 0, 2, 3];
+    let mut controller = FaceController::new(&positions, &indices).unwrap();
+    let mut reference = FaceController::new(&positions, &indices).unwrap();
+
+    // A frame shorter than the 1/60 s step runs no step.
+    let initial_positions = get_vertex_positions(&controller, 4);
+    controller.tick(0.016);
+    assert_eq!(get_vertex_positions(&controller, 4), initial_positions);
+
+    // The leftover time completes one step with the next frame, and only one.
+    controller.tick(0.016);
+    reference.tick(1.0 / 60.0);
+    assert_ne!(get_vertex_positions(&controller, 4), initial_positions);
+    assert_eq!(
+        get_vertex_positions(&controller, 4),
+        get_vertex_positions(&reference, 4)
+    );
+}
+
+#[wasm_bindgen_test(unsupported = test)]
+fn test_tick_is_independent_of_frame_rate() {
+    let positions = vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0];
+    let indices = vec![0, 1, 2, 0, 2, 3];
+    let run = |frame_rate: u32| {
+        let mut controller = FaceController::new(&positions, &indices).unwrap();
+        for _ in 0..frame_rate / 2 {
+            controller.tick(1.0 / frame_rate as f32);
+        }
+        // Half a step more, so rounding in the frame times cannot change
+        // the number of steps taken.
+        controller.tick(1.0 / 120.0);
+        get_vertex_positions(&controller, 4)
+    };
+    let reference = run(30);
+    assert_ne!(reference, positions);
+    assert_eq!(run(144), reference);
+    assert_eq!(run(60), reference);
+}
+
+#[wasm_bindgen_test(unsupported = test)]
+fn test_mouse_interaction() {
+    let positions = vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0];
+    let indices = vec![0, 1, 2, 0, 2, 3];
+    let mut controller = FaceController::new(&positions, &indices).unwrap();
+
+    controller.on_mouse_down(0, 1.0, 2.0, 3.0);
+    controller.on_mouse_move(4.0, 5.0, 6.0);
+
+    // The vertex position is not updated in the buffer until after the tick
+    controller.tick(0.016);
+    let positions_after_move = get_vertex_positions(&controller, 4);
+    assert!((positions_after_move[0] - 4.0).abs() < 1e-3);
+    assert!((positions_after_move[1] - 5.0).abs() < 1e-3);
+    assert!((positions_after_move[2] - 6.0).abs() < 1e-3);
+
+    controller.on_mouse_up();
+    controller.tick(1.0 / 30.0);
+    let positions_after_mouseup = get_vertex_positions(&controller, 4);
+    assert_ne!(positions_after_mouseup[0], 4.0);
+}
+
+#[wasm_bindgen_test]
+fn test_pick_maps_welded_vertices_to_render_vertices() {
+    // A unit square whose two triangles do not share vertices, as at a UV
+    // seam. Render vertices 3 and 4 duplicate 0 and 2, so the simulation
+    // welds them and has four vertices where the renderer has six.
+    let positions = vec![
+        0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, //
+        0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0,
+    ];
+    let indices = vec![0, 1, 2, 3, 4, 5];
+    let controller = FaceController::new(&positions, &indices).unwrap();
+    assert_eq!(controller.get_vertex_count(), 6);
+
+    let pick = |x: f32, y: f32| {
+        let hit = controller.pick(&[x, y, 1.0], &[0.0, 0.0, -1.0]).unwrap();
+        let get = |key: &str| js_sys::Reflect::get(&hit, &JsValue::from_str(key)).unwrap();
+        let vertex = get("vertex").as_f64().unwrap() as u32;
+        let point: Vec<f32> = js_sys::Array::from(&get("point"))
+            .iter()
+            .map(|c| c.as_f64().unwrap() as f32)
+            .collect();
+        let distance = get("distance").as_f64().unwrap() as f32;
+        (vertex, point, distance)
+    };
+
+    // Near the corner only the second triangle has: the fourth welded
+    // vertex, which is render vertex 5.
+    let (vertex, point, distance) = pick(0.1, 0.8);
+    assert_eq!(vertex, 5);
+    assert_eq!(point, vec![0.1, 0.8, 0.0]);
+    assert_eq!(distance, 1.0);
+
+    // Near the seam corner from either triangle: a render vertex at that
+    // corner, whichever of the duplicates it is.
+    for (x, y) in [(0.15, 0.1), (0.1, 0.15)] {
+        let (vertex, point, _) = pick(x, y);
+        assert!(vertex == 0 || vertex == 3, "picked vertex {}", vertex);
+        assert_eq!(point, vec![x, y, 0.0]);
+    }
+
+    assert!(controller
+        .pick(&[5.0, 5.0, 1.0], &[0.0, 0.0, -1.0])
+        .unwrap()
+        .is_undefined());
+}
+
+#[wasm_bindgen_test]
+fn test_face_controller_new_invalid_input() {
+    let positions = vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0]; // Invalid length
+    let indices = vec![0, 1, 2, 0, 2, 3];
+    assert!(FaceController::new(&positions, &indices).is_err());
+}
+
+/// A strip of 4 x 1 unit squares in the xy-plane, with vertex `row * 5 +
+/// column` at `(column, row, height(column, row))`. The diagonals are
+/// mirrored across the middle column, so the strip is symmetric across the
+/// plane x = 2 when `height` is.
+fn strip(height: fn(f32, f32) -> f32) -> FaceController {
+    let positions: Vec<f32> = (0..10)
+        .flat_map(|v| {
+            let (x, y) = ((v % 5) as f32, (v / 5) as f32);
+            [x, y, height(x, y)]
+        })
+        .collect();
+    let mut indices = Vec::new();
+    for v in 0..4 {
+        if v < 2 {
+            indices.extend([v, v + 1, v + 6, v, v + 6, v + 5]);
+        } else {
+            indices.extend([v, v + 1, v + 5, v + 1, v + 6, v + 5]);
+        }
+    }
+    FaceController::new(&positions, &indices).unwrap()
+}
+
+fn vertex(positions: &[f32], v: usize) -> [f32; 3] {
+    [positions[v * 3], positions[v * 3 + 1], positions[v * 3 + 2]]
+}
+
+#[wasm_bindgen_test(unsupported = test)]
+fn test_drag_without_region_moves_only_the_picked_vertex() {
+    let mut controller = strip(|_, _| 0.0);
+    controller.set_drag_region(0.0, "linear").unwrap();
+    let initial = get_vertex_positions(&controller, 10);
+
+    // The mesh is not welded, so the buffer shows the drag right away.
+    controller.on_mouse_down(6, 1.0, 1.0, 1.0);
+    let dragged = get_vertex_positions(&controller, 10);
+    assert_eq!(vertex(&dragged, 6), [1.0, 1.0, 1.0]);
+    for v in (0..10).filter(|&v| v != 6) {
+        assert_eq!(vertex(&dragged, v), vertex(&initial, v));
+    }
+
+    controller.on_mouse_move(1.0, 2.0, 0.5);
+    let moved = get_vertex_positions(&controller, 10);
+    assert_eq!(vertex(&moved, 6), [1.0, 2.0, 0.5]);
+    assert_eq!(vertex(&moved, 5), vertex(&initial, 5));
+}
+
+#[wasm_bindgen_test(unsupported = test)]
+fn test_drag_region_moves_neighbours_by_falloff_weight() {
+    let mut controller = strip(|_, _| 0.0);
+    controller.set_drag_region(2.0, "linear").unwrap();
+    controller.on_mouse_down(0, 0.0, 0.0, 1.0);
+    let positions = get_vertex_positions(&controller, 10);
+
+    // Each vertex moves by 1 - distance / radius of the offset.
+    assert_eq!(vertex(&positions, 0), [0.0, 0.0, 1.0]);
+    assert_eq!(vertex(&positions, 1), [1.0, 0.0, 0.5]);
+    assert_eq!(vertex(&positions, 5), [0.0, 1.0, 0.5]);
+    let diagonal = 1.0 - 2.0f32.sqrt() / 2.0;
+    assert!((positions[6 * 3 + 2] - diagonal).abs() < 1e-5);
+    for v in [2, 3, 4, 7, 8, 9] {
+        assert_eq!(positions[v * 3 + 2], 0.0, "vertex {} moved", v);
+    }
+
+    // A constant falloff moves the whole region by the full offset.
+    let mut controller = strip(|_, _| 0.0);
+    controller.set_drag_region(1.0, "constant").unwrap();
+    controller.on_mouse_down(0, 0.0, 0.0, 1.0);
+    let positions = get_vertex_positions(&controller, 10);
+    for v in [0, 1, 5] {
+        assert_eq!(positions[v * 3 + 2], 1.0, "vertex {} did not move", v);
+    }
+    assert_eq!(positions[6 * 3 + 2], 0.0);
+}
+
+#[wasm_bindgen_test]
+fn test_set_drag_region_rejects_invalid_arguments() {
+    let mut controller = strip(|_, _| 0.0);
+    for falloff in ["constant", "linear", "smooth", "gaussian"] {
+        assert!(controller.set_drag_region(1.0, falloff).is_ok());
+    }
+    assert!(controller.set_drag_region(1.0, "cubic").is_err());
+    assert!(controller.set_drag_region(-1.0, "linear").is_err());
+    assert!(controller.set_drag_region(f32::NAN, "linear").is_err());
+}
+
+#[wasm_bindgen_test(unsupported = test)]
+fn test_symmetric_drag_moves_mirror_vertex_by_reflected_offset() {
+    // Curved across x = 2 and sloped along y, so x = 2 is the only plane
+    // of symmetry.
+    let height = |x: f32, y: f32| 0.3 * (x - 2.0) * (x - 2.0) + 0.5 * y;
+    let mut controller = strip(height);
+    controller.set_symmetric_drag(true).unwrap();
+    let initial = get_vertex_positions(&controller, 10);
+
+    // Vertex 0 at x = 0 mirrors to vertex 4 at x = 4.
+    controller.on_mouse_down(0, 0.2, 0.1, 1.5);
+    let positions = get_vertex_positions(&controller, 10);
+    let close = |a: [f32; 3], b: [f32; 3]| (0..3).all(|i| (a[i] - b[i]).abs() < 1e-4);
+    assert!(close(vertex(&positions, 0), [0.2, 0.1, 1.5]));
+    assert!(close(vertex(&positions, 4), [3.8, 0.1, 1.5]));
+    for v in [1, 2, 3, 5, 6, 7, 8, 9] {
+        assert_eq!(vertex(&positions, v), vertex(&initial, v));
+    }
+
+    controller.on_mouse_move(-0.5, 0.0, 1.2);
+    let positions = get_vertex_positions(&controller, 10);
+    assert!(close(vertex(&positions, 4), [4.5, 0.0, 1.2]));
+    controller.on_mouse_up();
+
+    // A vertex on the plane is its own mirror image, so it is dragged alone
+    // by the unreflected offset.
+    let mut controller = strip(height);
+    controller.set_symmetric_drag(true).unwrap();
+    controller.on_mouse_down(7, 2.3, 1.0, 0.5);
+    let positions = get_vertex_positions(&controller, 10);
+    assert!(close(vertex(&positions, 7), [2.3, 1.0, 0.5]));
+    for v in (0..10).filter(|&v| v != 7) {
+        assert_eq!(vertex(&positions, v), vertex(&initial, v));
+    }
+
+    // Without symmetric dragging the mirror vertex stays put.
+    let mut controller = strip(height);
+    controller.on_mouse_down(0, 0.2, 0.1, 1.5);
+    let positions = get_vertex_positions(&controller, 10);
+    assert_eq!(vertex(&positions, 4), vertex(&initial, 4));
+}
+
+#[wasm_bindgen_test(unsupported = test)]
+fn test_smooth_region_ignores_out_of_range_vertices() {
+    // A pyramid whose apex is its only interior vertex, so smoothing moves
+    // nothing else.
+    let positions = vec![
+        0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, -1.0, 0.0, 0.0, 0.0, -1.0, 0.0,
+    ];
+    let indices = vec![0, 1, 2, 0, 2, 3, 0, 3, 4, 0, 4, 1];
+    let mut controller = FaceController::new(&positions, &indices).unwrap();
+    controller.smooth_region(&[0, 5, u32::MAX], 1);
+    let smoothed = get_vertex_positions(&controller, 5);
+    assert!(smoothed[2] < 1.0);
+    assert_eq!(smoothed[3..], positions[3..]);
+
+    // Dragging a vertex that does not exist does nothing.
+    controller.on_mouse_down(5, 1.0, 1.0, 1.0);
+    controller.on_mouse_move(2.0, 2.0, 2.0);
+    assert_eq!(get_vertex_positions(&controller, 5), smoothed);
+}
+
+#[wasm_bindgen_test(unsupported = test)]
+fn test_island_of() {
+    // Two triangles that share no vertices.
+    let positions = vec![
+        0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, //
+        5.0, 0.0, 0.0, 6.0, 0.0, 0.0, 5.0, 1.0, 0.0,
+    ];
+    let indices = vec![0, 1, 2, 3, 4, 5];
+    let controller = FaceController::new(&positions, &indices).unwrap();
+    assert_eq!(controller.island_of(1).unwrap(), 0);
+    assert_eq!(controller.island_of(5).unwrap(), 1);
+}
+
+#[wasm_bindgen_test]
+fn test_island_of_rejects_missing_vertex() {
+    let positions = vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+    let controller = FaceController::new(&positions, &[0, 1, 2]).unwrap();
+    assert!(controller.island_of(3).is_err());
+}
+
+#[wasm_bindgen_test]
+fn test_set_material_rejects_missing_vertices() {
+    let mut controller = strip(|_, _| 0.0);
+    assert!(controller.set_material(&[0, 9], "cartilage").is_ok());
+    assert!(controller.set_material(&[0, 10], "cartilage").is_err());
+    assert!(controller
+        .set_material_parameters(&[u32::MAX], 100.0, 1.0, 1.0, 0.0, 0.5)
+        .is_err());
+    assert!(controller.set_material(&[0], "bone").is_err());
+}
+
+// --- Image Processing Tests ---
+
+// A tiny 1x1 valid PNG, solid red.
+const TEST_PNG_BYTES: &[u8] = &[
+    137, 80, 78, 71, 13, 10, 26, 10, 0, 0, 0, 13, 73, 72, 68, 82, 0, 0, 0, 1, 0, 0, 0, 1, 8, 2, 0,
+    0, 0, 144, 119, 83, 222, 0, 0, 0, 12, 73, 68, 65, 84, 24, 87, 99, 248, 207, 192, 0, 0, 3, 1, 1,
+    0, 29, 122, 18, 16, 0, 0, 0, 0, 73, 69, 78, 68, 174, 66, 96, 130,
+];
+
+#[wasm_bindgen_test]
+fn test_apply_grayscale_wasm() {
+    let result = apply_grayscale(TEST_PNG_BYTES);
+    assert!(result.is_ok());
+    let pixel_data = result.unwrap();
+    // 1x1 image, Photon returns RGB, so 3 bytes.
+    assert_eq!(pixel_data.len(), 3);
+}
+
+#[wasm_bindgen_test]
+fn test_apply_sepia_wasm() {
+    let result = apply_sepia(TEST_PNG_BYTES);
+    assert!(result.is_ok());
+    let pixel_data = result.unwrap();
+    assert_eq!(pixel_data.len(), 3);
+}
+
+#[wasm_bindgen_test]
+fn test_invalid_image_bytes_wasm() {
+    let invalid_bytes = &[1, 2, 3, 4];
+    let result = apply_grayscale(invalid_bytes);
+    assert!(result.is_err());
+}
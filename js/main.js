@@ -70,14 +70,25 @@ function main(wasm) {
     faceMesh = model.children[0];
     scene.add(model);
 
-    const positions = faceMesh.geometry.attributes.position.array;
-    const indices = faceMesh.geometry.index.array;
-
-    console.log('JS vertex count:', positions.length / 3);
-    console.log('JS index count:', indices.length);
-
-    faceController = new FaceController(positions, indices);
-    console.log('Rust vertex count:', faceController.get_vertex_count());
+    console.log('JS vertex count:', faceMesh.geometry.attributes.position.count);
+
+    // Build the simulation mesh from the same file with the Rust loader, so the
+    // physics does not depend on how Three.js unpacked the geometry.
+    fetch('assets/face.gltf')
+      .then((response) => {
+        if (!response.ok) {
+          throw new Error(`failed to load assets/face.gltf: ${response.status} ${response.statusText}`);
+        }
+        return response.arrayBuffer();
+      })
+      .then((buffer) => {
+        faceController = FaceController.from_gltf(new Uint8Array(buffer));
+        console.log('Rust vertex count:', faceController.get_vertex_count());
+      })
+      .catch((error) => {
+        console.error('Error loading the simulation mesh:', error);
+        alert(`Error loading the simulation mesh: ${error.message ?? error}`);
+      });
   });
 
   const raycaster = new THREE.Raycaster();
@@ -88,7 +99,7 @@ function main(wasm) {
    * @param {MouseEvent} event - The mouse event.
    */
   function onMouseDown(event) {
-    if (!faceMesh) return;
+    if (!faceMesh || !faceController) return;
 
     mouse.x = (event.clientX / window.innerWidth) * 2 - 1;
     mouse.y = - (event.clientY / window.innerHeight) * 2 + 1;
@@ -105,41 +116,39 @@ function main(wasm) {
         return;
     }
 
-    const intersects = raycaster.intersectObject(faceMesh);
+    const hit = pick(raycaster.ray);
 
-    if (intersects.length > 0) {
-      const intersection = intersects[0];
-      const face = intersection.face;
-      const point = intersection.point;
-
-      let closestVertexIndex = -1;
-      let minDistance = Infinity;
-
-      const vertices = faceMesh.geometry.attributes.position;
-      const faceIndices = [face.a, face.b, face.c];
-      for (const vertexIndex of faceIndices) {
-        const vertex = new THREE.Vector3().fromBufferAttribute(vertices, vertexIndex);
-        const distance = point.distanceTo(vertex);
-        if (distance < minDistance) {
-          minDistance = distance;
-          closestVertexIndex = vertexIndex;
-        }
-      }
-
-      if (closestVertexIndex !== -1) {
-        isDragging = true;
-        controls.enabled = false;
-        faceController.on_mouse_down(closestVertexIndex, point.x, point.y, point.z);
-      }
+    if (hit) {
+      const [x, y, z] = hit.point;
+      isDragging = true;
+      controls.enabled = false;
+      faceController.on_mouse_down(hit.vertex, x, y, z);
     }
   }
 
+  /**
+   * Finds the vertex under a ray using the simulation's own picking, which
+   * follows the deformed surface.
+   * @param {THREE.Ray} ray The ray to cast.
+   * @returns {{vertex: number, point: number[], distance: number} | undefined}
+   *   The picked vertex, or undefined if the ray misses the mesh or the
+   *   simulation has not loaded yet.
+   */
+  function pick(ray) {
+    if (!faceController) return;
+    const { origin, direction } = ray;
+    return faceController.pick(
+      new Float32Array([origin.x, origin.y, origin.z]),
+      new Float32Array([direction.x, direction.y, direction.z])
+    );
+  }
+
   /**
    * Handles the mouse move event to update the position of a dragged vertex.
    * @param {MouseEvent} event - The mouse event.
    */
   function onMouseMove(event) {
-    if (!isDragging || !faceMesh) return;
+    if (!isDragging || !faceMesh || !faceController) return;
 
     mouse.x = (event.clientX / window.innerWidth) * 2 - 1;
     mouse.y = - (event.clientY / window.innerHeight) * 2 + 1;
@@ -244,6 +253,7 @@ function main(wasm) {
       const imageUrl = URL.createObjectURL(file);
       const image = new Image();
       image.onload = () => {
+        if (!faceController) return;
         faces.forEach(face => {
           const { x1, y1, x2, y2 } = face;
 
@@ -262,38 +272,19 @@ function main(wasm) {
             );
 
             raycaster.setFromCamera(ndc, camera);
-            const intersects = raycaster.intersectObject(faceMesh);
-
-            if (intersects.length > 0) {
-              const intersection = intersects[0];
-              const point = intersection.point;
-
-              let closestVertexIndex = -1;
-              let minDistance = Infinity;
+            const hit = pick(raycaster.ray);
 
+            if (hit) {
+              const closestVertexIndex = hit.vertex;
               const vertices = faceMesh.geometry.attributes.position;
-              const face = intersection.face;
-              const faceIndices = [face.a, face.b, face.c];
-
-              for (const vertexIndex of faceIndices) {
-                const vertex = new THREE.Vector3().fromBufferAttribute(vertices, vertexIndex);
-                const distance = point.distanceTo(vertex);
-                if (distance < minDistance) {
-                  minDistance = distance;
-                  closestVertexIndex = vertexIndex;
-                }
-              }
-
-              if (closestVertexIndex !== -1) {
-                const vertex = new THREE.Vector3().fromBufferAttribute(vertices, closestVertexIndex);
-                const sphereGeometry = new THREE.SphereGeometry(0.01, 32, 32);
-                const sphereMaterial = new THREE.MeshBasicMaterial({ color: 0xff0000 });
-                const sphere = new THREE.Mesh(sphereGeometry, sphereMaterial);
-                sphere.position.copy(vertex);
-                sphere.userData.vertexIndex = closestVertexIndex;
-                handles.push(sphere);
-                handlesGroup.add(sphere);
-              }
+              const vertex = new THREE.Vector3().fromBufferAttribute(vertices, closestVertexIndex);
+              const sphereGeometry = new THREE.SphereGeometry(0.01, 32, 32);
+              const sphereMaterial = new THREE.MeshBasicMaterial({ color: 0xff0000 });
+              const sphere = new THREE.Mesh(sphereGeometry, sphereMaterial);
+              sphere.position.copy(vertex);
+              sphere.userData.vertexIndex = closestVertexIndex;
+              handles.push(sphere);
+              handlesGroup.add(sphere);
             }
           });
         });
@@ -347,6 +338,15 @@ function main(wasm) {
 
       faceMesh.geometry.attributes.position.array.set(wasmVertexBuffer);
       faceMesh.geometry.attributes.position.needsUpdate = true;
+
+      const geometry = faceMesh.geometry;
+      const normalBufferPtr = faceController.get_normal_buffer_ptr();
+      const wasmNormalBuffer = new Float32Array(wasm.memory.buffer, normalBufferPtr, vertexCount * 3);
+      if (!geometry.attributes.normal) {
+        geometry.setAttribute('normal', new THREE.BufferAttribute(new Float32Array(vertexCount * 3), 3));
+      }
+      geometry.attributes.normal.array.set(wasmNormalBuffer);
+      geometry.attributes.normal.needsUpdate = true;
     }
 
     controls.update();